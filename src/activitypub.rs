@@ -0,0 +1,199 @@
+use serde_json::{Value, json};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::config::{ActivityType, Config};
+use crate::{PostMetadata, dates, escape_html, feeds, staging, url_stem};
+
+const CONTEXT: &str = "https://www.w3.org/ns/activitystreams";
+const PUBLIC: &str = "https://www.w3.org/ns/activitystreams#Public";
+
+/// The full URL of the site's actor document.
+pub fn actor_url(config: &Config) -> String {
+    config.absolute_url(&format!("{}/actor.json", config.activitypub.dir))
+}
+
+/// Writes the ActivityPub export of `posts` to `activitypub.dir`: the actor,
+/// its outbox with a `Create` for each post newest first, and the post
+/// objects themselves at `<dir>/<page>.json`. Everything in it links by
+/// full URL, so `base_url` has to be one.
+pub fn write(posts: &[PostMetadata], config: &Config, output_dir: &Path) -> io::Result<()> {
+    if !config.base_url.contains("://") {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "ActivityPub needs base_url to be a full URL, like https://example.com/",
+        ));
+    }
+    let settings = &config.activitypub;
+    let dir = output_dir.join(&settings.dir);
+    fs::create_dir_all(&dir)?;
+    let actor = actor_url(config);
+
+    let listed = match settings.limit {
+        Some(limit) => &posts[..limit.min(posts.len())],
+        None => posts,
+    };
+    let mut activities = Vec::new();
+    for post in listed {
+        let stem = url_stem(&post.url);
+        let relative = format!("{}.json", stem.trim_start_matches('/'));
+        let object_url = config.absolute_url(&format!("{}/{}", settings.dir, relative));
+        let object = object(post, &object_url, &actor, config);
+
+        let path = dir.join(&relative);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&path, to_json(&with_context(object.clone()))?)?;
+
+        activities.push(json!({
+            "id": format!("{}#create", object_url),
+            "type": "Create",
+            "actor": actor,
+            "published": object["published"],
+            "to": [PUBLIC],
+            "object": object,
+        }));
+    }
+
+    let outbox_url = config.absolute_url(&format!("{}/outbox.json", settings.dir));
+    let outbox = json!({
+        "@context": CONTEXT,
+        "id": outbox_url,
+        "type": "OrderedCollection",
+        "totalItems": activities.len(),
+        "orderedItems": activities,
+    });
+    fs::write(dir.join("outbox.json"), to_json(&outbox)?)?;
+
+    // Servers won't take an actor without an inbox, so one that can't
+    // receive anything stands in when there's none elsewhere
+    let inbox = if settings.inbox.is_empty() {
+        let inbox_url = config.absolute_url(&format!("{}/inbox.json", settings.dir));
+        let inbox = json!({
+            "@context": CONTEXT,
+            "id": inbox_url,
+            "type": "OrderedCollection",
+            "totalItems": 0,
+            "orderedItems": [],
+        });
+        fs::write(dir.join("inbox.json"), to_json(&inbox)?)?;
+        inbox_url
+    } else {
+        settings.inbox.clone()
+    };
+    fs::write(
+        dir.join("actor.json"),
+        to_json(&actor_document(&actor, &inbox, &outbox_url, config))?,
+    )?;
+
+    println!(
+        "  🐘 Generated {} ({} posts)",
+        staging::shown(&dir.join("outbox.json")),
+        listed.len()
+    );
+    Ok(())
+}
+
+/// The actor the posts are attributed to, named after the site unless
+/// `activitypub` says otherwise.
+fn actor_document(actor: &str, inbox: &str, outbox: &str, config: &Config) -> Value {
+    let settings = &config.activitypub;
+    let name = if settings.name.is_empty() {
+        &config.title
+    } else {
+        &settings.name
+    };
+    let summary = if settings.summary.is_empty() {
+        &config.description
+    } else {
+        &settings.summary
+    };
+
+    let mut document = json!({
+        "@context": [CONTEXT, "https://w3id.org/security/v1"],
+        "id": actor,
+        "type": "Person",
+        "preferredUsername": settings.username,
+        "name": name,
+        "summary": escape_html(summary),
+        "url": config.absolute_url("/"),
+        "inbox": inbox,
+        "outbox": outbox,
+    });
+    if !settings.icon.is_empty() {
+        document["icon"] = json!({ "type": "Image", "url": settings.icon });
+    }
+    if !settings.public_key.is_empty() {
+        document["publicKey"] = json!({
+            "id": format!("{}#main-key", actor),
+            "owner": actor,
+            "publicKeyPem": settings.public_key,
+        });
+    }
+    document
+}
+
+/// The object standing for `post`: an `Article` with its full content, or a
+/// `Note` with its title, summary and a link to it.
+fn object(post: &PostMetadata, id: &str, actor: &str, config: &Config) -> Value {
+    let url = config.absolute_url(&post.url);
+    let summary = feeds::absolute_links(&post.summary, &post.url, config);
+    let tags: Vec<Value> = post
+        .tags
+        .iter()
+        .map(|tag| json!({ "type": "Hashtag", "name": format!("#{}", tag.replace(' ', "")) }))
+        .collect();
+
+    let mut object = json!({
+        "id": id,
+        "attributedTo": actor,
+        "url": url,
+        "to": [PUBLIC],
+        "tag": tags,
+    });
+    match config.activitypub.object_type {
+        ActivityType::Article => {
+            let content = if post.content.is_empty() {
+                &post.summary
+            } else {
+                &post.content
+            };
+            object["type"] = json!("Article");
+            object["name"] = json!(post.title);
+            object["content"] = json!(feeds::absolute_links(content, &post.url, config));
+            if !summary.is_empty() {
+                object["summary"] = json!(summary);
+            }
+        }
+        ActivityType::Note => {
+            object["type"] = json!("Note");
+            object["content"] = json!(format!(
+                "<p><strong>{}</strong></p>{}<p><a href=\"{}\">{}</a></p>",
+                escape_html(&post.title),
+                summary.trim_end(),
+                escape_html(&url),
+                escape_html(&url)
+            ));
+        }
+    }
+    if let Some(date) = dates::parse(&post.date) {
+        object["published"] = json!(date.to_rfc3339());
+    }
+    object
+}
+
+/// `object` as a document of its own, with the `@context` objects embedded
+/// in an activity leave out.
+fn with_context(object: Value) -> Value {
+    let mut document = json!({ "@context": CONTEXT });
+    if let (Some(document), Value::Object(fields)) = (document.as_object_mut(), object) {
+        document.extend(fields);
+    }
+    document
+}
+
+fn to_json(value: &Value) -> io::Result<String> {
+    serde_json::to_string_pretty(value).map_err(io::Error::other)
+}
@@ -0,0 +1,326 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::config::{Config, ImagesConfig};
+use crate::images::{self, ImageInfo};
+use crate::sync::{self, FileSync};
+use crate::theme;
+use crate::walk::Walk;
+
+/// Maps each static asset's path (relative to `static/`, `/`-separated) to the
+/// path it is published under. Without fingerprinting the two are the same.
+/// Sass sources appear under the name of the stylesheet they compile to.
+#[derive(Debug, Default)]
+pub struct AssetManifest {
+    pub entries: BTreeMap<String, String>,
+    /// Raster images handled by the image pipeline, keyed like `entries`
+    pub images: BTreeMap<String, ImageInfo>,
+    sources: BTreeMap<String, AssetSource>,
+}
+
+#[derive(Debug)]
+enum AssetSource {
+    File(PathBuf),
+    Compiled(String),
+    /// Part of the default theme, see `theme::DEFAULT_ASSETS`
+    Embedded(&'static str),
+}
+
+impl AssetManifest {
+    /// Hashes every asset under `static_dir` whose extension is configured for
+    /// fingerprinting, producing names like `style.3f9a1c2e.css`.
+    ///
+    /// With `sass` enabled, `.scss`/`.sass` files are compiled to CSS here so
+    /// the fingerprint covers the compiled output. Partials (`_name.scss`) are
+    /// only pulled in through imports and never published on their own.
+    ///
+    /// Assets in later `static_dirs` replace those at the same path in
+    /// earlier ones, as the site's own do its theme's. The default theme's
+    /// assets are added for those none of them replace.
+    pub fn build(static_dirs: &[PathBuf], site_config: &Config) -> io::Result<Self> {
+        let mut manifest = AssetManifest::default();
+
+        for static_dir in static_dirs.iter().filter(|dir| dir.is_dir()) {
+            manifest.add_dir(static_dir, site_config)?;
+        }
+
+        for (name, contents) in theme::DEFAULT_ASSETS {
+            if !manifest.entries.contains_key(name) {
+                let extension = name.rsplit_once('.').unwrap_or_default().1;
+                let source = AssetSource::Embedded(contents);
+                manifest.add(name.to_string(), extension, source, site_config)?;
+            }
+        }
+
+        Ok(manifest)
+    }
+
+    /// Adds every asset under `static_dir`.
+    fn add_dir(&mut self, static_dir: &Path, site_config: &Config) -> io::Result<()> {
+        let config = &site_config.assets;
+        let mut walk = Walk::new(static_dir, site_config.follow_symlinks);
+        for file in find_files_in(static_dir, &mut walk)? {
+            let mut relative = file
+                .strip_prefix(static_dir)
+                .unwrap_or(&file)
+                .to_string_lossy()
+                .replace('\\', "/");
+
+            let mut extension = file.extension().and_then(|s| s.to_str()).unwrap_or("");
+            let source = if config.sass && is_sass(&file) {
+                let is_partial = file
+                    .file_name()
+                    .and_then(|s| s.to_str())
+                    .is_some_and(|name| name.starts_with('_'));
+                if is_partial {
+                    continue;
+                }
+
+                relative = format!("{}.css", relative.rsplit_once('.').unwrap_or_default().0);
+                extension = "css";
+                AssetSource::Compiled(compile_sass(&file, static_dir)?)
+            } else {
+                // What replaces an image of the theme needn't be one
+                self.images.remove(&relative);
+                if let Some(info) = images::plan(&file, &relative, &site_config.images)? {
+                    self.images.insert(relative.clone(), info);
+                }
+                AssetSource::File(file.clone())
+            };
+
+            self.add(relative, extension, source, site_config)?;
+        }
+        Ok(())
+    }
+
+    /// Plans the image pipeline's work for `path`, an image kept next to the
+    /// content and published at `relative`, unless a static asset of the
+    /// same name takes its place.
+    pub fn add_content_image(
+        &mut self,
+        path: &Path,
+        relative: &str,
+        config: &ImagesConfig,
+    ) -> io::Result<()> {
+        if self.entries.contains_key(relative) {
+            return Ok(());
+        }
+        if let Some(info) = images::plan(path, relative, config)? {
+            self.images.insert(relative.to_string(), info);
+        }
+        Ok(())
+    }
+
+    /// Adds the asset published as `relative`, fingerprinting its name if
+    /// its extension calls for it.
+    fn add(
+        &mut self,
+        relative: String,
+        extension: &str,
+        source: AssetSource,
+        site_config: &Config,
+    ) -> io::Result<()> {
+        let config = &site_config.assets;
+        let published =
+            if config.fingerprint && config.fingerprint_extensions.iter().any(|e| e == extension) {
+                let contents = match &source {
+                    AssetSource::File(path) => fs::read(path)?,
+                    AssetSource::Compiled(css) => css.clone().into_bytes(),
+                    AssetSource::Embedded(contents) => contents.as_bytes().to_vec(),
+                };
+                fingerprinted_name(&relative, &contents)
+            } else {
+                relative.clone()
+            };
+
+        self.entries.insert(relative.clone(), published);
+        self.sources.insert(relative, source);
+        Ok(())
+    }
+
+    /// The manifest keyed for templates: `css/style.css` becomes `css_style_css`,
+    /// since TinyTemplate paths can't contain dots or slashes.
+    pub fn template_map(&self) -> BTreeMap<String, String> {
+        self.entries
+            .iter()
+            .map(|(original, published)| {
+                let key = original
+                    .chars()
+                    .map(|c| if c.is_alphanumeric() { c } else { '_' })
+                    .collect();
+                (key, published.clone())
+            })
+            .collect()
+    }
+
+    /// Copies every asset into `output_dir` with `sync` (writing compiled
+    /// stylesheets in place of their Sass sources), adds the fingerprinted
+    /// copies next to the originals, and writes an `asset-manifest.json`
+    /// describing the mapping.
+    pub fn publish(&self, output_dir: &Path, sync: &FileSync) -> io::Result<()> {
+        for (original, published) in &self.entries {
+            let dest_path = output_dir.join(original);
+            if let Some(parent) = dest_path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+
+            match &self.sources[original] {
+                AssetSource::File(path) => {
+                    if let Some(info) = self.images.get(original) {
+                        // The pipeline rewrites it, so it can't share its source's data
+                        sync::copy_file(path, &dest_path)?;
+                        println!("  📎 Copied: {}", original);
+                        images::process(path, original, info, output_dir)?;
+                    } else if sync.copy(path, output_dir, original)? {
+                        println!("  📎 Unchanged: {}", original);
+                    } else {
+                        println!("  📎 Copied: {}", original);
+                    }
+                }
+                AssetSource::Compiled(css) => {
+                    sync::unlink(&dest_path)?;
+                    fs::write(&dest_path, css)?;
+                    println!("  🎨 Compiled: {}", original);
+                }
+                AssetSource::Embedded(contents) => {
+                    sync::unlink(&dest_path)?;
+                    fs::write(&dest_path, contents)?;
+                    println!("  📎 Copied: {} (default theme)", original);
+                }
+            }
+
+            if original != published {
+                sync.copy(&dest_path, output_dir, published)?;
+                println!("  🔖 Fingerprinted: {} -> {}", original, published);
+            }
+        }
+
+        if !self.images.is_empty() {
+            let json = serde_json::to_string_pretty(&self.images).map_err(io::Error::other)?;
+            fs::write(output_dir.join("image-manifest.json"), json)?;
+        }
+
+        let json = serde_json::to_string_pretty(&self.entries).map_err(io::Error::other)?;
+        fs::write(output_dir.join("asset-manifest.json"), json)
+    }
+}
+
+fn is_sass(path: &Path) -> bool {
+    matches!(
+        path.extension().and_then(|s| s.to_str()),
+        Some("scss") | Some("sass")
+    )
+}
+
+fn compile_sass(path: &Path, static_dir: &Path) -> io::Result<String> {
+    let options = grass::Options::default().load_path(static_dir);
+    grass::from_path(path, &options).map_err(|e| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{}: {}", path.display(), e),
+        )
+    })
+}
+
+fn fingerprinted_name(relative: &str, contents: &[u8]) -> String {
+    let hash = format!("{:016x}", fnv1a(contents));
+    let hash = &hash[..8];
+
+    let (dir, file) = match relative.rsplit_once('/') {
+        Some((dir, file)) => (format!("{}/", dir), file),
+        None => (String::new(), relative),
+    };
+
+    match file.rsplit_once('.') {
+        Some((stem, ext)) if !stem.is_empty() => format!("{}{}.{}.{}", dir, stem, hash, ext),
+        _ => format!("{}{}.{}", dir, file, hash),
+    }
+}
+
+/// 64-bit FNV-1a: not cryptographic, but stable across builds and platforms,
+/// which is all a cache-busting name needs.
+pub(crate) fn fnv1a(bytes: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in bytes {
+        hash ^= u64::from(*byte);
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+pub(crate) fn find_files(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    find_files_in(dir, &mut Walk::new(dir, true))
+}
+
+/// Every file under `dir`, following symlinked directories as `walk` says.
+fn find_files_in(dir: &Path, walk: &mut Walk) -> io::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+
+        if path.is_dir() {
+            if let Some(found) = walk.descend(&path, |walk| find_files_in(&path, walk))? {
+                files.extend(found);
+            }
+        } else {
+            files.push(path);
+        }
+    }
+
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::scratch_dir;
+
+    #[test]
+    fn publishing_downscaled_images_leaves_their_sources_alone() {
+        let dir = scratch_dir("assets-images");
+        let (static_dir, output) = (dir.join("static"), dir.join("output"));
+        fs::create_dir_all(&static_dir).unwrap();
+        fs::create_dir_all(&output).unwrap();
+        image::RgbImage::new(40, 2)
+            .save(static_dir.join("photo.png"))
+            .unwrap();
+
+        let mut config = Config::default();
+        config.assets.hard_links = true;
+        config.images.optimize = true;
+        config.images.max_width = 20;
+        config.images.webp = false;
+        config.images.widths.clear();
+        let manifest = AssetManifest::build(std::slice::from_ref(&static_dir), &config).unwrap();
+        manifest
+            .publish(&output, &FileSync::new(&dir, &config.assets))
+            .unwrap();
+
+        let source = static_dir.join("photo.png");
+        assert_eq!(image::image_dimensions(&source).unwrap(), (40, 2));
+        assert_eq!(
+            image::image_dimensions(output.join("photo.png")).unwrap(),
+            (20, 1)
+        );
+    }
+
+    #[test]
+    fn fingerprinted_names_change_with_the_contents_and_keep_the_extension() {
+        let name = fingerprinted_name("css/style.css", b"body {}");
+        assert!(name.starts_with("css/style."));
+        assert!(name.ends_with(".css"));
+        assert_eq!(name.len(), "css/style.12345678.css".len());
+        assert_eq!(name, fingerprinted_name("css/style.css", b"body {}"));
+        assert_ne!(
+            name,
+            fingerprinted_name("css/style.css", b"body { margin: 0 }")
+        );
+        assert_eq!(
+            fingerprinted_name(".htaccess", b"").len(),
+            ".htaccess.".len() + 8
+        );
+    }
+}
@@ -0,0 +1,176 @@
+use serde::Serialize;
+use std::fs;
+use std::io;
+
+use crate::config::Config;
+use crate::markdown::slugify;
+use crate::{PageContext, RawText, Site, dates, escape_html, staging, write_html};
+
+/// A page's author as templates see it, with whatever the `[authors]`
+/// table says about them.
+#[derive(Serialize, Debug, Clone)]
+pub struct Author {
+    pub id: String,
+    #[serde(serialize_with = "crate::escaped")]
+    pub name: String,
+    #[serde(serialize_with = "crate::escaped")]
+    pub bio: String,
+    pub avatar: String,
+    pub links: Vec<AuthorLink>,
+    /// The author's page, if author pages are generated
+    pub url: String,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct AuthorLink {
+    #[serde(serialize_with = "crate::escaped")]
+    pub name: String,
+    pub url: String,
+}
+
+/// Looks up the authors named in a page's frontmatter, by their key in the
+/// `[authors]` table or by name. Authors missing from the table still show
+/// up, under the name given.
+pub fn resolve(names: &[String], config: &Config, language_prefix: &str) -> Vec<Author> {
+    names
+        .iter()
+        .map(|name| {
+            let known = config.authors.get(name).map(|a| (name, a)).or_else(|| {
+                config
+                    .authors
+                    .iter()
+                    .find(|(_, author)| author.name == *name)
+            });
+            let id = match known {
+                Some((id, _)) => id.clone(),
+                None => slugify(name),
+            };
+
+            Author {
+                url: if config.posts.author_pages {
+                    author_url(&id, language_prefix)
+                } else {
+                    String::new()
+                },
+                name: known
+                    .map(|(_, a)| a.name.clone())
+                    .filter(|n| !n.is_empty())
+                    .unwrap_or_else(|| name.clone()),
+                bio: known.map(|(_, a)| a.bio.clone()).unwrap_or_default(),
+                avatar: known.map(|(_, a)| a.avatar.clone()).unwrap_or_default(),
+                links: known
+                    .into_iter()
+                    .flat_map(|(_, a)| &a.links)
+                    .map(|(name, url)| AuthorLink {
+                        name: name.clone(),
+                        url: url.clone(),
+                    })
+                    .collect(),
+                id,
+            }
+        })
+        .collect()
+}
+
+fn author_url(id: &str, language_prefix: &str) -> String {
+    format!("{}/authors/{}.html", language_prefix, id)
+}
+
+/// Writes a page for every author of a post in `lang`, introducing them and
+/// listing their posts, and returns their URLs.
+pub fn generate_author_pages(site: &Site, lang: &str) -> io::Result<Vec<String>> {
+    let posts = site.posts_for(lang);
+    let language_prefix = site.config.language_prefix(lang);
+
+    let mut authors: Vec<Author> = posts
+        .iter()
+        .flat_map(|post| resolve(&post.authors, site.config, &language_prefix))
+        .collect();
+    authors.sort_by(|a, b| a.id.cmp(&b.id));
+    authors.dedup_by(|a, b| a.id == b.id);
+
+    let mut urls = Vec::new();
+    for author in authors {
+        let items: String = posts
+            .iter()
+            .filter(|post| {
+                resolve(&post.authors, site.config, &language_prefix)
+                    .iter()
+                    .any(|a| a.id == author.id)
+            })
+            .map(|post| {
+                format!(
+                    "<li class=\"h-entry\"><a class=\"u-url p-name\" href=\"{}{}\">{}</a> \
+                     <time class=\"post-date dt-published\" datetime=\"{}\">{}</time></li>\n",
+                    site.base_path,
+                    post.url.trim_start_matches('/'),
+                    escape_html(&post.title),
+                    escape_html(&post.date),
+                    dates::format(&post.date, &site.config.dates.format)
+                )
+            })
+            .collect();
+
+        let mut content = String::from("<header class=\"author-header h-card\">\n");
+        if !author.avatar.is_empty() {
+            content.push_str(&format!(
+                "<img class=\"author-avatar u-photo\" src=\"{}\" alt=\"\">\n",
+                escape_html(&author.avatar)
+            ));
+        }
+        content.push_str(&format!(
+            "<h1 class=\"p-name\">{}</h1>\n",
+            escape_html(&author.name)
+        ));
+        if !author.bio.is_empty() {
+            content.push_str(&format!(
+                "<p class=\"p-note\">{}</p>\n",
+                escape_html(&author.bio)
+            ));
+        }
+        if !author.links.is_empty() {
+            let links: Vec<String> = author
+                .links
+                .iter()
+                .map(|link| {
+                    format!(
+                        "<a class=\"u-url\" rel=\"me\" href=\"{}\">{}</a>",
+                        escape_html(&link.url),
+                        escape_html(&link.name)
+                    )
+                })
+                .collect();
+            content.push_str(&format!(
+                "<p class=\"author-links\">{}</p>\n",
+                links.join(" · ")
+            ));
+        }
+        content.push_str(&format!(
+            "</header>\n<ul class=\"author-posts\">\n{}</ul>\n",
+            items
+        ));
+
+        let mut context = PageContext {
+            title: author.name.clone(),
+            content,
+            assets: site.assets.clone(),
+            base_path: site.link_prefix(),
+            data: site.data.clone(),
+            menus: site.menus_for(lang, &author.url),
+            language_prefix: language_prefix.clone(),
+            posts_url: site.posts_url(lang),
+            ..Default::default()
+        };
+        context.raw = RawText::of(&context);
+        let rendered = site.render("base", &context)?;
+        let output_path = site.output_dir.join(author.url.trim_start_matches('/'));
+        if let Some(parent) = output_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        write_html(&output_path, &rendered, site)?;
+        println!("  👤 Generated {}", staging::shown(&output_path));
+        urls.push(author.url);
+    }
+
+    Ok(urls)
+}
@@ -0,0 +1,190 @@
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+
+use crate::{BuiltPage, PageContext, RawText, Site, escape_html, publish, staging, write_html};
+
+/// Where the blogroll page goes; the OPML version goes next to it
+const URL: &str = "/blogroll.html";
+
+/// A blog in `data/blogroll.yaml`.
+#[derive(Debug, Deserialize)]
+struct Blog {
+    #[serde(alias = "title")]
+    name: String,
+    /// The blog's home page
+    url: String,
+    /// The blog's feed, for feed readers importing the OPML
+    #[serde(default)]
+    feed: String,
+    #[serde(default)]
+    description: String,
+}
+
+/// `data/blogroll.yaml` is either a plain list of blogs or, to group them,
+/// a table of lists keyed by category.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum Blogroll {
+    List(Vec<Blog>),
+    Categories(BTreeMap<String, Vec<Blog>>),
+}
+
+impl Blogroll {
+    /// The blogs by category, with uncategorized ones under `""`.
+    fn categories(&self) -> Vec<(&str, &[Blog])> {
+        match self {
+            Blogroll::List(blogs) => vec![("", blogs)],
+            Blogroll::Categories(categories) => categories
+                .iter()
+                .map(|(name, blogs)| (name.as_str(), blogs.as_slice()))
+                .collect(),
+        }
+    }
+}
+
+/// Writes the blogroll in `data/blogroll.*` twice from the same list: as
+/// `blogroll.html`, unless a content page or a `blogroll` template already
+/// makes one, and as `blogroll.opml` for importing into feed readers.
+/// Returns the page's URL if it wrote one.
+pub fn generate_blogroll(site: &Site, pages: &[BuiltPage]) -> io::Result<Option<String>> {
+    let Some(data) = site.data.get("blogroll") else {
+        return Ok(None);
+    };
+    let blogroll: Blogroll = serde_json::from_value(data.clone()).map_err(|e| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "data/blogroll: expected a list of blogs with a name and url: {}",
+                e
+            ),
+        )
+    })?;
+    let title = &site.config.blogroll.title;
+    let opml_url = URL.replace(".html", ".opml");
+
+    let opml_path = site.output_dir.join(opml_url.trim_start_matches('/'));
+    fs::write(&opml_path, opml(&blogroll, title))?;
+    println!("  📰 Generated {}", staging::shown(&opml_path));
+
+    let has_page =
+        site.template_sources.contains_key("blogroll") || pages.iter().any(|page| page.url == URL);
+    if has_page {
+        return Ok(None);
+    }
+
+    let mut content = format!("<h1>{}</h1>\n", escape_html(title));
+    if !site.config.blogroll.description.is_empty() {
+        content.push_str(&format!(
+            "<p>{}</p>\n",
+            escape_html(&site.config.blogroll.description)
+        ));
+    }
+    for (category, blogs) in blogroll.categories() {
+        if !category.is_empty() {
+            content.push_str(&format!("<h2>{}</h2>\n", escape_html(category)));
+        }
+        content.push_str("<ul class=\"blogroll\">\n");
+        for blog in blogs {
+            content.push_str(&format!(
+                "<li class=\"h-card\"><a class=\"p-name u-url\" href=\"{}\">{}</a>",
+                escape_html(&blog.url),
+                escape_html(&blog.name)
+            ));
+            if !blog.feed.is_empty() {
+                content.push_str(&format!(
+                    " <a class=\"blogroll-feed\" href=\"{}\">(feed)</a>",
+                    escape_html(&blog.feed)
+                ));
+            }
+            if !blog.description.is_empty() {
+                content.push_str(&format!(
+                    " <span class=\"p-note\">{}</span>",
+                    escape_html(&blog.description)
+                ));
+            }
+            content.push_str("</li>\n");
+        }
+        content.push_str("</ul>\n");
+    }
+    content.push_str(&format!(
+        "<p><a href=\"{}{}\">Download as OPML</a></p>\n",
+        site.base_path,
+        opml_url.trim_start_matches('/')
+    ));
+
+    let lang = site.config.default_language();
+    let mut context = PageContext {
+        title: title.clone(),
+        content,
+        assets: site.assets.clone(),
+        base_path: site.link_prefix(),
+        data: site.data.clone(),
+        menus: site.menus_for(lang, URL),
+        language_prefix: site.config.language_prefix(lang),
+        posts_url: site.posts_url(lang),
+        ..Default::default()
+    };
+    context.raw = RawText::of(&context);
+    let rendered = site.render("base", &context)?;
+    let output_path = site.output_dir.join(URL.trim_start_matches('/'));
+    write_html(&output_path, &rendered, site)?;
+    println!("  📰 Generated {}", staging::shown(&output_path));
+    Ok(Some(URL.to_string()))
+}
+
+/// The blogroll as an OPML 2.0 subscription list, categories becoming
+/// outlines of their own.
+fn opml(blogroll: &Blogroll, title: &str) -> String {
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    xml.push_str("<opml version=\"2.0\">\n  <head>\n");
+    xml.push_str(&format!("    <title>{}</title>\n", escape_html(title)));
+    xml.push_str(&format!(
+        "    <dateCreated>{}</dateCreated>\n",
+        publish::now().to_rfc2822()
+    ));
+    xml.push_str("    <docs>http://opml.org/spec2.opml</docs>\n");
+    xml.push_str("  </head>\n  <body>\n");
+
+    for (category, blogs) in blogroll.categories() {
+        let indent = if category.is_empty() {
+            "    "
+        } else {
+            xml.push_str(&format!(
+                "    <outline text=\"{}\">\n",
+                escape_html(category)
+            ));
+            "      "
+        };
+        for blog in blogs {
+            let mut outline = format!(
+                "{}<outline text=\"{}\" title=\"{}\" htmlUrl=\"{}\"",
+                indent,
+                escape_html(&blog.name),
+                escape_html(&blog.name),
+                escape_html(&blog.url)
+            );
+            if !blog.feed.is_empty() {
+                outline.push_str(&format!(
+                    " type=\"rss\" xmlUrl=\"{}\"",
+                    escape_html(&blog.feed)
+                ));
+            }
+            if !blog.description.is_empty() {
+                outline.push_str(&format!(
+                    " description=\"{}\"",
+                    escape_html(&blog.description)
+                ));
+            }
+            xml.push_str(&outline);
+            xml.push_str("/>\n");
+        }
+        if !category.is_empty() {
+            xml.push_str("    </outline>\n");
+        }
+    }
+
+    xml.push_str("  </body>\n</opml>\n");
+    xml
+}
@@ -0,0 +1,556 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+
+use crate::config::{AccessibilityConfig, Severity};
+
+/// A link in a rendered page that doesn't resolve to a file or anchor in the output.
+#[derive(Debug)]
+pub struct BrokenLink {
+    pub page: PathBuf,
+    pub link: String,
+    pub reason: &'static str,
+}
+
+/// Scans every HTML file under `output_dir` for internal links (`href`/`src`)
+/// and reports the ones pointing at missing pages or fragments.
+///
+/// Absolute links are resolved relative to `base_path`, the prefix the site is
+/// served under (e.g. `/bread/` for a GitHub Pages project site).
+pub fn check_links(output_dir: &Path, base_path: &str) -> io::Result<Vec<BrokenLink>> {
+    let base_path = normalize_base_path(base_path);
+
+    let mut pages: HashMap<PathBuf, String> = HashMap::new();
+    for file in find_html_files(output_dir)? {
+        let html = fs::read_to_string(&file)?;
+        let relative = file.strip_prefix(output_dir).unwrap_or(&file).to_path_buf();
+        pages.insert(relative, strip_comments(&html));
+    }
+
+    let ids: HashMap<&PathBuf, HashSet<String>> = pages
+        .iter()
+        .map(|(path, html)| (path, collect_ids(html)))
+        .collect();
+
+    let mut broken = Vec::new();
+    let mut page_paths: Vec<&PathBuf> = pages.keys().collect();
+    page_paths.sort();
+
+    for page in page_paths {
+        let html = &pages[page];
+        for link in extract_links(html) {
+            if is_external(&link) {
+                continue;
+            }
+
+            let (path_part, fragment) = match link.split_once('#') {
+                Some((path, fragment)) => (path, Some(fragment)),
+                None => (link.as_str(), None),
+            };
+            let path_part = path_part.split('?').next().unwrap_or_default();
+
+            let target = if path_part.is_empty() {
+                Some(page.clone())
+            } else {
+                resolve_target(output_dir, page, &percent_decode(path_part), &base_path)
+            };
+
+            let Some(target) = target else {
+                broken.push(BrokenLink {
+                    page: page.clone(),
+                    link,
+                    reason: "missing page",
+                });
+                continue;
+            };
+
+            if let Some(fragment) = fragment.filter(|f| !f.is_empty()) {
+                let known = ids
+                    .get(&target)
+                    .is_some_and(|set| set.contains(&percent_decode(fragment)));
+                if !known {
+                    broken.push(BrokenLink {
+                        page: page.clone(),
+                        link,
+                        reason: "missing anchor",
+                    });
+                }
+            }
+        }
+    }
+
+    Ok(broken)
+}
+
+/// An accessibility problem in a rendered page.
+#[derive(Debug)]
+pub struct Issue {
+    pub line: usize,
+    /// The `[accessibility]` setting for the rule it breaks
+    pub rule: &'static str,
+    pub message: String,
+    pub severity: Severity,
+}
+
+/// Looks through every HTML file under `output_dir` for images without alt
+/// text, headings skipping levels, links without text and documents without
+/// a `lang`, each reported at the severity `config` gives it. Returns the
+/// pages with problems, sorted, and their problems in page order.
+pub fn check_accessibility(
+    output_dir: &Path,
+    config: &AccessibilityConfig,
+) -> io::Result<Vec<(PathBuf, Vec<Issue>)>> {
+    let mut files = find_html_files(output_dir)?;
+    files.sort();
+
+    let mut reports = Vec::new();
+    for file in files {
+        let html = fs::read_to_string(&file)?;
+        let issues = accessibility_issues(&html, config);
+        if !issues.is_empty() {
+            let relative = file.strip_prefix(output_dir).unwrap_or(&file).to_path_buf();
+            reports.push((relative, issues));
+        }
+    }
+    Ok(reports)
+}
+
+fn accessibility_issues(html: &str, config: &AccessibilityConfig) -> Vec<Issue> {
+    let mut issues = Vec::new();
+    let mut report = |severity: Severity, rule, at: usize, message: String| {
+        if severity != Severity::Off {
+            issues.push(Issue {
+                line: line_number(html, at),
+                rule,
+                message,
+                severity,
+            });
+        }
+    };
+
+    let tags = tags(html);
+    let mut previous_heading: Option<(u8, &str)> = None;
+    for (i, tag) in tags.iter().enumerate() {
+        if tag.closing {
+            continue;
+        }
+        match tag.name.as_str() {
+            "html" if attribute(tag.text, "lang").is_none_or(|lang| lang.trim().is_empty()) => {
+                report(
+                    config.missing_lang,
+                    "missing_lang",
+                    tag.start,
+                    "<html> has no lang attribute".to_string(),
+                );
+            }
+            "img" if attribute(tag.text, "alt").is_none() => {
+                let src = attribute(tag.text, "src").unwrap_or_default();
+                report(
+                    config.missing_alt,
+                    "missing_alt",
+                    tag.start,
+                    format!("image {} has no alt text", src),
+                );
+            }
+            "a" if attribute(tag.text, "href").is_some()
+                && !has_accessible_name(html, &tags, i) =>
+            {
+                let href = attribute(tag.text, "href").unwrap_or_default();
+                report(
+                    config.empty_link,
+                    "empty_link",
+                    tag.start,
+                    format!("link to {} has no text", href),
+                );
+            }
+            name => {
+                let level = name
+                    .strip_prefix('h')
+                    .and_then(|level| level.parse::<u8>().ok())
+                    .filter(|level| (1..=6).contains(level));
+                if let Some(level) = level {
+                    if let Some((previous, previous_name)) = previous_heading
+                        && level > previous + 1
+                    {
+                        report(
+                            config.skipped_heading,
+                            "skipped_heading",
+                            tag.start,
+                            format!("<{}> follows <{}>, skipping a level", name, previous_name),
+                        );
+                    }
+                    previous_heading = Some((level, &tag.name));
+                }
+            }
+        }
+    }
+    issues
+}
+
+/// Whether the link opened by `tags[open]` has something for a screen
+/// reader to say: text, an image with alt text, or a label of its own.
+fn has_accessible_name(html: &str, tags: &[Tag], open: usize) -> bool {
+    let link = &tags[open];
+    if ["aria-label", "aria-labelledby", "title"]
+        .iter()
+        .any(|label| attribute(link.text, label).is_some_and(|value| !value.trim().is_empty()))
+    {
+        return true;
+    }
+
+    let mut text_start = link.end;
+    for tag in &tags[open + 1..] {
+        if !html[text_start..tag.start].trim().is_empty() {
+            return true;
+        }
+        if tag.name == "a" && tag.closing {
+            return false;
+        }
+        let labelled = match tag.name.as_str() {
+            "img" => attribute(tag.text, "alt"),
+            "svg" | "span" | "i" => attribute(tag.text, "aria-label"),
+            _ => None,
+        };
+        if labelled.is_some_and(|label| !label.trim().is_empty()) {
+            return true;
+        }
+        text_start = tag.end;
+    }
+    !html[text_start..].trim().is_empty()
+}
+
+/// A start or end tag in an HTML document.
+#[derive(Debug)]
+pub struct Tag<'a> {
+    /// Lowercased
+    pub name: String,
+    pub closing: bool,
+    /// The whole tag, from `<` to `>`
+    pub text: &'a str,
+    pub start: usize,
+    pub end: usize,
+}
+
+/// The tags in `html`, in order, skipping comments, doctypes and whatever
+/// is inside `<script>` and `<style>` elements.
+pub fn tags(html: &str) -> Vec<Tag<'_>> {
+    let mut tags = Vec::new();
+    let mut pos = 0;
+    while let Some(offset) = html[pos..].find('<') {
+        let start = pos + offset;
+        let rest = &html[start..];
+        if rest.starts_with("<!--") {
+            pos = rest.find("-->").map_or(html.len(), |end| start + end + 3);
+            continue;
+        }
+        if rest.starts_with("<!") || rest.starts_with("<?") {
+            pos = rest.find('>').map_or(html.len(), |end| start + end + 1);
+            continue;
+        }
+
+        let closing = rest.starts_with("</");
+        let name_start = start + if closing { 2 } else { 1 };
+        let name: String = html[name_start..]
+            .chars()
+            .take_while(|c| c.is_ascii_alphanumeric() || *c == '-')
+            .collect::<String>()
+            .to_ascii_lowercase();
+        if name.is_empty() || !html[name_start..].starts_with(|c: char| c.is_ascii_alphabetic()) {
+            pos = start + 1;
+            continue;
+        }
+
+        let end = tag_end(html, name_start + name.len());
+        pos = end;
+        if !closing && (name == "script" || name == "style") {
+            let close = format!("</{}", name);
+            pos = html[end..]
+                .to_ascii_lowercase()
+                .find(&close)
+                .map_or(html.len(), |offset| end + offset);
+        }
+        tags.push(Tag {
+            name,
+            closing,
+            text: &html[start..end],
+            start,
+            end,
+        });
+    }
+    tags
+}
+
+/// Where the tag whose attributes start at `from` ends, just past its `>`,
+/// not counting any `>` inside quoted attribute values.
+fn tag_end(html: &str, from: usize) -> usize {
+    let mut quote = None;
+    for (i, c) in html[from..].char_indices() {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(open), _) if c == open => quote = None,
+            (None, '>') => return from + i + 1,
+            _ => {}
+        }
+    }
+    html.len()
+}
+
+/// The value of attribute `name` in `tag`, empty for one without a value,
+/// or `None` if it isn't there.
+pub fn attribute<'a>(tag: &'a str, name: &str) -> Option<&'a str> {
+    let inner = tag.trim_start_matches('<').trim_end_matches('>');
+    let mut rest = inner.trim_start_matches(|c: char| !c.is_whitespace());
+    loop {
+        rest = rest.trim_start_matches(|c: char| c.is_whitespace() || c == '/');
+        if rest.is_empty() {
+            return None;
+        }
+        let name_len = rest
+            .find(|c: char| c.is_whitespace() || c == '=' || c == '/')
+            .unwrap_or(rest.len());
+        let attribute_name = &rest[..name_len];
+        rest = rest[name_len..].trim_start();
+
+        let value = match rest.strip_prefix('=') {
+            Some(after) => {
+                let after = after.trim_start();
+                let (value, remainder) = match after.chars().next() {
+                    Some(quote @ ('"' | '\'')) => {
+                        let len = after[1..].find(quote).unwrap_or(after.len() - 1);
+                        (&after[1..1 + len], after.get(len + 2..).unwrap_or(""))
+                    }
+                    _ => {
+                        let len = after.find(char::is_whitespace).unwrap_or(after.len());
+                        (&after[..len], &after[len..])
+                    }
+                };
+                rest = remainder;
+                value
+            }
+            None => "",
+        };
+        if attribute_name.eq_ignore_ascii_case(name) {
+            return Some(value);
+        }
+    }
+}
+
+/// The line of `html` byte `at` is on, counting from 1.
+pub fn line_number(html: &str, at: usize) -> usize {
+    html[..at].matches('\n').count() + 1
+}
+
+fn normalize_base_path(base_path: &str) -> String {
+    let trimmed = base_path.trim_matches('/');
+    if trimmed.is_empty() {
+        "/".to_string()
+    } else {
+        format!("/{}/", trimmed)
+    }
+}
+
+fn is_external(link: &str) -> bool {
+    link.starts_with("//")
+        || link.contains("://")
+        || ["mailto:", "tel:", "data:", "javascript:"]
+            .iter()
+            .any(|scheme| link.starts_with(scheme))
+}
+
+/// Resolves a link path to the output file it refers to, relative to the
+/// output directory. Returns `None` if nothing exists there.
+fn resolve_target(output_dir: &Path, page: &Path, link: &str, base_path: &str) -> Option<PathBuf> {
+    let joined = if link.starts_with('/') {
+        let with_slash = format!("{}/", link.trim_end_matches('/'));
+        let rest = if with_slash == base_path {
+            ""
+        } else {
+            link.strip_prefix(base_path)?
+        };
+        PathBuf::from(rest)
+    } else {
+        page.parent().unwrap_or(Path::new("")).join(link)
+    };
+
+    let mut normalized = PathBuf::new();
+    for component in joined.components() {
+        match component {
+            Component::Normal(part) => normalized.push(part),
+            Component::ParentDir if !normalized.pop() => return None,
+            _ => {}
+        }
+    }
+
+    let full = output_dir.join(&normalized);
+    if full.is_dir() {
+        let index = normalized.join("index.html");
+        output_dir.join(&index).is_file().then_some(index)
+    } else if full.is_file() {
+        Some(normalized)
+    } else {
+        None
+    }
+}
+
+fn find_html_files(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut html_files = Vec::new();
+
+    if !dir.is_dir() {
+        return Ok(html_files);
+    }
+
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+
+        if path.is_dir() {
+            html_files.extend(find_html_files(&path)?);
+        } else if path.extension().and_then(|s| s.to_str()) == Some("html") {
+            html_files.push(path);
+        }
+    }
+
+    Ok(html_files)
+}
+
+fn strip_comments(html: &str) -> String {
+    let mut result = String::with_capacity(html.len());
+    let mut rest = html;
+    while let Some(start) = rest.find("<!--") {
+        result.push_str(&rest[..start]);
+        match rest[start..].find("-->") {
+            Some(end) => rest = &rest[start + end + 3..],
+            None => {
+                rest = "";
+                break;
+            }
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+/// Returns the values of every occurrence of `attr="..."` (or single-quoted)
+/// in the document.
+pub fn attribute_values(html: &str, attr: &str) -> Vec<String> {
+    let mut values = Vec::new();
+    let needle = format!("{}=", attr);
+    let mut search_from = 0;
+
+    while let Some(pos) = html[search_from..].find(&needle) {
+        let start = search_from + pos;
+        let value_start = start + needle.len();
+        search_from = value_start;
+
+        // Make sure we matched a whole attribute name, not e.g. `data-href=`
+        let preceded_by_space = html[..start]
+            .chars()
+            .next_back()
+            .is_some_and(|c| c.is_whitespace());
+        if !preceded_by_space {
+            continue;
+        }
+
+        let Some(quote) = html[value_start..].chars().next() else {
+            break;
+        };
+        if quote != '"' && quote != '\'' {
+            continue;
+        }
+        if let Some(len) = html[value_start + 1..].find(quote) {
+            values.push(html[value_start + 1..value_start + 1 + len].to_string());
+            search_from = value_start + 1 + len;
+        }
+    }
+
+    values
+}
+
+fn extract_links(html: &str) -> Vec<String> {
+    let mut links = attribute_values(html, "href");
+    links.extend(attribute_values(html, "src"));
+    links.retain(|l| !l.trim().is_empty());
+    links
+}
+
+fn collect_ids(html: &str) -> HashSet<String> {
+    let mut ids: HashSet<String> = attribute_values(html, "id").into_iter().collect();
+    ids.extend(attribute_values(html, "name"));
+    ids
+}
+
+fn percent_decode(input: &str) -> String {
+    let bytes = input.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).ok();
+            if let Some(byte) = hex.and_then(|h| u8::from_str_radix(h, 16).ok()) {
+                decoded.push(byte);
+                i += 3;
+                continue;
+            }
+        }
+        decoded.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tags_skip_comments_doctypes_and_script_contents() {
+        let html = "<!DOCTYPE html><!-- <b> --><P class=x>hi</p>\
+                    <script>if (a < b) { x = '<i>'; }</script><br/>";
+        let found: Vec<(String, bool)> = tags(html)
+            .into_iter()
+            .map(|tag| (tag.name, tag.closing))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                ("p".to_string(), false),
+                ("p".to_string(), true),
+                ("script".to_string(), false),
+                ("script".to_string(), true),
+                ("br".to_string(), false),
+            ]
+        );
+    }
+
+    #[test]
+    fn tags_know_where_they_are() {
+        let html = "text <a href=\"/x\">link</a>";
+        let found = tags(html);
+        assert_eq!(found[0].text, "<a href=\"/x\">");
+        assert_eq!(&html[found[0].start..found[0].end], found[0].text);
+        assert_eq!(found[1].text, "</a>");
+    }
+
+    #[test]
+    fn tags_ignore_a_lone_less_than() {
+        assert!(tags("1 < 2 and 3 <4").is_empty());
+    }
+
+    #[test]
+    fn attribute_reads_every_quoting_style() {
+        let tag = "<img src=\"a.png\" alt='An image' width=10 hidden data-x = \"y\">";
+        assert_eq!(attribute(tag, "src"), Some("a.png"));
+        assert_eq!(attribute(tag, "alt"), Some("An image"));
+        assert_eq!(attribute(tag, "width"), Some("10"));
+        assert_eq!(attribute(tag, "hidden"), Some(""));
+        assert_eq!(attribute(tag, "data-x"), Some("y"));
+        assert_eq!(attribute(tag, "title"), None);
+    }
+
+    #[test]
+    fn attribute_matches_whole_names() {
+        assert_eq!(attribute("<a data-href=\"x\">", "href"), None);
+        assert_eq!(attribute("<input disabled/>", "disabled"), Some(""));
+        assert_eq!(attribute("<img alt=\"\">", "alt"), Some(""));
+    }
+}
@@ -0,0 +1,286 @@
+use clap::{Args, CommandFactory, Parser, Subcommand};
+use clap_complete::Shell;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::config::{Config, DEFAULT_PROFILE, DeployTarget};
+use crate::{Plugins, build_site, dry_run, watch};
+
+/// The `bread` command line, kept in the library so completions and man
+/// pages can be generated from it.
+#[derive(Parser, Debug)]
+#[command(version, about = "Bread: A minimal static site generator", long_about = None)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Commands,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Commands {
+    Build {
+        #[command(flatten)]
+        args: BuildArgs,
+
+        /// Render the build into a scratch directory next to the output
+        /// directory and list what would change in it, without writing to
+        /// the output directory or running hooks
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Keep running, and rebuild with the config and templates
+        /// reloaded whenever the content, templates, static files, data,
+        /// theme or config change
+        #[arg(long, conflicts_with = "dry_run")]
+        watch: bool,
+    },
+    /// Build the site and publish it, by default to a branch of this
+    /// repository for GitHub Pages
+    Deploy {
+        #[command(flatten)]
+        build: BuildArgs,
+
+        /// Where to deploy, instead of `deploy.target`
+        #[arg(long, value_enum)]
+        target: Option<DeployTarget>,
+
+        /// Branch to push to, instead of `deploy.branch`
+        #[arg(long)]
+        branch: Option<String>,
+
+        /// Commit message for the deploy
+        #[arg(short, long, default_value = "Deploy site")]
+        message: String,
+    },
+    /// Show statistics about the content, without building the site
+    Stats {
+        #[arg(short, long, default_value = "content")]
+        content_dir: String,
+
+        /// Site configuration file
+        #[arg(long, default_value = "bread.toml")]
+        config: String,
+
+        /// Config profile to use, like `dev` or `prod`
+        #[arg(long)]
+        profile: Option<String>,
+    },
+    /// Check the prose in the markdown content for misspellings and the
+    /// site's own `[[lint.rules]]`
+    Lint {
+        #[arg(short, long, default_value = "content")]
+        content_dir: String,
+
+        /// Site configuration file
+        #[arg(long, default_value = "bread.toml")]
+        config: String,
+
+        /// Config profile to use, like `dev` or `prod`
+        #[arg(long)]
+        profile: Option<String>,
+    },
+    /// Take a draft live: set `draft: false` and today's date in its
+    /// frontmatter
+    Publish {
+        file: PathBuf,
+
+        /// Date to publish on instead of today, like `2024-03-05`
+        #[arg(long)]
+        date: Option<String>,
+
+        /// Also put the date in front of the file name, keeping its URL
+        /// with a `slug:`
+        #[arg(long)]
+        rename: bool,
+    },
+    /// Print a completion script for a shell
+    Completions {
+        #[arg(value_enum)]
+        shell: Shell,
+    },
+    /// Print the man page, or write pages for bread and each of its
+    /// subcommands to a directory
+    Man {
+        #[arg(long)]
+        out_dir: Option<PathBuf>,
+    },
+    /// Manage the site's theme
+    Theme {
+        #[command(subcommand)]
+        command: ThemeCommand,
+    },
+    /// Check the built site for broken internal links and anchors, and for
+    /// accessibility problems
+    Check {
+        #[arg(short, long, default_value = "public")]
+        output_dir: String,
+
+        /// Path prefix the site is served under; defaults to the path of
+        /// the config's `base_url`
+        #[arg(short, long)]
+        base_path: Option<String>,
+
+        /// Site configuration file, for the `[accessibility]` rules
+        #[arg(long, default_value = "bread.toml")]
+        config: String,
+
+        /// Config profile to use, like `dev` or `prod`
+        #[arg(long)]
+        profile: Option<String>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ThemeCommand {
+    /// Clone a theme's git repository into `themes/` and build with it
+    Install {
+        url: String,
+
+        /// Directory name for the theme under `themes/`, instead of the
+        /// repository's name
+        #[arg(long)]
+        name: Option<String>,
+
+        /// Site configuration file to set the theme in
+        #[arg(long, default_value = "bread.toml")]
+        config: String,
+    },
+}
+
+#[derive(Args, Debug)]
+pub struct BuildArgs {
+    #[arg(short, long, default_value = "content")]
+    pub content_dir: String,
+
+    #[arg(short, long, default_value = "public")]
+    pub output_dir: String,
+
+    #[arg(short, long, default_value = "templates")]
+    pub template_dir: String,
+
+    /// Site configuration file
+    #[arg(long, default_value = "bread.toml")]
+    pub config: String,
+
+    /// Config profile to build with, like `dev` or `prod`; defaults to
+    /// `$BREAD_ENV`, then `prod`
+    #[arg(long)]
+    pub profile: Option<String>,
+
+    /// Minify rendered HTML
+    #[arg(long)]
+    pub minify: bool,
+
+    /// Treat warnings, like pages without a title, as errors
+    #[arg(long)]
+    pub strict: bool,
+
+    /// Stop at the first page that fails to render
+    #[arg(long)]
+    pub fail_fast: bool,
+
+    /// Also write a Gemini capsule of the site
+    #[arg(long)]
+    pub gemini: bool,
+
+    /// Write the build report as JSON to this file
+    #[arg(long)]
+    pub report: Option<PathBuf>,
+}
+
+impl BuildArgs {
+    pub fn load_config(&self) -> io::Result<Config> {
+        let mut config = Config::load(Path::new(&self.config), &profile(&self.profile))?;
+        config.minify |= self.minify;
+        config.strict |= self.strict;
+        config.fail_fast |= self.fail_fast;
+        config.gemini.enabled |= self.gemini;
+        if self.report.is_some() {
+            config.report = self.report.clone();
+        }
+        Ok(config)
+    }
+
+    pub fn build(&self, config: &Config) -> io::Result<()> {
+        build_site(
+            &self.content_dir,
+            &self.output_dir,
+            &self.template_dir,
+            config,
+            Plugins::new(),
+        )
+    }
+
+    pub fn dry_run(&self, config: &Config) -> io::Result<()> {
+        dry_run(
+            &self.content_dir,
+            &self.output_dir,
+            &self.template_dir,
+            config,
+            Plugins::new(),
+        )
+    }
+
+    /// Builds the site, then again whenever a file it's built from changes.
+    pub fn watch(&self) -> ! {
+        watch::run(
+            || self.load_config().and_then(|config| self.build(&config)),
+            || self.watched_paths(),
+        )
+    }
+
+    /// The files and directories a build reads. The config is loaded again
+    /// for the content and theme directories, so changing those in it
+    /// watches the new ones.
+    fn watched_paths(&self) -> Vec<PathBuf> {
+        let config = self.load_config().unwrap_or_default();
+        let mut paths = config.content_roots(Path::new(&self.content_dir));
+        paths.extend(config.theme_dir());
+        paths.extend(
+            [
+                &self.template_dir,
+                &self.config,
+                "static",
+                "data",
+                ".breadignore",
+            ]
+            .map(PathBuf::from),
+        );
+        paths
+    }
+}
+
+/// The profile asked for with `--profile`, else in `BREAD_ENV`, else the
+/// default.
+pub fn profile(arg: &Option<String>) -> String {
+    arg.clone()
+        .or_else(|| std::env::var("BREAD_ENV").ok())
+        .unwrap_or_else(|| DEFAULT_PROFILE.to_string())
+}
+
+/// Writes the completion script for `shell` to stdout.
+pub fn print_completions(shell: Shell) {
+    clap_complete::generate(shell, &mut Cli::command(), "bread", &mut io::stdout());
+}
+
+/// Writes the man page to stdout or, given `out_dir`, `bread.1` and a
+/// `bread-<subcommand>.1` for every subcommand there.
+pub fn write_man_pages(out_dir: Option<&Path>) -> io::Result<()> {
+    let command = Cli::command();
+    let Some(out_dir) = out_dir else {
+        return clap_mangen::Man::new(command).render(&mut io::stdout());
+    };
+
+    fs::create_dir_all(out_dir)?;
+    for subcommand in command.get_subcommands() {
+        let name = format!("bread-{}", subcommand.get_name());
+        let page = subcommand.clone().name(name.clone());
+        let path = out_dir.join(format!("{}.1", name));
+        clap_mangen::Man::new(page).render(&mut fs::File::create(&path)?)?;
+        println!("  📖 Wrote {}", path.display());
+    }
+    let path = out_dir.join("bread.1");
+    clap_mangen::Man::new(command).render(&mut fs::File::create(&path)?)?;
+    println!("  📖 Wrote {}", path.display());
+    Ok(())
+}
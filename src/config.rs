@@ -0,0 +1,1112 @@
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// The profile the config is loaded for unless `--profile` or `BREAD_ENV`
+/// name another.
+pub const DEFAULT_PROFILE: &str = "prod";
+
+/// Where themes are kept, one directory each
+pub const THEMES_DIR: &str = "themes";
+
+/// Site-wide settings read from `bread.toml`. Every field has a default, so
+/// the file itself is optional.
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// Collapse whitespace and strip comments from rendered HTML
+    pub minify: bool,
+    /// Fail the build on anything it would otherwise just warn about
+    pub strict: bool,
+    /// Stop at the first page that fails to render, instead of building the
+    /// rest and listing the failures at the end
+    pub fail_fast: bool,
+    /// Look over every built page for unclosed tags, duplicate IDs and
+    /// elements nested where they can't be, usually typos in raw HTML in the
+    /// content, and warn about them
+    pub lint_html: bool,
+    /// Build pages with `draft: true` in their frontmatter, or a
+    /// `publish_date` still to come, which are left out otherwise. On by
+    /// default in the `dev` profile.
+    pub drafts: bool,
+    /// The profile the config was loaded for, see `Config::load`
+    #[serde(skip)]
+    pub profile: String,
+    /// Also write the build report as JSON to this file
+    pub report: Option<PathBuf>,
+    /// Site name, used in feeds
+    pub title: String,
+    /// One-line description of the site, used in feeds
+    pub description: String,
+    /// Social preview image for pages that don't set their own `image:`
+    pub image: String,
+    /// Also list page aliases in a Netlify-style `_redirects` file
+    pub netlify_redirects: bool,
+    /// Where the site is served from: a full URL like
+    /// `https://example.com/blog/`, or just the path. Links generated into
+    /// page content start with its path.
+    pub base_url: String,
+    /// Custom domain the site is served from on GitHub Pages, like
+    /// `blog.example.com`. When set, the build writes a `CNAME` file for it,
+    /// along with `.nojekyll` so Pages serves the output as is.
+    pub domain: String,
+    /// Theme to build with, from `themes/<theme>/`: its `templates/` and
+    /// `static/` fill in for the files the site's own don't have
+    pub theme: String,
+    /// Look up when each content file was last committed to git, and by whom,
+    /// for `last_modified` and `git_authors` in templates and for the
+    /// sitemap's `<lastmod>`
+    pub git_history: bool,
+    /// Languages the content is written in, the first being the default. With
+    /// more than one, pages in `content/<lang>/` or named `<name>.<lang>.md`
+    /// are published under `/<lang>/`, and every language gets its own posts
+    /// listing and feeds.
+    pub languages: Vec<String>,
+    /// Write `sitemap.xml`, and point to it from `robots.txt`
+    pub sitemap: bool,
+    /// Entity-encode `mailto:` links and email addresses in pages, which
+    /// keeps them from the simpler scrapers
+    pub obfuscate_emails: bool,
+    /// Write links into the site relative to the page they're on, so the
+    /// built site works opened from disk or served from any path
+    pub relative_urls: bool,
+    /// More directories of content, like `["notes", "../shared-posts"]`,
+    /// merged into the site as if their files were in the content directory
+    pub content_dirs: Vec<PathBuf>,
+    /// Walk into symlinked directories under the content and static
+    /// directories, like a notes vault linked into `content/`. Links back to
+    /// a directory they're in are skipped either way.
+    pub follow_symlinks: bool,
+    /// Globs for files under the content directory to leave out of the build,
+    /// on top of those listed in `.breadignore`
+    pub ignore: Vec<String>,
+    pub markdown: MarkdownConfig,
+    pub posts: PostsConfig,
+    pub feeds: FeedsConfig,
+    pub assets: AssetsConfig,
+    pub images: ImagesConfig,
+    pub social_cards: SocialCardsConfig,
+    pub deploy: DeployConfig,
+    pub hooks: HooksConfig,
+    pub plugins: PluginsConfig,
+    pub dates: DatesConfig,
+    pub repository: RepositoryConfig,
+    pub prune: PruneConfig,
+    pub formats: FormatsConfig,
+    pub gemini: GeminiConfig,
+    pub activitypub: ActivityPubConfig,
+    pub alternates: AlternatesConfig,
+    pub snippets: SnippetsConfig,
+    pub external_links: ExternalLinksConfig,
+    pub protected: ProtectedConfig,
+    pub well_known: WellKnownConfig,
+    pub indieweb: IndieWebConfig,
+    pub blogroll: BlogrollConfig,
+    pub accessibility: AccessibilityConfig,
+    pub lint: LintConfig,
+    /// The people writing for the site, by the key pages refer to them with
+    /// in `author:`/`authors:`
+    pub authors: BTreeMap<String, AuthorConfig>,
+    /// Site menus by name, from `[[menu.main]]` tables; pages join them
+    /// with `menu:` in their frontmatter
+    pub menu: BTreeMap<String, Vec<MenuEntryConfig>>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            minify: false,
+            strict: false,
+            lint_html: false,
+            fail_fast: false,
+            drafts: false,
+            profile: DEFAULT_PROFILE.to_string(),
+            report: None,
+            title: String::new(),
+            description: String::new(),
+            image: String::new(),
+            netlify_redirects: false,
+            base_url: "/bread/".to_string(),
+            domain: String::new(),
+            theme: String::new(),
+            git_history: false,
+            languages: Vec::new(),
+            sitemap: true,
+            obfuscate_emails: true,
+            relative_urls: false,
+            content_dirs: Vec::new(),
+            follow_symlinks: true,
+            ignore: Vec::new(),
+            markdown: MarkdownConfig::default(),
+            posts: PostsConfig::default(),
+            feeds: FeedsConfig::default(),
+            assets: AssetsConfig::default(),
+            images: ImagesConfig::default(),
+            social_cards: SocialCardsConfig::default(),
+            deploy: DeployConfig::default(),
+            hooks: HooksConfig::default(),
+            plugins: PluginsConfig::default(),
+            dates: DatesConfig::default(),
+            repository: RepositoryConfig::default(),
+            prune: PruneConfig::default(),
+            formats: FormatsConfig::default(),
+            gemini: GeminiConfig::default(),
+            activitypub: ActivityPubConfig::default(),
+            alternates: AlternatesConfig::default(),
+            snippets: SnippetsConfig::default(),
+            external_links: ExternalLinksConfig::default(),
+            protected: ProtectedConfig::default(),
+            well_known: WellKnownConfig::default(),
+            indieweb: IndieWebConfig::default(),
+            blogroll: BlogrollConfig::default(),
+            accessibility: AccessibilityConfig::default(),
+            lint: LintConfig::default(),
+            authors: BTreeMap::new(),
+            menu: BTreeMap::new(),
+        }
+    }
+}
+
+/// Markdown rendering switches. Pages can override any of them from their
+/// frontmatter with `markdown.<option>: true|false`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct MarkdownConfig {
+    /// Append a `¶` link to every heading pointing at its own anchor
+    pub heading_anchors: bool,
+    /// Render images as `<picture>` elements with `srcset`s covering the
+    /// variants produced by the image pipeline, lazy-loaded and with their
+    /// dimensions set
+    pub responsive_images: bool,
+    /// GitHub-style pipe tables
+    pub tables: bool,
+    /// `[^note]` footnote references and definitions
+    pub footnotes: bool,
+    /// `~~struck through~~` text
+    pub strikethrough: bool,
+    /// `- [ ]` / `- [x]` task list items
+    pub tasklists: bool,
+    /// How task list items show whether they're done
+    pub tasklist_style: TaskListStyle,
+    /// Wrap code blocks in a `<div class="code-copy" data-copyable>`, and
+    /// load a small script on pages that have them which adds a copy button
+    /// to each
+    pub copy_button: bool,
+    /// `{#id .class}` attributes after heading text; off by default, so
+    /// headings that end in braces keep them
+    pub heading_attributes: bool,
+    /// Turn straight quotes into curly ones, `--`/`---` into en/em dashes and
+    /// `...` into an ellipsis
+    pub smart_punctuation: bool,
+    /// Treat `$...$` and `$$...$$` as TeX math, left untouched by markdown and
+    /// typeset in the browser by KaTeX
+    pub math: bool,
+    /// Turn ```` ```mermaid ```` code blocks into diagrams, drawn in the
+    /// browser by Mermaid unless `mermaid_command` is set
+    pub mermaid: bool,
+    /// Command that renders Mermaid diagrams at build time, reading the
+    /// diagram source on stdin and writing SVG to stdout, e.g.
+    /// `["mmdc", "-i", "-", "-o", "-", "-e", "svg"]`
+    pub mermaid_command: Vec<String>,
+    /// Resolve Obsidian-style `[[Page Name]]` and `[[page|text]]` links to the
+    /// page with that title or file name
+    pub wikilinks: bool,
+    /// Heading shown above the footnotes collected at the end of a page; none
+    /// if empty
+    pub footnotes_heading: String,
+    /// Turn GitHub-style `:rocket:` shortcodes into emoji
+    pub emoji: bool,
+    /// Image for emoji shortcodes bread doesn't know, with `{name}` standing
+    /// for the name, e.g.
+    /// `https://github.githubassets.com/images/icons/emoji/{name}.png`;
+    /// they're left as written if empty
+    pub emoji_image_url: String,
+    /// Demote every heading by one level, H1 to H2 and so on, for content
+    /// that starts its sections at H1 below the template's title
+    pub shift_headings: bool,
+    /// Leave out a heading at the very start of the page that repeats its
+    /// title, which the template already shows
+    pub strip_title_heading: bool,
+}
+
+impl Default for MarkdownConfig {
+    fn default() -> Self {
+        MarkdownConfig {
+            heading_anchors: false,
+            responsive_images: false,
+            tables: true,
+            footnotes: true,
+            strikethrough: true,
+            tasklists: true,
+            tasklist_style: TaskListStyle::Checkbox,
+            copy_button: false,
+            heading_attributes: false,
+            smart_punctuation: false,
+            math: false,
+            mermaid: false,
+            mermaid_command: Vec::new(),
+            wikilinks: false,
+            footnotes_heading: String::new(),
+            emoji: false,
+            emoji_image_url: String::new(),
+            shift_headings: false,
+            strip_title_heading: false,
+        }
+    }
+}
+
+impl MarkdownConfig {
+    /// Sets the option called `name`; unknown names are ignored.
+    pub fn set(&mut self, name: &str, value: bool) {
+        let option = match name {
+            "heading_anchors" => &mut self.heading_anchors,
+            "responsive_images" => &mut self.responsive_images,
+            "tables" => &mut self.tables,
+            "footnotes" => &mut self.footnotes,
+            "strikethrough" => &mut self.strikethrough,
+            "tasklists" => &mut self.tasklists,
+            "copy_button" => &mut self.copy_button,
+            "heading_attributes" => &mut self.heading_attributes,
+            "smart_punctuation" => &mut self.smart_punctuation,
+            "math" => &mut self.math,
+            "mermaid" => &mut self.mermaid,
+            "wikilinks" => &mut self.wikilinks,
+            "emoji" => &mut self.emoji,
+            "shift_headings" => &mut self.shift_headings,
+            "strip_title_heading" => &mut self.strip_title_heading,
+            _ => return,
+        };
+        *option = value;
+    }
+}
+
+/// How task list items are marked done or not. Either way the items get the
+/// `task-list-item` class, and `task-list-item-checked` when done.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TaskListStyle {
+    /// A disabled checkbox
+    #[default]
+    Checkbox,
+    /// A checkbox readers can tick, which isn't saved anywhere
+    Interactive,
+    /// A `☑`/`☐` span with the checkbox role, for themes to style
+    Span,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct PostsConfig {
+    /// How many related posts to list on each post
+    pub related: usize,
+    /// How many of the newest posts template pages get as `recent`
+    pub recent: usize,
+    /// Generate an index page under `series/` for every post series
+    pub series_pages: bool,
+    /// Generate a page under `tags/` for every tag, listing its posts with
+    /// the `tag` template
+    pub tag_pages: bool,
+    /// Generate a page under `authors/` for every post author, listing their
+    /// posts
+    pub author_pages: bool,
+    /// Order of the posts listing; posts with `pinned: true` come first
+    /// either way
+    pub sort: PostSort,
+    /// Write the posts listing at all
+    pub listing: bool,
+    /// Where the posts listing goes in the output, like `blog/index.html`,
+    /// under each language's prefix
+    pub listing_path: String,
+    /// The template the posts listing renders with
+    pub listing_template: String,
+    /// Where posts are published, like `/:year/:month/:slug/`, under their
+    /// language's prefix: `:year`, `:month` and `:day` come from the post's
+    /// date, `:slug` is its `slug` or file name, `:title` its title as a slug
+    /// and `:section` its top-level content directory. A trailing `/` gives
+    /// the post an `index.html` of its own. Posts keep their place in the
+    /// content directory if empty, or if the pattern needs a date they
+    /// don't have.
+    pub permalink: String,
+}
+
+/// How the posts listing orders posts.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PostSort {
+    /// Newest first
+    Date,
+    /// Alphabetically
+    Title,
+    /// By the posts' `weight:`, lightest first, then newest first
+    Weight,
+}
+
+impl Default for PostsConfig {
+    fn default() -> Self {
+        PostsConfig {
+            related: 3,
+            recent: 5,
+            series_pages: false,
+            tag_pages: true,
+            author_pages: false,
+            sort: PostSort::Date,
+            listing: true,
+            listing_path: "posts.html".to_string(),
+            listing_template: "posts".to_string(),
+            permalink: String::new(),
+        }
+    }
+}
+
+/// What goes into the RSS feeds.
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct FeedsConfig {
+    /// Write the feeds at all
+    pub enabled: bool,
+    /// Whether items carry the post's summary or all of it
+    pub content: FeedContent,
+    /// Most posts to list in each feed, newest first; all of them if unset
+    pub limit: Option<usize>,
+    /// Also write a feed for every top-level content directory with posts
+    /// in it, like `projects/rss.xml`
+    pub sections: bool,
+}
+
+impl Default for FeedsConfig {
+    fn default() -> Self {
+        FeedsConfig {
+            enabled: true,
+            content: FeedContent::Summary,
+            limit: None,
+            sections: false,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FeedContent {
+    #[default]
+    Summary,
+    Full,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct AssetsConfig {
+    /// Publish static assets under content-hashed names like `style.3f9a1c2e.css`
+    pub fingerprint: bool,
+    /// Extensions of the assets that get fingerprinted
+    pub fingerprint_extensions: Vec<String>,
+    /// Compile `.scss`/`.sass` files to CSS instead of copying them verbatim
+    pub sass: bool,
+    /// Hard-link static files into the output instead of copying them, which
+    /// is faster for big directories of photos but means anything editing
+    /// the output in place, like a `post_build` hook, edits the sources too
+    pub hard_links: bool,
+}
+
+impl Default for AssetsConfig {
+    fn default() -> Self {
+        AssetsConfig {
+            fingerprint: false,
+            fingerprint_extensions: vec!["css".to_string(), "js".to_string()],
+            sass: true,
+            hard_links: false,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct ImagesConfig {
+    /// Run PNG and JPEG assets through the image pipeline
+    pub optimize: bool,
+    /// Images wider than this are downscaled to it
+    pub max_width: u32,
+    /// Publish a WebP copy next to each image
+    pub webp: bool,
+    /// Only process images under these `static/` subdirectories (all if empty)
+    pub directories: Vec<String>,
+    /// Widths of the narrower copies generated for responsive `srcset` markup
+    pub widths: Vec<u32>,
+}
+
+impl Default for ImagesConfig {
+    fn default() -> Self {
+        ImagesConfig {
+            optimize: false,
+            max_width: 1600,
+            webp: true,
+            directories: Vec::new(),
+            widths: vec![480, 960],
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct SocialCardsConfig {
+    /// Render a preview image for every post without its own `image:`, into
+    /// `og/`, for the Open Graph and Twitter Card tags
+    pub enabled: bool,
+    /// Card background, as `#rrggbb`
+    pub background: String,
+    /// Text color, as `#rrggbb`
+    pub foreground: String,
+    /// TrueType/OpenType font to draw with instead of the built-in DejaVu Sans Bold
+    pub font: String,
+}
+
+impl Default for SocialCardsConfig {
+    fn default() -> Self {
+        SocialCardsConfig {
+            enabled: false,
+            background: "#1e293b".to_string(),
+            foreground: "#f8fafc".to_string(),
+            font: String::new(),
+        }
+    }
+}
+
+/// Shell commands run around every build, like `tailwindcss` or `pagefind`.
+/// They see the build's directories as `BREAD_CONTENT_DIR`,
+/// `BREAD_OUTPUT_DIR`, `BREAD_TEMPLATE_DIR` and `BREAD_STATIC_DIR`, and the
+/// site's address as `BREAD_BASE_URL`.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct HooksConfig {
+    /// Run before anything is read, so they can generate content or assets
+    pub pre_build: Vec<String>,
+    /// Run once the site has been written to the output directory
+    pub post_build: Vec<String>,
+}
+
+/// How dates are shown. Formats use strftime syntax, like `%B %d, %Y`.
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct DatesConfig {
+    /// Used for the dates in posts listings and by the `{date | date}`
+    /// template formatter
+    pub format: String,
+    /// Further formats by name, each available in templates as
+    /// `{date | date_<name>}`
+    pub formats: BTreeMap<String, String>,
+}
+
+impl Default for DatesConfig {
+    fn default() -> Self {
+        DatesConfig {
+            format: "%Y-%m-%d".to_string(),
+            formats: BTreeMap::new(),
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct AuthorConfig {
+    /// Display name; the key if empty
+    pub name: String,
+    pub bio: String,
+    /// URL of the author's picture
+    pub avatar: String,
+    /// Profiles and homepages, like `github = "https://github.com/..."`
+    pub links: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct MenuEntryConfig {
+    pub name: String,
+    /// Site-relative, like `/about.html`
+    pub url: String,
+    /// Items are listed lightest first
+    pub weight: i32,
+}
+
+/// Cleaning up the output directory after a build.
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct PruneConfig {
+    /// Delete files in the output directory the build didn't write, like the
+    /// pages of deleted or renamed posts
+    pub enabled: bool,
+    /// Globs, in `.breadignore` syntax, for files in the output directory to
+    /// keep regardless. By default `.git` and the `CNAME` and `.nojekyll`
+    /// files GitHub Pages reads, which are often put there by hand
+    pub protect: Vec<String>,
+}
+
+impl Default for PruneConfig {
+    fn default() -> Self {
+        PruneConfig {
+            enabled: true,
+            protect: vec![
+                ".git".to_string(),
+                "CNAME".to_string(),
+                ".nojekyll".to_string(),
+            ],
+        }
+    }
+}
+
+/// Converters for content written in formats other than markdown.
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct FormatsConfig {
+    /// Command turning an `.adoc` file on stdin into an HTML fragment on
+    /// stdout
+    pub asciidoc_command: Vec<String>,
+    /// Command turning an `.org` file on stdin into an HTML fragment on
+    /// stdout
+    pub org_command: Vec<String>,
+}
+
+impl Default for FormatsConfig {
+    fn default() -> Self {
+        FormatsConfig {
+            asciidoc_command: ["asciidoctor", "--embedded", "--out-file", "-", "-"]
+                .map(String::from)
+                .to_vec(),
+            org_command: ["pandoc", "--from", "org", "--to", "html"]
+                .map(String::from)
+                .to_vec(),
+        }
+    }
+}
+
+/// A Gemini capsule of the site, written alongside the HTML.
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct GeminiConfig {
+    /// Also write every page as gemtext, with an `index.gmi` listing the
+    /// posts
+    pub enabled: bool,
+    /// Where in the output directory the capsule goes
+    pub dir: String,
+}
+
+impl Default for GeminiConfig {
+    fn default() -> Self {
+        GeminiConfig {
+            enabled: false,
+            dir: "gemini".to_string(),
+        }
+    }
+}
+
+/// A static ActivityPub export of the posts: an actor, its outbox and an
+/// object for every post, as JSON files the fediverse can look up. Followers
+/// can't be tracked without a server, so following the blog needs its
+/// `.well-known/webfinger` and an `inbox` elsewhere, like a bridge service,
+/// and the host has to serve the files as `application/activity+json`.
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct ActivityPubConfig {
+    pub enabled: bool,
+    /// Where in the output directory the files go
+    pub dir: String,
+    /// The actor's handle, the `blog` in `@blog@example.com`
+    pub username: String,
+    /// The actor's display name; the site title if empty
+    pub name: String,
+    /// The actor's bio; the site description if empty
+    pub summary: String,
+    /// URL of the actor's avatar
+    pub icon: String,
+    /// Whether posts are published as `Article`s, with their full content,
+    /// or as `Note`s, with their summary and a link
+    pub object_type: ActivityType,
+    /// URL of an inbox that receives follows and replies for the actor
+    pub inbox: String,
+    /// The actor's public key, PEM-encoded, for servers checking signatures
+    pub public_key: String,
+    /// Most posts in the outbox, newest first; all of them if unset
+    pub limit: Option<usize>,
+}
+
+impl Default for ActivityPubConfig {
+    fn default() -> Self {
+        ActivityPubConfig {
+            enabled: false,
+            dir: "activitypub".to_string(),
+            username: "blog".to_string(),
+            name: String::new(),
+            summary: String::new(),
+            icon: String::new(),
+            object_type: ActivityType::Article,
+            inbox: String::new(),
+            public_key: String::new(),
+            limit: Some(20),
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ActivityType {
+    #[default]
+    Article,
+    Note,
+}
+
+/// Versions of every page for readers and tools that don't want HTML,
+/// written next to it and linked from its `<head>`.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct AlternatesConfig {
+    /// Write the page as plain text to `<page>.txt`
+    pub text: bool,
+    /// Publish the markdown source of markdown pages as `<page>.md`
+    pub markdown: bool,
+}
+
+/// HTML added to every page, like an analytics script, so it needn't be in
+/// every template.
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct SnippetsConfig {
+    /// Inserted at the end of `<head>`
+    pub head: String,
+    /// Inserted at the end of `<body>`
+    pub body: String,
+    /// The profiles that get the snippets; development builds shouldn't
+    /// count as visits
+    pub profiles: Vec<String>,
+}
+
+impl Default for SnippetsConfig {
+    fn default() -> Self {
+        SnippetsConfig {
+            head: String::new(),
+            body: String::new(),
+            profiles: vec![DEFAULT_PROFILE.to_string()],
+        }
+    }
+}
+
+/// How links to other sites are marked up. A link is external when it's an
+/// absolute URL outside the `base_url`.
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct ExternalLinksConfig {
+    /// Give external links `rel="noopener noreferrer"`
+    pub enabled: bool,
+    /// Also open them in a new tab
+    pub new_tab: bool,
+    /// Class added to external links, for styling them with an icon; none if
+    /// empty
+    pub class: String,
+}
+
+impl Default for ExternalLinksConfig {
+    fn default() -> Self {
+        ExternalLinksConfig {
+            enabled: false,
+            new_tab: false,
+            class: "external-link".to_string(),
+        }
+    }
+}
+
+/// Pages with `protected: true`, published encrypted and unlocked in the
+/// browser with a password. Encrypting them needs `openssl`.
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct ProtectedConfig {
+    /// Password for protected pages that don't give their own `password:`
+    pub password: String,
+    /// Rounds of PBKDF2 turning the password into the key; more make
+    /// guessing it slower, and unlocking it too
+    pub iterations: u32,
+}
+
+impl Default for ProtectedConfig {
+    fn default() -> Self {
+        ProtectedConfig {
+            password: String::new(),
+            iterations: 100_000,
+        }
+    }
+}
+
+/// Files at fixed, well-known paths, generated from these settings rather
+/// than kept in `static/`.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct WellKnownConfig {
+    pub security: SecurityTxtConfig,
+    /// Write a `humans.txt` crediting the people in `[authors]`
+    pub humans: bool,
+    /// Text added at the end of `humans.txt`, like thanks or the tools used
+    pub humans_note: String,
+    pub webfinger: WebfingerConfig,
+}
+
+/// `.well-known/security.txt`, telling security researchers how to report
+/// problems; written when there's a contact. See RFC 9116 for the fields.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct SecurityTxtConfig {
+    /// Where to send reports, like `mailto:security@example.com`
+    pub contact: Vec<String>,
+    /// When the file should no longer be trusted, as an RFC 3339 timestamp;
+    /// a year after the build if empty
+    pub expires: String,
+    /// URLs of keys to encrypt reports with
+    pub encryption: Vec<String>,
+    pub acknowledgments: String,
+    pub policy: String,
+    pub hiring: String,
+    pub preferred_languages: Vec<String>,
+}
+
+/// A static `.well-known/webfinger` answering for one account, so
+/// `@me@example.com` can be found from the fediverse; written when there's
+/// a subject. Most hosts need telling to serve it as JSON.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct WebfingerConfig {
+    /// The account, like `acct:me@example.com`; with `activitypub` on, its
+    /// actor's account by default
+    pub subject: String,
+    /// Other URLs for the account, like its profile on another server
+    pub aliases: Vec<String>,
+    /// The account's profile page
+    pub profile: String,
+    /// The account's ActivityPub actor, for following it; the exported
+    /// actor by default
+    pub actor: String,
+}
+
+/// Endpoints other sites notify when they link to a page, advertised with
+/// `<link>`s in every page's head. Bread doesn't receive them itself; point
+/// these at a service like webmention.io.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct IndieWebConfig {
+    pub webmention: String,
+    pub pingback: String,
+}
+
+/// How seriously `bread check` takes each accessibility problem it finds in
+/// the built pages. Errors fail the check, warnings are only reported.
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct AccessibilityConfig {
+    /// An `<img>` without an `alt` attribute; `alt=""` marks a decorative
+    /// image and is fine
+    pub missing_alt: Severity,
+    /// A heading more than one level below the one before it, like an `<h4>`
+    /// straight after an `<h2>`
+    pub skipped_heading: Severity,
+    /// A link with no text, image text or `aria-label` to read out
+    pub empty_link: Severity,
+    /// An `<html>` element without a `lang`
+    pub missing_lang: Severity,
+}
+
+impl Default for AccessibilityConfig {
+    fn default() -> Self {
+        AccessibilityConfig {
+            missing_alt: Severity::Error,
+            skipped_heading: Severity::Warning,
+            empty_link: Severity::Error,
+            missing_lang: Severity::Error,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Off,
+    #[default]
+    Warning,
+    Error,
+}
+
+/// What `bread lint` looks for in the prose of markdown content. Errors
+/// fail the lint, warnings are only reported.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct LintConfig {
+    /// Commonly misspelled words, from the built-in list and `misspellings`
+    pub spelling: Severity,
+    /// Words never to flag as misspelled, like names the built-in list
+    /// gets wrong
+    pub words: Vec<String>,
+    /// More misspellings to look for, with what they should be
+    pub misspellings: BTreeMap<String, String>,
+    /// Style rules of the site's own, checked on every line of prose
+    pub rules: Vec<LintRule>,
+}
+
+/// A `[[lint.rules]]` entry: prose matching `pattern` gets `message`.
+#[derive(Debug, Deserialize)]
+pub struct LintRule {
+    pub name: String,
+    /// A regular expression, like `\b(very|really)\b`
+    pub pattern: String,
+    pub message: String,
+    #[serde(default)]
+    pub severity: Severity,
+    /// Match the pattern regardless of case
+    #[serde(default)]
+    pub ignore_case: bool,
+}
+
+/// The page and OPML file made from `data/blogroll.yaml`, see
+/// `blogroll::generate_blogroll`.
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct BlogrollConfig {
+    pub title: String,
+    /// A line introducing the list on the page
+    pub description: String,
+}
+
+impl Default for BlogrollConfig {
+    fn default() -> Self {
+        BlogrollConfig {
+            title: "Blogroll".to_string(),
+            description: String::new(),
+        }
+    }
+}
+
+/// The repository the site's sources live in, for "edit this page" links.
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct RepositoryConfig {
+    /// Web address of the repository, like `https://github.com/user/site`;
+    /// no edit links without it
+    pub url: String,
+    /// Branch edits are made against
+    pub branch: String,
+    /// Where the content directory is within the repository
+    pub content_dir: String,
+}
+
+impl Default for RepositoryConfig {
+    fn default() -> Self {
+        RepositoryConfig {
+            url: String::new(),
+            branch: "main".to_string(),
+            content_dir: "content".to_string(),
+        }
+    }
+}
+
+impl RepositoryConfig {
+    /// Where to edit the content file at `path` (relative to the content
+    /// directory) in the repository's web editor, GitHub-style.
+    pub fn edit_url(&self, path: &str) -> Option<String> {
+        if self.url.is_empty() {
+            return None;
+        }
+        let dir = self.content_dir.trim_matches('/');
+        let path = if dir.is_empty() {
+            path.to_string()
+        } else {
+            format!("{}/{}", dir, path)
+        };
+        Some(format!(
+            "{}/edit/{}/{}",
+            self.url.trim_end_matches('/'),
+            self.branch,
+            path
+        ))
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct PluginsConfig {
+    /// WebAssembly modules that transform every page's markdown, applied in
+    /// order; see `WasmPlugin` for what they have to export
+    pub wasm: Vec<PathBuf>,
+}
+
+/// Where `bread deploy` publishes the site.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum DeployTarget {
+    /// Commit to a branch of this repository and push it
+    Git,
+    /// Copy changed files to a server over SSH
+    Ssh,
+    /// Upload changed files to an S3-compatible bucket with the AWS CLI
+    S3,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct DeployConfig {
+    /// Where to deploy when `--target` isn't given
+    pub target: DeployTarget,
+    /// Branch `bread deploy` commits the built site to
+    pub branch: String,
+    /// Git remote that branch is pushed to
+    pub remote: String,
+    /// SSH destination for the `ssh` target, like `deploy@example.com`
+    pub host: String,
+    /// Directory on `host` the site is served from
+    pub path: String,
+    /// Port to connect to instead of SSH's default
+    pub port: Option<u16>,
+    /// Bucket for the `s3` target
+    pub bucket: String,
+    /// Key prefix the site is uploaded under, if not the bucket's root
+    pub prefix: String,
+    /// Endpoint of an S3-compatible service other than AWS, like
+    /// `https://<account>.r2.cloudflarestorage.com`
+    pub endpoint: String,
+}
+
+impl Default for DeployConfig {
+    fn default() -> Self {
+        DeployConfig {
+            target: DeployTarget::Git,
+            branch: "gh-pages".to_string(),
+            remote: "origin".to_string(),
+            host: String::new(),
+            path: String::new(),
+            port: None,
+            bucket: String::new(),
+            prefix: String::new(),
+            endpoint: String::new(),
+        }
+    }
+}
+
+impl Config {
+    /// Where the site's theme lives, if it has one.
+    pub fn theme_dir(&self) -> Option<PathBuf> {
+        (!self.theme.is_empty()).then(|| Path::new(THEMES_DIR).join(&self.theme))
+    }
+
+    /// The path part of `base_url`, always ending in `/`.
+    pub fn base_path(&self) -> String {
+        let path = match self.base_url.split_once("://") {
+            Some((_, rest)) => rest.find('/').map_or("/", |i| &rest[i..]),
+            None => &self.base_url,
+        };
+        format!("/{}/", path.trim_matches('/')).replace("//", "/")
+    }
+
+    /// The directories content is read from: `content_dir`, followed by
+    /// those in `content_dirs` that aren't the same.
+    pub fn content_roots(&self, content_dir: &Path) -> Vec<PathBuf> {
+        let mut roots = vec![content_dir.to_path_buf()];
+        for dir in &self.content_dirs {
+            if !roots.contains(dir) {
+                roots.push(dir.clone());
+            }
+        }
+        roots
+    }
+
+    /// The language of pages that don't say otherwise; empty if the site
+    /// doesn't list its languages.
+    pub fn default_language(&self) -> &str {
+        self.languages.first().map_or("", String::as_str)
+    }
+
+    /// The URL of the posts listing in `lang`, without the base path and
+    /// `index.html`, if there is a listing.
+    pub fn listing_url(&self, lang: &str) -> Option<String> {
+        if !self.posts.listing {
+            return None;
+        }
+        let path = self.posts.listing_path.trim_start_matches('/');
+        let path = path.strip_suffix("index.html").unwrap_or(path);
+        Some(format!("{}/{}", self.language_prefix(lang), path))
+    }
+
+    /// What URLs of pages in `lang` start with: nothing for the default
+    /// language, `/<lang>` for the others.
+    pub fn language_prefix(&self, lang: &str) -> String {
+        if lang.is_empty() || lang == self.default_language() {
+            String::new()
+        } else {
+            format!("/{}", lang)
+        }
+    }
+
+    /// `path` (site-relative) as a full URL under `base_url`. Without a host
+    /// in `base_url` this is just the path under the base.
+    pub fn absolute_url(&self, path: &str) -> String {
+        format!(
+            "{}/{}",
+            self.base_url.trim_end_matches('/'),
+            path.trim_start_matches('/')
+        )
+    }
+
+    /// Reads the config for `profile`: the settings at the top level of the
+    /// file, overridden by those in its `[profiles.<profile>]` table. The
+    /// `dev` and `prod` profiles needn't be in the file; `dev` builds drafts
+    /// and doesn't minify unless its table says otherwise.
+    pub fn load(path: &Path, profile: &str) -> io::Result<Self> {
+        let invalid = |e: toml::de::Error| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{}: {}", path.display(), e),
+            )
+        };
+        let mut table = if path.exists() {
+            toml::from_str::<toml::Table>(&fs::read_to_string(path)?).map_err(invalid)?
+        } else {
+            toml::Table::new()
+        };
+
+        if profile == "dev" {
+            table.insert("drafts".to_string(), toml::Value::Boolean(true));
+            table.insert("minify".to_string(), toml::Value::Boolean(false));
+        }
+        let overrides = match table.remove("profiles") {
+            Some(toml::Value::Table(mut profiles)) => profiles.remove(profile),
+            _ => None,
+        };
+        match overrides {
+            Some(toml::Value::Table(overrides)) => merge(&mut table, overrides),
+            Some(_) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("{}: profiles.{} isn't a table", path.display(), profile),
+                ));
+            }
+            None if profile != "dev" && profile != "prod" => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("{}: no [profiles.{}] table", path.display(), profile),
+                ));
+            }
+            None => {}
+        }
+
+        let mut config: Config = toml::Value::Table(table).try_into().map_err(invalid)?;
+        config.profile = profile.to_string();
+        Ok(config)
+    }
+}
+
+/// Copies the settings in `overrides` over those in `table`, descending into
+/// tables present in both so an override only replaces the keys it sets.
+fn merge(table: &mut toml::Table, overrides: toml::Table) {
+    for (key, value) in overrides {
+        match (table.get_mut(&key), value) {
+            (Some(toml::Value::Table(existing)), toml::Value::Table(value)) => {
+                merge(existing, value)
+            }
+            (_, value) => {
+                table.insert(key, value);
+            }
+        }
+    }
+}
@@ -0,0 +1,19 @@
+// Adds a copy button to the code blocks bread marked with `data-copyable`.
+document.addEventListener('DOMContentLoaded', function () {
+    document.querySelectorAll('[data-copyable]').forEach(function (block) {
+        const button = document.createElement('button');
+        button.type = 'button';
+        button.className = 'copy-button';
+        button.textContent = 'Copy';
+        button.addEventListener('click', function () {
+            const code = block.querySelector('pre');
+            navigator.clipboard.writeText(code.innerText).then(function () {
+                button.textContent = 'Copied';
+                setTimeout(function () {
+                    button.textContent = 'Copy';
+                }, 2000);
+            });
+        });
+        block.appendChild(button);
+    });
+});
@@ -0,0 +1,47 @@
+use serde_json::{Map, Value};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Reads every JSON, TOML and YAML file under `dir` into one object for
+/// templates, keyed by file stem, so `data/nav.yaml` becomes `data.nav`.
+/// Subdirectories become nested objects: `data/projects/rust.toml` is
+/// `data.projects.rust`. Other files are ignored.
+pub fn load(dir: &Path) -> io::Result<Value> {
+    let mut data = Map::new();
+    if !dir.is_dir() {
+        return Ok(Value::Object(data));
+    }
+
+    let mut entries: Vec<_> = fs::read_dir(dir)?.collect::<Result<_, _>>()?;
+    entries.sort_by_key(|entry| entry.path());
+
+    for entry in entries {
+        let path = entry.path();
+        let Some(stem) = path.file_stem().and_then(|s| s.to_str()) else {
+            continue;
+        };
+
+        if path.is_dir() {
+            data.insert(stem.to_string(), load(&path)?);
+            continue;
+        }
+
+        let text = || fs::read_to_string(&path);
+        let value = match path.extension().and_then(|s| s.to_str()) {
+            Some("json") => serde_json::from_str(&text()?).map_err(|e| e.to_string()),
+            Some("toml") => toml::from_str(&text()?).map_err(|e| e.to_string()),
+            Some("yaml" | "yml") => serde_yaml::from_str(&text()?).map_err(|e| e.to_string()),
+            _ => continue,
+        };
+        let value = value.map_err(|e| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{}: {}", path.display(), e),
+            )
+        })?;
+        data.insert(stem.to_string(), value);
+    }
+
+    Ok(Value::Object(data))
+}
@@ -0,0 +1,50 @@
+use chrono::format::StrftimeItems;
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime};
+use std::fmt::Write;
+use std::io;
+
+/// Date and time layouts accepted in frontmatter besides RFC 3339, which
+/// covers timestamps with a UTC offset. Dates without one are taken as UTC.
+const NAIVE_LAYOUTS: [&str; 3] = ["%Y-%m-%dT%H:%M:%S", "%Y-%m-%d %H:%M:%S", "%Y-%m-%d %H:%M"];
+
+/// Parses a frontmatter `date:`, either a plain `YYYY-MM-DD` day or one
+/// with a time of day.
+pub fn parse(date: &str) -> Option<DateTime<FixedOffset>> {
+    let date = date.trim();
+    if let Ok(datetime) = DateTime::parse_from_rfc3339(date) {
+        return Some(datetime);
+    }
+    NAIVE_LAYOUTS
+        .iter()
+        .find_map(|layout| NaiveDateTime::parse_from_str(date, layout).ok())
+        .or_else(|| {
+            NaiveDate::parse_from_str(date, "%Y-%m-%d")
+                .ok()
+                .and_then(|day| day.and_hms_opt(0, 0, 0))
+        })
+        .map(|datetime| datetime.and_utc().fixed_offset())
+}
+
+/// Shows `date` in a strftime-style `format`. Dates that don't parse are
+/// passed through as written.
+pub fn format(date: &str, format: &str) -> String {
+    let Some(datetime) = parse(date) else {
+        return date.to_string();
+    };
+    let mut formatted = String::new();
+    match write!(formatted, "{}", datetime.format(format)) {
+        Ok(()) => formatted,
+        Err(_) => date.to_string(),
+    }
+}
+
+/// Checks a configured display format up front, so mistakes in it fail the
+/// build instead of quietly printing dates unformatted.
+pub fn validate_format(format: &str) -> io::Result<()> {
+    StrftimeItems::new(format).parse().map(|_| ()).map_err(|e| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("invalid date format '{}': {}", format, e),
+        )
+    })
+}
@@ -0,0 +1,44 @@
+// Unlocks the password-protected content bread encrypted into
+// `data-protected`: OpenSSL's salted AES-256-CBC, with the key and IV
+// derived from the password by PBKDF2-SHA256.
+document.addEventListener('DOMContentLoaded', function () {
+    const MARKER = '<!--bread-protected-->';
+
+    async function decrypt(data, password, iterations) {
+        const bytes = Uint8Array.from(atob(data), function (c) {
+            return c.charCodeAt(0);
+        });
+        // `Salted__`, then eight bytes of salt, then the ciphertext
+        const salt = bytes.slice(8, 16);
+        const material = await crypto.subtle.importKey(
+            'raw', new TextEncoder().encode(password), 'PBKDF2', false, ['deriveBits']);
+        const bits = new Uint8Array(await crypto.subtle.deriveBits(
+            { name: 'PBKDF2', salt: salt, iterations: iterations, hash: 'SHA-256' },
+            material, 384));
+        const key = await crypto.subtle.importKey(
+            'raw', bits.slice(0, 32), 'AES-CBC', false, ['decrypt']);
+        const plain = await crypto.subtle.decrypt(
+            { name: 'AES-CBC', iv: bits.slice(32, 48) }, key, bytes.slice(16));
+        return new TextDecoder().decode(plain);
+    }
+
+    document.querySelectorAll('[data-protected]').forEach(function (block) {
+        const form = block.querySelector('form');
+        const error = block.querySelector('.protected-error');
+        form.addEventListener('submit', function (event) {
+            event.preventDefault();
+            const password = form.querySelector('input[type=password]').value;
+            const iterations = parseInt(block.dataset.iterations, 10);
+            decrypt(block.dataset.protected, password, iterations)
+                .then(function (html) {
+                    if (!html.startsWith(MARKER)) {
+                        throw new Error('wrong password');
+                    }
+                    block.outerHTML = html.slice(MARKER.length);
+                })
+                .catch(function () {
+                    error.hidden = false;
+                });
+        });
+    });
+});
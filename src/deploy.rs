@@ -0,0 +1,477 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use crate::assets::{find_files, fnv1a};
+use crate::config::DeployConfig;
+
+/// Kept next to the deployed files, recording the hash of each one as of the
+/// last deploy so the next only has to send what changed.
+const MANIFEST_NAME: &str = ".bread-manifest.json";
+
+/// Commits the contents of `output_dir` to `branch` and pushes it to the
+/// configured remote. The commit is built with a throwaway index, so neither
+/// the working tree nor the checked-out branch is touched, and it goes on top
+/// of the branch's previous deploy when there is one.
+pub fn push_to_branch(
+    output_dir: &Path,
+    branch: &str,
+    message: &str,
+    config: &DeployConfig,
+) -> io::Result<()> {
+    let output_dir = output_dir.canonicalize()?;
+    let git_dir = git(&["rev-parse", "--absolute-git-dir"], &[])?;
+    let index = Path::new(&git_dir).join("bread-deploy-index");
+    let work_tree = output_dir.to_string_lossy();
+    let env = [("GIT_INDEX_FILE", index.to_string_lossy().into_owned())];
+
+    let _ = fs::remove_file(&index);
+    let tree = git(&["--work-tree", &work_tree, "add", "--all", "."], &env)
+        .and_then(|_| git(&["write-tree"], &env));
+    let _ = fs::remove_file(&index);
+    let tree = tree?;
+
+    let local_ref = format!("refs/heads/{}", branch);
+    let remote_ref = format!("refs/remotes/{}/{}", config.remote, branch);
+    let parent = [&local_ref, &remote_ref]
+        .into_iter()
+        .find_map(|name| git(&["rev-parse", "--verify", "--quiet", name], &[]).ok());
+
+    let mut commit_args = vec!["commit-tree", &tree, "-m", message];
+    if let Some(parent) = &parent {
+        if git(&["rev-parse", &format!("{}^{{tree}}", parent)], &[])? == tree {
+            println!("  ℹ Nothing changed since the last deploy to {}", branch);
+            return Ok(());
+        }
+        commit_args.extend(["-p", parent]);
+    }
+    let commit = git(&commit_args, &[])?;
+    git(&["update-ref", &local_ref, &commit], &[])?;
+    println!(
+        "  📝 Committed {} to {}",
+        &commit[..commit.len().min(8)],
+        branch
+    );
+
+    git(
+        &[
+            "push",
+            &config.remote,
+            &format!("{}:{}", local_ref, local_ref),
+        ],
+        &[],
+    )?;
+    println!("  🚀 Pushed {} to {}", branch, config.remote);
+
+    Ok(())
+}
+
+/// Copies the contents of `output_dir` to `path` on `host`, sending only the
+/// files whose contents changed since the last deploy and removing those
+/// that are gone. What was deployed is tracked by a manifest of file hashes
+/// kept in the remote directory; the remote side just needs `sh` and `tar`.
+pub fn sync_over_ssh(output_dir: &Path, config: &DeployConfig) -> io::Result<()> {
+    if config.host.is_empty() || config.path.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "deploy.host and deploy.path must be set to deploy over SSH",
+        ));
+    }
+    let remote_dir = shell_quote(&config.path);
+
+    let local = output_manifest(output_dir)?;
+    let remote_json = ssh(
+        config,
+        &format!("cat {}/{} 2>/dev/null || true", remote_dir, MANIFEST_NAME),
+        None,
+    )?;
+    let remote: BTreeMap<String, String> = if remote_json.trim_ascii().is_empty() {
+        BTreeMap::new()
+    } else {
+        serde_json::from_slice(&remote_json).map_err(|e| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{}:{}/{}: {}", config.host, config.path, MANIFEST_NAME, e),
+            )
+        })?
+    };
+
+    let changed: Vec<&str> = local
+        .iter()
+        .filter(|(file, hash)| remote.get(*file) != Some(hash))
+        .map(|(file, _)| file.as_str())
+        .collect();
+    let removed: Vec<&str> = remote
+        .keys()
+        .filter(|file| !local.contains_key(*file))
+        .map(String::as_str)
+        .collect();
+
+    if changed.is_empty() && removed.is_empty() {
+        println!("  ℹ Nothing changed since the last deploy");
+        return Ok(());
+    }
+
+    if !changed.is_empty() {
+        upload(output_dir, &changed, &remote_dir, config)?;
+        println!("  📤 Uploaded {} changed file(s)", changed.len());
+    }
+    if !removed.is_empty() {
+        let files: Vec<String> = removed.iter().map(|file| shell_quote(file)).collect();
+        ssh(
+            config,
+            &format!("cd {} && rm -f -- {}", remote_dir, files.join(" ")),
+            None,
+        )?;
+        println!("  🗑  Removed {} deleted file(s)", removed.len());
+    }
+
+    let manifest = serde_json::to_vec_pretty(&local).map_err(io::Error::other)?;
+    ssh(
+        config,
+        &format!("cat > {}/{}", remote_dir, MANIFEST_NAME),
+        Some(&manifest),
+    )?;
+    println!("  🚀 Deployed to {}:{}", config.host, config.path);
+
+    Ok(())
+}
+
+/// Uploads the contents of `output_dir` to an S3 bucket through the `aws`
+/// CLI, which brings its own credentials. Like the SSH target, only files
+/// whose contents changed since the last deploy are sent, tracked by a
+/// manifest stored in the bucket; objects under the prefix that no longer
+/// exist locally are deleted.
+///
+/// Every object gets a `Content-Type` from its extension. Fingerprinted
+/// assets are cached for a year as immutable, while everything else has to
+/// be revalidated, so a deploy shows up right away.
+pub fn sync_to_s3(output_dir: &Path, config: &DeployConfig) -> io::Result<()> {
+    if config.bucket.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "deploy.bucket must be set to deploy to S3",
+        ));
+    }
+    let prefix = match config.prefix.trim_matches('/') {
+        "" => String::new(),
+        prefix => format!("{}/", prefix),
+    };
+    let manifest_key = format!("{}{}", prefix, MANIFEST_NAME);
+
+    let local = output_manifest(output_dir)?;
+    let immutable = fingerprinted_files(output_dir)?;
+
+    let listing = aws(
+        config,
+        &[
+            "s3api",
+            "list-objects-v2",
+            "--bucket",
+            &config.bucket,
+            "--prefix",
+            &prefix,
+            "--query",
+            "Contents[].Key",
+            "--output",
+            "json",
+        ],
+    )?;
+    let remote_keys: Vec<String> = serde_json::from_slice::<Option<Vec<String>>>(&listing)
+        .map_err(io::Error::other)?
+        .unwrap_or_default();
+
+    let remote: BTreeMap<String, String> = if remote_keys.contains(&manifest_key) {
+        let json = aws(
+            config,
+            &[
+                "s3",
+                "cp",
+                &format!("s3://{}/{}", config.bucket, manifest_key),
+                "-",
+            ],
+        )?;
+        serde_json::from_slice(&json).map_err(|e| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("s3://{}/{}: {}", config.bucket, manifest_key, e),
+            )
+        })?
+    } else {
+        BTreeMap::new()
+    };
+
+    let mut uploaded = 0;
+    for (file, hash) in &local {
+        let key = format!("{}{}", prefix, file);
+        if remote.get(file) == Some(hash) && remote_keys.contains(&key) {
+            continue;
+        }
+        let cache_control = if immutable.contains(file) {
+            "public, max-age=31536000, immutable"
+        } else {
+            "public, max-age=0, must-revalidate"
+        };
+        aws(
+            config,
+            &[
+                "s3api",
+                "put-object",
+                "--bucket",
+                &config.bucket,
+                "--key",
+                &key,
+                "--body",
+                &output_dir.join(file).to_string_lossy(),
+                "--content-type",
+                content_type(file),
+                "--cache-control",
+                cache_control,
+            ],
+        )?;
+        uploaded += 1;
+    }
+    if uploaded > 0 {
+        println!("  📤 Uploaded {} changed file(s)", uploaded);
+    }
+
+    let stale: Vec<&String> = remote_keys
+        .iter()
+        .filter(|key| **key != manifest_key)
+        .filter(|key| !local.contains_key(&key[prefix.len()..]))
+        .collect();
+    // delete-objects takes at most 1000 keys per request
+    for batch in stale.chunks(1000) {
+        let objects: Vec<_> = batch
+            .iter()
+            .map(|key| serde_json::json!({ "Key": key }))
+            .collect();
+        let request = serde_json::json!({ "Objects": objects, "Quiet": true });
+        aws(
+            config,
+            &[
+                "s3api",
+                "delete-objects",
+                "--bucket",
+                &config.bucket,
+                "--delete",
+                &request.to_string(),
+            ],
+        )?;
+    }
+    if !stale.is_empty() {
+        println!("  🗑  Removed {} deleted file(s)", stale.len());
+    }
+
+    if uploaded == 0 && stale.is_empty() {
+        println!("  ℹ Nothing changed since the last deploy");
+        return Ok(());
+    }
+
+    let manifest_path = output_dir.join(MANIFEST_NAME);
+    fs::write(
+        &manifest_path,
+        serde_json::to_vec_pretty(&local).map_err(io::Error::other)?,
+    )?;
+    let result = aws(
+        config,
+        &[
+            "s3api",
+            "put-object",
+            "--bucket",
+            &config.bucket,
+            "--key",
+            &manifest_key,
+            "--body",
+            &manifest_path.to_string_lossy(),
+            "--content-type",
+            "application/json",
+        ],
+    );
+    fs::remove_file(&manifest_path)?;
+    result?;
+    println!("  🚀 Deployed to s3://{}/{}", config.bucket, prefix);
+
+    Ok(())
+}
+
+/// Published names of the fingerprinted assets, from the build's
+/// `asset-manifest.json`.
+fn fingerprinted_files(output_dir: &Path) -> io::Result<Vec<String>> {
+    let path = output_dir.join("asset-manifest.json");
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let entries: BTreeMap<String, String> =
+        serde_json::from_slice(&fs::read(&path)?).map_err(io::Error::other)?;
+    Ok(entries
+        .into_iter()
+        .filter(|(original, published)| original != published)
+        .map(|(_, published)| published)
+        .collect())
+}
+
+/// The `Content-Type` to serve a file with, going by its extension.
+fn content_type(file: &str) -> &'static str {
+    let extension = file.rsplit_once('.').map_or("", |(_, ext)| ext);
+    match extension.to_ascii_lowercase().as_str() {
+        "html" | "htm" => "text/html; charset=utf-8",
+        "css" => "text/css; charset=utf-8",
+        "js" | "mjs" => "text/javascript; charset=utf-8",
+        "json" | "map" => "application/json",
+        "xml" => "application/xml",
+        "txt" => "text/plain; charset=utf-8",
+        "md" => "text/markdown; charset=utf-8",
+        "svg" => "image/svg+xml",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "avif" => "image/avif",
+        "ico" => "image/x-icon",
+        "woff" => "font/woff",
+        "woff2" => "font/woff2",
+        "ttf" => "font/ttf",
+        "otf" => "font/otf",
+        "pdf" => "application/pdf",
+        "mp4" => "video/mp4",
+        "webm" => "video/webm",
+        "mp3" => "audio/mpeg",
+        "wasm" => "application/wasm",
+        _ => "application/octet-stream",
+    }
+}
+
+/// Runs the AWS CLI against the configured endpoint, returning its stdout.
+fn aws(config: &DeployConfig, args: &[&str]) -> io::Result<Vec<u8>> {
+    let mut command = Command::new("aws");
+    if !config.endpoint.is_empty() {
+        command.arg("--endpoint-url").arg(&config.endpoint);
+    }
+    let output = command.args(args).output()?;
+
+    if !output.status.success() {
+        return Err(io::Error::other(format!(
+            "aws {} failed: {}",
+            args[..2.min(args.len())].join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(output.stdout)
+}
+
+/// Every file under `output_dir`, by `/`-separated relative path, with a
+/// hash of its contents.
+fn output_manifest(output_dir: &Path) -> io::Result<BTreeMap<String, String>> {
+    let mut manifest = BTreeMap::new();
+    for file in find_files(output_dir)? {
+        let relative = file
+            .strip_prefix(output_dir)
+            .unwrap_or(&file)
+            .to_string_lossy()
+            .replace('\\', "/");
+        manifest.insert(relative, format!("{:016x}", fnv1a(&fs::read(&file)?)));
+    }
+    Ok(manifest)
+}
+
+/// Streams `files` to the remote directory as a tar archive.
+fn upload(
+    output_dir: &Path,
+    files: &[&str],
+    remote_dir: &str,
+    config: &DeployConfig,
+) -> io::Result<()> {
+    let mut tar = Command::new("tar")
+        .arg("-cf")
+        .arg("-")
+        .arg("-C")
+        .arg(output_dir)
+        .args(["--null", "-T", "-"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()?;
+
+    let archive = tar
+        .stdout
+        .take()
+        .map(Stdio::from)
+        .unwrap_or_else(Stdio::null);
+    let mut extract = ssh_command(
+        config,
+        &format!("mkdir -p {0} && tar -xf - -C {0}", remote_dir),
+    )
+    .stdin(archive)
+    .spawn()?;
+
+    if let Some(mut stdin) = tar.stdin.take() {
+        for file in files {
+            stdin.write_all(file.as_bytes())?;
+            stdin.write_all(b"\0")?;
+        }
+    }
+    let status = extract.wait()?;
+    if !tar.wait()?.success() || !status.success() {
+        return Err(io::Error::other(format!(
+            "uploading to {}:{} failed",
+            config.host, config.path
+        )));
+    }
+    Ok(())
+}
+
+fn ssh_command(config: &DeployConfig, remote_command: &str) -> Command {
+    let mut command = Command::new("ssh");
+    if let Some(port) = config.port {
+        command.arg("-p").arg(port.to_string());
+    }
+    command.arg(&config.host).arg(remote_command);
+    command
+}
+
+/// Runs `remote_command` on the deploy host, feeding it `input`, and returns
+/// its stdout.
+fn ssh(config: &DeployConfig, remote_command: &str, input: Option<&[u8]>) -> io::Result<Vec<u8>> {
+    let mut child = ssh_command(config, remote_command)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(input.unwrap_or_default())?;
+    }
+    let output = child.wait_with_output()?;
+
+    if !output.status.success() {
+        return Err(io::Error::other(format!(
+            "ssh {} failed: {}",
+            config.host,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(output.stdout)
+}
+
+/// Quotes `text` for a POSIX shell.
+fn shell_quote(text: &str) -> String {
+    format!("'{}'", text.replace('\'', "'\\''"))
+}
+
+/// Runs git in the current directory, returning its trimmed stdout.
+pub(crate) fn git(args: &[&str], env: &[(&str, String)]) -> io::Result<String> {
+    let output = Command::new("git")
+        .args(args)
+        .envs(env.iter().map(|(key, value)| (key, value)))
+        .output()?;
+
+    if !output.status.success() {
+        return Err(io::Error::other(format!(
+            "git {} failed: {}",
+            args.first().unwrap_or(&""),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
@@ -0,0 +1,76 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::assets::find_files;
+use crate::relative_path;
+
+/// How a fresh build differs from what's in the output directory, by
+/// output-relative path.
+#[derive(Debug, Default)]
+pub struct OutputDiff {
+    pub created: Vec<String>,
+    pub changed: Vec<String>,
+    pub deleted: Vec<String>,
+}
+
+/// Compares the build in `new_dir` to the one in `old_dir`, file by file.
+pub fn compare(new_dir: &Path, old_dir: &Path) -> io::Result<OutputDiff> {
+    let new_files = files_by_path(new_dir)?;
+    let old_files = files_by_path(old_dir)?;
+    let mut diff = OutputDiff::default();
+
+    for (path, new_file) in &new_files {
+        match old_files.get(path) {
+            None => diff.created.push(path.clone()),
+            Some(old_file) => {
+                if fs::read(new_file)? != fs::read(old_file)? {
+                    diff.changed.push(path.clone());
+                }
+            }
+        }
+    }
+    diff.deleted = old_files
+        .keys()
+        .filter(|path| !new_files.contains_key(*path))
+        .cloned()
+        .collect();
+
+    Ok(diff)
+}
+
+impl OutputDiff {
+    pub fn print(&self, output_dir: &str) {
+        println!("\n🔍 Changes to {}/\n", output_dir);
+        for path in &self.created {
+            println!("  + {}", path);
+        }
+        for path in &self.changed {
+            println!("  ~ {}", path);
+        }
+        for path in &self.deleted {
+            println!("  - {}", path);
+        }
+        if self.created.is_empty() && self.changed.is_empty() && self.deleted.is_empty() {
+            println!("  No changes");
+        } else {
+            println!(
+                "\n  {} created, {} changed, {} deleted",
+                self.created.len(),
+                self.changed.len(),
+                self.deleted.len()
+            );
+        }
+    }
+}
+
+fn files_by_path(dir: &Path) -> io::Result<BTreeMap<String, PathBuf>> {
+    if !dir.is_dir() {
+        return Ok(BTreeMap::new());
+    }
+    Ok(find_files(dir)?
+        .into_iter()
+        .map(|path| (relative_path(&path, dir), path))
+        .collect())
+}
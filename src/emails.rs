@@ -0,0 +1,123 @@
+/// Elements whose text isn't HTML, where entities wouldn't be decoded
+const RAW_TEXT_ELEMENTS: [&str; 2] = ["script", "style"];
+
+/// Writes the email addresses in `html`, in `mailto:` links, attributes and
+/// text, as numeric character references. Browsers show them as usual, but
+/// scrapers looking for `@` in the source find nothing.
+pub fn obfuscate(html: &str) -> String {
+    let mut output = String::with_capacity(html.len());
+    let mut rest = html;
+
+    while let Some(start) = rest.find('<') {
+        output.push_str(&obfuscate_text(&rest[..start]));
+        let Some(len) = rest[start..].find('>') else {
+            rest = &rest[start..];
+            break;
+        };
+        let tag = &rest[start..=start + len];
+        output.push_str(&obfuscate_text(&obfuscate_mailto(tag)));
+        rest = &rest[start + len + 1..];
+
+        // Leave the insides of scripts and styles as they are
+        let name = tag[1..]
+            .split(|c: char| c.is_whitespace() || c == '>')
+            .next()
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+        if RAW_TEXT_ELEMENTS.contains(&name.as_str()) {
+            let close = format!("</{}", name);
+            let end = rest.to_ascii_lowercase().find(&close).unwrap_or(rest.len());
+            output.push_str(&rest[..end]);
+            rest = &rest[end..];
+        }
+    }
+
+    output.push_str(&obfuscate_text(rest));
+    output
+}
+
+/// `tag` with the address of a `mailto:` link in it encoded.
+fn obfuscate_mailto(tag: &str) -> String {
+    let Some(start) = tag.find("mailto:") else {
+        return tag.to_string();
+    };
+    let value = &tag[start..];
+    let end = value.find(['"', '\'', ' ', '>']).unwrap_or(value.len());
+    format!(
+        "{}{}{}",
+        &tag[..start],
+        encode(&value[..end]),
+        &value[end..]
+    )
+}
+
+/// `text` with the email addresses in it encoded.
+fn obfuscate_text(text: &str) -> String {
+    if !text.contains('@') {
+        return text.to_string();
+    }
+
+    let mut output = String::with_capacity(text.len());
+    let mut copied = 0;
+    for (at, _) in text.match_indices('@') {
+        if at < copied {
+            continue;
+        }
+        let local = text[copied..at]
+            .rfind(|c: char| !is_local_char(c))
+            .map_or(copied, |i| copied + i + 1);
+        let domain_len = text[at + 1..]
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '-' || c == '.'))
+            .unwrap_or(text.len() - at - 1);
+        let domain = text[at + 1..at + 1 + domain_len].trim_end_matches('.');
+        if local == at || !domain.contains('.') || domain.starts_with('.') {
+            continue;
+        }
+        let end = at + 1 + domain.len();
+        output.push_str(&text[copied..local]);
+        output.push_str(&encode(&text[local..end]));
+        copied = end;
+    }
+    output.push_str(&text[copied..]);
+    output
+}
+
+fn is_local_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || "._%+-".contains(c)
+}
+
+/// Every character of `text` as a decimal character reference.
+fn encode(text: &str) -> String {
+    text.chars().map(|c| format!("&#{};", c as u32)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn obfuscate_encodes_addresses_in_links_and_text() {
+        let html = "<p>Write to <a href=\"mailto:me@example.com\">me@example.com</a>.</p>";
+        let obfuscated = obfuscate(html);
+        assert!(!obfuscated.contains('@'), "{}", obfuscated);
+        assert_eq!(
+            obfuscated,
+            format!(
+                "<p>Write to <a href=\"{}\">{}</a>.</p>",
+                encode("mailto:me@example.com"),
+                encode("me@example.com")
+            )
+        );
+    }
+
+    #[test]
+    fn obfuscate_leaves_other_at_signs_and_scripts_alone() {
+        for html in [
+            "<p>Ask @bread on Mastodon, or user@localhost</p>",
+            "<script>const a = \"me@example.com\";</script>",
+            "<style>@media print { a { color: black } }</style>",
+        ] {
+            assert_eq!(obfuscate(html), html);
+        }
+    }
+}
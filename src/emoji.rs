@@ -0,0 +1,289 @@
+use pulldown_cmark::{CowStr, Event, Tag, TagEnd};
+
+use crate::escape_html;
+
+/// Replaces `:name:` shortcodes in the text of `events`, but not in code,
+/// with the emoji they name, the way GitHub does. Names missing from the
+/// table become `<img class="emoji">`s from `image_url`, with `{name}` in it
+/// replaced by the name, or stay as written if there's no `image_url`.
+pub fn replace_shortcodes<'a>(events: Vec<Event<'a>>, image_url: &str) -> Vec<Event<'a>> {
+    let mut output = Vec::with_capacity(events.len());
+    // Text can arrive in pieces, split around characters like `_`
+    let mut text = String::new();
+    let mut in_code_block = false;
+
+    for event in events {
+        match event {
+            Event::Text(piece) if !in_code_block => {
+                text.push_str(&piece);
+                continue;
+            }
+            Event::Start(Tag::CodeBlock(_)) => in_code_block = true,
+            Event::End(TagEnd::CodeBlock) => in_code_block = false,
+            _ => {}
+        }
+        expand(&std::mem::take(&mut text), image_url, &mut output);
+        output.push(event);
+    }
+    expand(&text, image_url, &mut output);
+
+    output
+}
+
+/// Pushes `text` with its shortcodes replaced onto `output`.
+fn expand<'a>(text: &str, image_url: &str, output: &mut Vec<Event<'a>>) {
+    let mut expanded = String::new();
+    let mut rest = text;
+
+    while let Some(start) = rest.find(':') {
+        let after = &rest[start + 1..];
+        let Some(len) = after.find(':') else {
+            break;
+        };
+        let name = &after[..len];
+        let is_name = !name.is_empty()
+            && name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || "_+-".contains(c));
+
+        if is_name && let Some(emoji) = lookup(name) {
+            expanded.push_str(&rest[..start]);
+            expanded.push_str(emoji);
+        } else if is_name && !image_url.is_empty() && name.chars().any(|c| c.is_ascii_alphabetic())
+        {
+            expanded.push_str(&rest[..start]);
+            output.push(Event::Text(CowStr::from(std::mem::take(&mut expanded))));
+            output.push(Event::InlineHtml(CowStr::from(format!(
+                "<img class=\"emoji\" src=\"{}\" alt=\":{}:\" title=\":{}:\">",
+                escape_html(&image_url.replace("{name}", name)),
+                name,
+                name
+            ))));
+        } else {
+            // The closing colon may open the next shortcode
+            expanded.push_str(&rest[..=start + len]);
+            rest = &rest[start + 1 + len..];
+            continue;
+        }
+        rest = &after[len + 1..];
+    }
+    expanded.push_str(rest);
+
+    if !expanded.is_empty() {
+        output.push(Event::Text(CowStr::from(expanded)));
+    }
+}
+
+/// The emoji GitHub calls `name`, like 🚀 for `rocket`.
+pub fn lookup(name: &str) -> Option<&'static str> {
+    EMOJI
+        .binary_search_by(|(known, _)| known.cmp(&name))
+        .ok()
+        .map(|i| EMOJI[i].1)
+}
+
+/// GitHub's names for the most used emoji, sorted by name for binary search.
+const EMOJI: [(&str, &str); 202] = [
+    ("+1", "👍"),
+    ("-1", "👎"),
+    ("100", "💯"),
+    ("1234", "🔢"),
+    ("alarm_clock", "⏰"),
+    ("alien", "👽"),
+    ("angry", "😠"),
+    ("apple", "🍎"),
+    ("arrow_down", "⬇️"),
+    ("arrow_left", "⬅️"),
+    ("arrow_right", "➡️"),
+    ("arrow_up", "⬆️"),
+    ("art", "🎨"),
+    ("baby", "👶"),
+    ("balloon", "🎈"),
+    ("bangbang", "‼️"),
+    ("beer", "🍺"),
+    ("beers", "🍻"),
+    ("bell", "🔔"),
+    ("bike", "🚲"),
+    ("bird", "🐦"),
+    ("blush", "😊"),
+    ("book", "📖"),
+    ("books", "📚"),
+    ("boom", "💥"),
+    ("bow", "🙇"),
+    ("bread", "🍞"),
+    ("broken_heart", "💔"),
+    ("bug", "🐛"),
+    ("bulb", "💡"),
+    ("bus", "🚌"),
+    ("cake", "🍰"),
+    ("calendar", "📆"),
+    ("camera", "📷"),
+    ("car", "🚗"),
+    ("cat", "🐱"),
+    ("chart_with_downwards_trend", "📉"),
+    ("chart_with_upwards_trend", "📈"),
+    ("check", "✔️"),
+    ("checkered_flag", "🏁"),
+    ("cherries", "🍒"),
+    ("christmas_tree", "🎄"),
+    ("clap", "👏"),
+    ("clipboard", "📋"),
+    ("clock3", "🕒"),
+    ("cloud", "☁️"),
+    ("coffee", "☕"),
+    ("computer", "💻"),
+    ("confused", "😕"),
+    ("construction", "🚧"),
+    ("cookie", "🍪"),
+    ("cool", "🆒"),
+    ("cry", "😢"),
+    ("crystal_ball", "🔮"),
+    ("dart", "🎯"),
+    ("dash", "💨"),
+    ("dog", "🐶"),
+    ("dollar", "💵"),
+    ("door", "🚪"),
+    ("earth_africa", "🌍"),
+    ("earth_americas", "🌎"),
+    ("email", "📧"),
+    ("envelope", "✉️"),
+    ("exclamation", "❗"),
+    ("eyes", "👀"),
+    ("facepalm", "🤦"),
+    ("fire", "🔥"),
+    ("fish", "🐟"),
+    ("flashlight", "🔦"),
+    ("floppy_disk", "💾"),
+    ("flower_playing_cards", "🎴"),
+    ("gear", "⚙️"),
+    ("gem", "💎"),
+    ("ghost", "👻"),
+    ("gift", "🎁"),
+    ("globe_with_meridians", "🌐"),
+    ("grey_question", "❔"),
+    ("grin", "😁"),
+    ("grinning", "😀"),
+    ("hammer", "🔨"),
+    ("hammer_and_wrench", "🛠️"),
+    ("hand", "✋"),
+    ("heart", "❤️"),
+    ("heart_eyes", "😍"),
+    ("heavy_check_mark", "✔️"),
+    ("heavy_minus_sign", "➖"),
+    ("heavy_plus_sign", "➕"),
+    ("hourglass", "⌛"),
+    ("house", "🏠"),
+    ("hugs", "🤗"),
+    ("hundred", "💯"),
+    ("information_source", "ℹ️"),
+    ("innocent", "😇"),
+    ("joy", "😂"),
+    ("key", "🔑"),
+    ("keyboard", "⌨️"),
+    ("kiss", "💋"),
+    ("laughing", "😆"),
+    ("leaves", "🍃"),
+    ("link", "🔗"),
+    ("lock", "🔒"),
+    ("loudspeaker", "📢"),
+    ("mag", "🔍"),
+    ("mailbox", "📫"),
+    ("memo", "📝"),
+    ("microscope", "🔬"),
+    ("moneybag", "💰"),
+    ("moon", "🌙"),
+    ("mortar_board", "🎓"),
+    ("muscle", "💪"),
+    ("mushroom", "🍄"),
+    ("musical_note", "🎵"),
+    ("neutral_face", "😐"),
+    ("new", "🆕"),
+    ("no_entry", "⛔"),
+    ("no_entry_sign", "🚫"),
+    ("notebook", "📓"),
+    ("ok", "🆗"),
+    ("ok_hand", "👌"),
+    ("open_mouth", "😮"),
+    ("package", "📦"),
+    ("page_facing_up", "📄"),
+    ("paperclip", "📎"),
+    ("partying_face", "🥳"),
+    ("pencil", "📝"),
+    ("pencil2", "✏️"),
+    ("penguin", "🐧"),
+    ("phone", "☎️"),
+    ("pig", "🐷"),
+    ("pizza", "🍕"),
+    ("point_down", "👇"),
+    ("point_left", "👈"),
+    ("point_right", "👉"),
+    ("point_up", "☝️"),
+    ("pray", "🙏"),
+    ("pushpin", "📌"),
+    ("question", "❓"),
+    ("rainbow", "🌈"),
+    ("raised_hands", "🙌"),
+    ("recycle", "♻️"),
+    ("red_circle", "🔴"),
+    ("relaxed", "☺️"),
+    ("relieved", "😌"),
+    ("rocket", "🚀"),
+    ("rofl", "🤣"),
+    ("rose", "🌹"),
+    ("rotating_light", "🚨"),
+    ("runner", "🏃"),
+    ("sad", "😞"),
+    ("scissors", "✂️"),
+    ("scream", "😱"),
+    ("see_no_evil", "🙈"),
+    ("seedling", "🌱"),
+    ("shield", "🛡️"),
+    ("ship", "🚢"),
+    ("shrug", "🤷"),
+    ("skull", "💀"),
+    ("sleeping", "😴"),
+    ("slightly_smiling_face", "🙂"),
+    ("smile", "😄"),
+    ("smiley", "😃"),
+    ("smirk", "😏"),
+    ("snail", "🐌"),
+    ("snake", "🐍"),
+    ("snowflake", "❄️"),
+    ("sob", "😭"),
+    ("soccer", "⚽"),
+    ("sparkles", "✨"),
+    ("sparkling_heart", "💖"),
+    ("speech_balloon", "💬"),
+    ("star", "⭐"),
+    ("star2", "🌟"),
+    ("stars", "🌠"),
+    ("stop_sign", "🛑"),
+    ("sun_with_face", "🌞"),
+    ("sunglasses", "😎"),
+    ("sunny", "☀️"),
+    ("sweat_smile", "😅"),
+    ("tada", "🎉"),
+    ("tea", "🍵"),
+    ("thinking", "🤔"),
+    ("thought_balloon", "💭"),
+    ("thumbsdown", "👎"),
+    ("thumbsup", "👍"),
+    ("tired_face", "😫"),
+    ("tophat", "🎩"),
+    ("trophy", "🏆"),
+    ("truck", "🚚"),
+    ("turtle", "🐢"),
+    ("umbrella", "☔"),
+    ("unicorn", "🦄"),
+    ("unlock", "🔓"),
+    ("v", "✌️"),
+    ("warning", "⚠️"),
+    ("wave", "👋"),
+    ("white_check_mark", "✅"),
+    ("wink", "😉"),
+    ("wrench", "🔧"),
+    ("x", "❌"),
+    ("yum", "😋"),
+    ("zap", "⚡"),
+    ("zzz", "💤"),
+];
@@ -0,0 +1,93 @@
+use crate::check::attribute_values;
+use crate::config::Config;
+
+/// Marks up the links in `html` that point off the site as
+/// `[external_links]` says: `rel="noopener noreferrer"`, and optionally
+/// `target="_blank"` and a class. Attributes a link already has are left
+/// alone, apart from the class being added to its others.
+pub fn decorate(html: &str, config: &Config) -> String {
+    let settings = &config.external_links;
+    let mut output = String::with_capacity(html.len());
+    let mut rest = html;
+
+    while let Some(start) = find_link_tag(rest) {
+        let Some(len) = rest[start..].find('>') else {
+            break;
+        };
+        let (before, tag) = (&rest[..start], &rest[start..start + len]);
+        output.push_str(before);
+        rest = &rest[start + len..];
+
+        let external = attribute_values(tag, "href")
+            .first()
+            .is_some_and(|href| is_off_site(href, &config.base_url));
+        if !external {
+            output.push_str(tag);
+            continue;
+        }
+
+        let mut tag = tag.to_string();
+        if !settings.class.is_empty() {
+            tag = add_class(&tag, &settings.class);
+        }
+        if attribute_values(&tag, "rel").is_empty() {
+            tag.push_str(" rel=\"noopener noreferrer\"");
+        }
+        if settings.new_tab && attribute_values(&tag, "target").is_empty() {
+            tag.push_str(" target=\"_blank\"");
+        }
+        output.push_str(&tag);
+    }
+
+    output.push_str(rest);
+    output
+}
+
+/// Where the next `<a>` tag starts, if there is one.
+fn find_link_tag(html: &str) -> Option<usize> {
+    let mut from = 0;
+    while let Some(pos) = html[from..].find("<a") {
+        let start = from + pos;
+        if html[start + 2..].starts_with(|c: char| c.is_whitespace()) {
+            return Some(start);
+        }
+        from = start + 2;
+    }
+    None
+}
+
+/// Whether `href` is an absolute URL outside `base_url`. With a `base_url`
+/// that's only a path, every absolute URL is.
+fn is_off_site(href: &str, base_url: &str) -> bool {
+    let absolute =
+        href.starts_with("http://") || href.starts_with("https://") || href.starts_with("//");
+    if !absolute {
+        return false;
+    }
+    if !base_url.contains("://") {
+        return true;
+    }
+    let without_scheme = |url: &str| {
+        url.split_once("//")
+            .map_or("", |(_, rest)| rest)
+            .to_string()
+    };
+    let site = without_scheme(base_url);
+    let site = site.trim_end_matches('/');
+    let link = without_scheme(href);
+    !(link == site || link.starts_with(&format!("{}/", site)))
+}
+
+/// `tag` with `class` added to its class attribute, or given one.
+fn add_class(tag: &str, class: &str) -> String {
+    for quote in ['"', '\''] {
+        let needle = format!(" class={}", quote);
+        if let Some(pos) = tag.find(&needle) {
+            let value_start = pos + needle.len();
+            let mut tag = tag.to_string();
+            tag.insert_str(value_start, &format!("{} ", class));
+            return tag;
+        }
+    }
+    format!("{} class=\"{}\"", tag, class)
+}
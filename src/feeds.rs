@@ -0,0 +1,241 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::config::{Config, FeedContent};
+use crate::{PostMetadata, TagPosts, dates, escape_html, staging};
+
+/// Writes the site-wide `rss.xml`, a `tags/<tag>/rss.xml` for every tag and,
+/// with `feeds.sections` on, a `<section>/rss.xml` for every top-level
+/// content directory, each listing posts newest first with their summaries
+/// or, as the config says, their full content.
+pub fn write_feeds(
+    posts: &[PostMetadata],
+    tags: &[TagPosts],
+    config: &Config,
+    output_dir: &Path,
+) -> io::Result<()> {
+    let site_title = if config.title.is_empty() {
+        "Posts"
+    } else {
+        &config.title
+    };
+
+    let all: Vec<&PostMetadata> = posts.iter().collect();
+    let feed = rss(site_title, &config.description, &all, config);
+    let path = output_dir.join("rss.xml");
+    fs::write(&path, feed)?;
+    println!(
+        "  📡 Generated {} ({} posts)",
+        staging::shown(&path),
+        limited(&all, config).len()
+    );
+
+    for tag in tags {
+        let dir = output_dir.join("tags").join(&tag.slug);
+        fs::create_dir_all(&dir)?;
+
+        let title = format!("{}: #{}", site_title, tag.name);
+        let description = format!("Posts tagged #{}", tag.name);
+        let feed = rss(&title, &description, &tag.posts, config);
+        fs::write(dir.join("rss.xml"), feed)?;
+    }
+    if !tags.is_empty() {
+        println!("  📡 Generated {} tag feed(s)", tags.len());
+    }
+
+    if config.feeds.sections {
+        let sections = sections(posts, config);
+        for (section, posts) in &sections {
+            let dir = output_dir.join(section);
+            fs::create_dir_all(&dir)?;
+
+            let title = format!("{}: {}", site_title, section);
+            let feed = rss(&title, &config.description, posts, config);
+            fs::write(dir.join("rss.xml"), feed)?;
+        }
+        if !sections.is_empty() {
+            println!("  📡 Generated {} section feed(s)", sections.len());
+        }
+    }
+
+    Ok(())
+}
+
+/// Posts grouped by the top-level directory they're published in, below
+/// their language's. Posts at the top level aren't in any section.
+fn sections<'a>(
+    posts: &'a [PostMetadata],
+    config: &Config,
+) -> BTreeMap<String, Vec<&'a PostMetadata>> {
+    let mut sections: BTreeMap<String, Vec<&PostMetadata>> = BTreeMap::new();
+    for post in posts {
+        let prefix = config.language_prefix(&post.lang);
+        let path = post.url.strip_prefix(&prefix).unwrap_or(&post.url);
+        if let Some((section, _)) = path.trim_start_matches('/').split_once('/') {
+            sections.entry(section.to_string()).or_default().push(post);
+        }
+    }
+    sections
+}
+
+/// The posts a feed lists: the newest `feeds.limit` of them.
+fn limited<'a, 'p>(posts: &'a [&'p PostMetadata], config: &Config) -> &'a [&'p PostMetadata] {
+    match config.feeds.limit {
+        Some(limit) => &posts[..limit.min(posts.len())],
+        None => posts,
+    }
+}
+
+fn rss(title: &str, description: &str, posts: &[&PostMetadata], config: &Config) -> String {
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    xml.push_str("<rss version=\"2.0\">\n<channel>\n");
+    xml.push_str(&format!("  <title>{}</title>\n", escape_html(title)));
+    xml.push_str(&format!(
+        "  <link>{}</link>\n",
+        escape_html(&config.absolute_url("/"))
+    ));
+    xml.push_str(&format!(
+        "  <description>{}</description>\n",
+        escape_html(description)
+    ));
+
+    for post in limited(posts, config) {
+        let url = escape_html(&config.absolute_url(&post.url));
+        xml.push_str("  <item>\n");
+        xml.push_str(&format!(
+            "    <title>{}</title>\n",
+            escape_html(&post.title)
+        ));
+        xml.push_str(&format!("    <link>{}</link>\n", url));
+        xml.push_str(&format!("    <guid>{}</guid>\n", url));
+        if let Some(date) = rfc822_date(&post.date) {
+            xml.push_str(&format!("    <pubDate>{}</pubDate>\n", date));
+        }
+        for tag in &post.tags {
+            xml.push_str(&format!("    <category>{}</category>\n", escape_html(tag)));
+        }
+        let content = match config.feeds.content {
+            FeedContent::Summary => &post.summary,
+            FeedContent::Full => &post.content,
+        };
+        xml.push_str(&format!(
+            "    <description>{}</description>\n",
+            escape_html(&absolute_links(content, &post.url, config))
+        ));
+        xml.push_str("  </item>\n");
+    }
+
+    xml.push_str("</channel>\n</rss>\n");
+    xml
+}
+
+/// Rewrites the `href` and `src` attributes in `html`, from the page at
+/// `page_url`, to full URLs, since feed readers show items away from the
+/// site. Site-absolute paths can only be completed when `base_url` is a full
+/// URL, and are left alone otherwise.
+pub fn absolute_links(html: &str, page_url: &str, config: &Config) -> String {
+    let origin = config.base_url.split_once("://").map(|(scheme, rest)| {
+        let host = rest.split('/').next().unwrap_or(rest);
+        format!("{}://{}", scheme, host)
+    });
+    let page = config.absolute_url(page_url);
+    let page_dir = page.rsplit_once('/').map_or(page.as_str(), |(dir, _)| dir);
+
+    let mut result = String::with_capacity(html.len());
+    let mut rest = html;
+    while let Some((start, quote)) = next_url_attribute(rest) {
+        result.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let Some(end) = rest.find(quote) else {
+            break;
+        };
+        let url = &rest[..end];
+        if url.starts_with("//") || url.contains(':') {
+            result.push_str(url);
+        } else if let Some(fragment) = url.strip_prefix('#') {
+            result.push_str(&format!("{}#{}", page, fragment));
+        } else if url.starts_with('/') {
+            match &origin {
+                Some(origin) => result.push_str(&format!("{}{}", origin, url)),
+                None => result.push_str(url),
+            }
+        } else {
+            result.push_str(&format!("{}/{}", page_dir, url));
+        }
+        rest = &rest[end..];
+    }
+    result.push_str(rest);
+    result
+}
+
+/// Where the value of the next ` href="…"` or ` src="…"` in `html` starts,
+/// and the quote it's in.
+fn next_url_attribute(html: &str) -> Option<(usize, char)> {
+    ["href=", "src="]
+        .iter()
+        .filter_map(|attribute| {
+            let mut from = 0;
+            while let Some(pos) = html[from..].find(attribute) {
+                let start = from + pos;
+                let value = start + attribute.len();
+                from = value;
+                let whole_name = html[..start].ends_with(char::is_whitespace);
+                let quote = html[value..].chars().next();
+                if let Some(quote @ ('"' | '\'')) = quote.filter(|_| whole_name) {
+                    return Some((value + 1, quote));
+                }
+            }
+            None
+        })
+        .min()
+}
+
+/// Formats a frontmatter date the way RSS wants it.
+fn rfc822_date(date: &str) -> Option<String> {
+    dates::parse(date).map(|date| date.to_rfc2822())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(base_url: &str) -> Config {
+        Config {
+            base_url: base_url.to_string(),
+            ..Config::default()
+        }
+    }
+
+    #[test]
+    fn absolute_links_completes_every_kind_of_link() {
+        let config = config("https://example.com/blog/");
+        let html = "<a href=\"/blog/about.html\">a</a> <a href=\"#top\">b</a> \
+                    <img src=\"cat.png\"> <a href='other.html'>c</a>";
+        assert_eq!(
+            absolute_links(html, "/posts/first.html", &config),
+            "<a href=\"https://example.com/blog/about.html\">a</a> \
+             <a href=\"https://example.com/blog/posts/first.html#top\">b</a> \
+             <img src=\"https://example.com/blog/posts/cat.png\"> \
+             <a href='https://example.com/blog/posts/other.html'>c</a>"
+        );
+    }
+
+    #[test]
+    fn absolute_links_leaves_full_urls_alone() {
+        let config = config("https://example.com/");
+        let html = "<a href=\"https://other.org/\">a</a> <a href=\"mailto:me@example.com\">b</a> \
+                    <img src=\"//cdn.example.net/x.png\">";
+        assert_eq!(absolute_links(html, "/index.html", &config), html);
+    }
+
+    #[test]
+    fn absolute_links_keeps_site_paths_without_a_full_base_url() {
+        let config = config("/bread/");
+        assert_eq!(
+            absolute_links("<a href=\"/bread/x.html\">x</a>", "/index.html", &config),
+            "<a href=\"/bread/x.html\">x</a>"
+        );
+    }
+}
@@ -0,0 +1,204 @@
+use std::io::{self, Write};
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use crate::Frontmatter;
+
+/// The kinds of content files that become pages. Markdown (and notebooks,
+/// by way of markdown) goes through bread's own renderer; the others arrive
+/// as HTML, either as written or
+/// from a converter, and skip straight to the templates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputFormat {
+    Markdown,
+    /// Hand-written HTML, see `html_with_frontmatter`
+    Html,
+    /// Converted by `formats.asciidoc_command`
+    AsciiDoc,
+    /// Org-mode, converted by `formats.org_command`
+    Org,
+    /// Jupyter notebooks, turned into markdown by `notebook::to_markdown`
+    Notebook,
+}
+
+impl InputFormat {
+    /// The format of a content file, going by its extension.
+    pub fn of(path: &Path) -> Option<Self> {
+        match path.extension().and_then(|s| s.to_str())? {
+            "md" => Some(InputFormat::Markdown),
+            "html" => Some(InputFormat::Html),
+            "adoc" | "asciidoc" => Some(InputFormat::AsciiDoc),
+            "org" => Some(InputFormat::Org),
+            "ipynb" => Some(InputFormat::Notebook),
+            _ => None,
+        }
+    }
+
+    /// Whether pages in this format are rendered as markdown.
+    pub fn is_markdown(self) -> bool {
+        matches!(self, InputFormat::Markdown | InputFormat::Notebook)
+    }
+
+    /// Whether files in this format are always pages, rather than only
+    /// sometimes like HTML.
+    pub fn is_page(self) -> bool {
+        self != InputFormat::Html
+    }
+}
+
+/// Fills in what an AsciiDoc document's header says and the frontmatter
+/// doesn't: the title from its `= Title` line, and the date and description
+/// from `:revdate:`/`:date:` and `:description:` attributes.
+pub fn read_asciidoc_header(source: &str, frontmatter: &mut Frontmatter) {
+    let mut lines = source.lines().skip_while(|line| line.trim().is_empty());
+    let Some(first) = lines.next() else {
+        return;
+    };
+    if let Some(title) = first.strip_prefix("= ") {
+        frontmatter
+            .title
+            .get_or_insert_with(|| title.trim().to_string());
+    }
+
+    for line in lines.take_while(|line| !line.trim().is_empty()) {
+        let Some((name, value)) = line
+            .strip_prefix(':')
+            .and_then(|attribute| attribute.split_once(':'))
+        else {
+            continue;
+        };
+        let value = value.trim().to_string();
+        match name {
+            "revdate" | "date" => {
+                frontmatter.date.get_or_insert(value);
+            }
+            "description" => {
+                frontmatter.description.get_or_insert(value);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Fills in what an org file's `#+TITLE`, `#+DATE`, `#+DESCRIPTION` and
+/// `#+FILETAGS` keywords say and the frontmatter doesn't. Org timestamps like
+/// `<2024-03-05 Tue 10:30>` become `2024-03-05 10:30`.
+pub fn read_org_keywords(source: &str, frontmatter: &mut Frontmatter) {
+    for line in source.lines() {
+        let Some((keyword, value)) = line
+            .trim()
+            .strip_prefix("#+")
+            .and_then(|keyword| keyword.split_once(':'))
+        else {
+            continue;
+        };
+        let value = value.trim();
+        match keyword.to_ascii_uppercase().as_str() {
+            "TITLE" => {
+                frontmatter.title.get_or_insert_with(|| value.to_string());
+            }
+            "DATE" => {
+                frontmatter.date.get_or_insert_with(|| org_timestamp(value));
+            }
+            "DESCRIPTION" => {
+                frontmatter
+                    .description
+                    .get_or_insert_with(|| value.to_string());
+            }
+            "FILETAGS" => {
+                frontmatter.tags.get_or_insert_with(|| {
+                    value
+                        .split([':', ' '])
+                        .filter(|tag| !tag.is_empty())
+                        .map(String::from)
+                        .collect()
+                });
+            }
+            _ => {}
+        }
+    }
+}
+
+/// An org timestamp without its brackets and day name.
+fn org_timestamp(value: &str) -> String {
+    value
+        .trim_matches(['<', '>', '[', ']'])
+        .split_whitespace()
+        .filter(|part| !part.chars().all(char::is_alphabetic))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Runs `source` through a converter that reads it on stdin and writes HTML
+/// to stdout.
+pub fn convert(command: &[String], source: &str, path: &Path) -> io::Result<String> {
+    let Some((program, args)) = command.split_first() else {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{}: no converter configured", path.display()),
+        ));
+    };
+
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| {
+            io::Error::new(
+                e.kind(),
+                format!("{}: could not run {}: {}", path.display(), program, e),
+            )
+        })?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(source.as_bytes())?;
+    }
+    let output = child.wait_with_output()?;
+
+    if !output.status.success() {
+        return Err(io::Error::other(format!(
+            "{}: {} failed: {}",
+            path.display(),
+            program,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// The text of an HTML fragment without its tags, for word counts and the
+/// search index.
+pub fn html_text(html: &str) -> String {
+    let mut text = String::with_capacity(html.len());
+    let mut rest = html;
+    while let Some(start) = rest.find('<') {
+        text.push_str(&rest[..start]);
+        text.push(' ');
+        rest = rest[start..]
+            .find('>')
+            .map_or("", |end| &rest[start + end + 1..]);
+    }
+    text.push_str(rest);
+
+    let text = text
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&nbsp;", " ")
+        .replace("&amp;", "&");
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// The first `<p>` of an HTML fragment, for summaries of pages that aren't
+/// markdown.
+pub fn first_paragraph(html: &str) -> &str {
+    let Some(start) = html.find("<p>") else {
+        return "";
+    };
+    match html[start..].find("</p>") {
+        Some(end) => &html[start..start + end + "</p>".len()],
+        None => "",
+    }
+}
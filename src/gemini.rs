@@ -0,0 +1,207 @@
+use pulldown_cmark::{Event, HeadingLevel, Tag, TagEnd};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::config::{Config, MarkdownConfig};
+use crate::markdown::{self, RenderContext};
+use crate::{PostMetadata, staging, url_stem};
+
+/// Where the capsule file for the page at `url` goes.
+pub fn page_path(url: &str, config: &Config, output_dir: &Path) -> PathBuf {
+    let path = url_stem(url);
+    output_dir
+        .join(&config.gemini.dir)
+        .join(format!("{}.gmi", path.trim_start_matches('/')))
+}
+
+/// Writes a page of the capsule: its title as a heading, the date if it has
+/// one, then the body.
+pub fn write_page(title: &str, date: &str, body: &str, path: &Path) -> io::Result<()> {
+    let mut gemtext = format!("# {}\n\n", title);
+    if !date.is_empty() {
+        gemtext.push_str(&format!("{}\n\n", date));
+    }
+    gemtext.push_str(body);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, gemtext)
+}
+
+/// Writes the capsule's `index.gmi`, linking to every post newest first.
+pub fn write_index(posts: &[PostMetadata], config: &Config, output_dir: &Path) -> io::Result<()> {
+    let title = if config.title.is_empty() {
+        "Posts"
+    } else {
+        &config.title
+    };
+    let mut gemtext = format!("# {}\n\n", title);
+    if !config.description.is_empty() {
+        gemtext.push_str(&format!("{}\n\n", config.description));
+    }
+    for post in posts {
+        let url = url_stem(&post.url);
+        let date = post.date.get(..10).unwrap_or(&post.date);
+        let line = format!("=> {}.gmi {} {}", url, date, post.title);
+        gemtext.push_str(&format!("{}\n", line.replace("  ", " ")));
+    }
+
+    let dir = output_dir.join(&config.gemini.dir);
+    fs::create_dir_all(&dir)?;
+    let path = dir.join("index.gmi");
+    fs::write(&path, gemtext)?;
+    println!(
+        "  🚀 Generated {} ({} posts)",
+        staging::shown(&path),
+        posts.len()
+    );
+    Ok(())
+}
+
+/// Downconverts markdown to gemtext, which has no inline markup: paragraphs
+/// become single lines with their links listed after them as `=>` lines,
+/// lists and quotes keep their markers, code blocks and tables become
+/// preformatted text, and images become links. Links to pages of the site
+/// point to their capsule versions.
+pub fn from_markdown(
+    markdown: &str,
+    markdown_config: &MarkdownConfig,
+    site: &RenderContext,
+    config: &Config,
+) -> String {
+    let mut gemtext = String::new();
+    let mut line = String::new();
+    let mut links: Vec<(String, String)> = Vec::new();
+    let mut link_text = String::new();
+    let mut in_link = false;
+    let mut in_code_block = false;
+    let mut quote_depth = 0;
+    let mut item_depth = 0;
+    let base_path = config.base_path();
+
+    let flush = |gemtext: &mut String,
+                 line: &mut String,
+                 links: &mut Vec<(String, String)>,
+                 prefix: &str| {
+        let text = line.split_whitespace().collect::<Vec<_>>().join(" ");
+        if !text.is_empty() {
+            gemtext.push_str(&format!("{}{}\n", prefix, text));
+        }
+        for (url, label) in links.drain(..) {
+            gemtext.push_str(format!("=> {} {}", url, label).trim_end());
+            gemtext.push('\n');
+        }
+        line.clear();
+    };
+
+    for event in markdown::events(markdown, markdown_config, site) {
+        match event {
+            Event::Start(Tag::Heading { .. }) | Event::Start(Tag::Paragraph) => line.clear(),
+            Event::Start(Tag::Item) => {
+                // What a parent item says before its nested list
+                flush(&mut gemtext, &mut line, &mut links, "* ");
+                item_depth += 1;
+            }
+            Event::End(TagEnd::Heading(level)) => {
+                let marker = match level {
+                    HeadingLevel::H1 => "# ",
+                    HeadingLevel::H2 => "## ",
+                    _ => "### ",
+                };
+                flush(&mut gemtext, &mut line, &mut links, marker);
+                gemtext.push('\n');
+            }
+            // Paragraphs of loose list items stay list items
+            Event::End(TagEnd::Paragraph) if item_depth > 0 => {
+                flush(&mut gemtext, &mut line, &mut links, "* ");
+            }
+            Event::End(TagEnd::Paragraph) => {
+                let prefix = if quote_depth > 0 { "> " } else { "" };
+                flush(&mut gemtext, &mut line, &mut links, prefix);
+                gemtext.push('\n');
+            }
+            Event::End(TagEnd::Item) => {
+                item_depth -= 1;
+                flush(&mut gemtext, &mut line, &mut links, "* ");
+            }
+            Event::End(TagEnd::List(_)) => gemtext.push('\n'),
+            Event::Start(Tag::BlockQuote(_)) => quote_depth += 1,
+            Event::End(TagEnd::BlockQuote(_)) => quote_depth -= 1,
+            Event::Start(Tag::CodeBlock(_)) => {
+                in_code_block = true;
+                gemtext.push_str("```\n");
+            }
+            Event::End(TagEnd::CodeBlock) => {
+                in_code_block = false;
+                if !gemtext.ends_with('\n') {
+                    gemtext.push('\n');
+                }
+                gemtext.push_str("```\n\n");
+            }
+            Event::Start(Tag::Table(_)) => gemtext.push_str("```\n"),
+            Event::End(TagEnd::Table) => gemtext.push_str("```\n\n"),
+            Event::End(TagEnd::TableRow) | Event::End(TagEnd::TableHead) => {
+                gemtext.push_str(line.trim_end_matches(" | "));
+                gemtext.push('\n');
+                line.clear();
+            }
+            Event::End(TagEnd::TableCell) => line.push_str(" | "),
+            Event::Start(Tag::Link { dest_url, .. })
+            | Event::Start(Tag::Image { dest_url, .. }) => {
+                in_link = true;
+                link_text.clear();
+                links.push((capsule_url(&dest_url, &base_path, config), String::new()));
+            }
+            Event::End(TagEnd::Link) | Event::End(TagEnd::Image) => {
+                in_link = false;
+                if let Some(link) = links.last_mut() {
+                    link.1 = link_text.trim().to_string();
+                }
+            }
+            Event::Text(text) | Event::Code(text) => {
+                // Text of code blocks is kept as is, line breaks and all
+                if in_code_block {
+                    gemtext.push_str(&text);
+                    continue;
+                }
+                if in_link {
+                    link_text.push_str(&text);
+                }
+                line.push_str(&text);
+            }
+            Event::SoftBreak | Event::HardBreak => line.push(' '),
+            Event::Rule => gemtext.push_str("---\n\n"),
+            _ => {}
+        }
+    }
+    flush(&mut gemtext, &mut line, &mut links, "");
+
+    gemtext.trim_end().to_string() + "\n"
+}
+
+/// Where a link in the content points in the capsule: pages of the site to
+/// their `.gmi` files, other files of the site to the web, and everything
+/// else where it did.
+fn capsule_url(url: &str, base_path: &str, config: &Config) -> String {
+    if url.contains("://") || url.starts_with("mailto:") || url.starts_with('#') {
+        return url.to_string();
+    }
+    let (path, fragment) = url.split_once('#').unwrap_or((url, ""));
+    let path = match path.strip_prefix(base_path) {
+        Some(rest) => format!("/{}", rest),
+        None => path.to_string(),
+    };
+    match path.strip_suffix(".html") {
+        Some(page) => format!("{}.gmi", page),
+        None if path.starts_with('/') => {
+            let url = config.absolute_url(&path);
+            if fragment.is_empty() {
+                url
+            } else {
+                format!("{}#{}", url, fragment)
+            }
+        }
+        None => url.to_string(),
+    }
+}
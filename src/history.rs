@@ -0,0 +1,60 @@
+use std::collections::BTreeMap;
+use std::io;
+use std::path::Path;
+use std::process::Command;
+
+/// What git knows about a content file.
+#[derive(Debug, Default)]
+pub struct FileHistory {
+    /// When the file was last committed, as an RFC 3339 timestamp
+    pub last_modified: String,
+    /// Everyone who has committed to the file, most recent first
+    pub authors: Vec<String>,
+}
+
+/// Reads the commit history of everything under `content_dir` with a single
+/// `git log`, keyed by `/`-separated path relative to `content_dir`. Files
+/// that were never committed are left out.
+pub fn load(content_dir: &Path) -> io::Result<BTreeMap<String, FileHistory>> {
+    let output = Command::new("git")
+        .args(["-c", "core.quotePath=false"])
+        .args([
+            "log",
+            "--relative",
+            "--name-only",
+            "--format=%x00%aI%x00%an",
+        ])
+        .args(["--", "."])
+        .current_dir(content_dir)
+        .output()?;
+    if !output.status.success() {
+        return Err(io::Error::other(format!(
+            "git log failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+
+    let mut history: BTreeMap<String, FileHistory> = BTreeMap::new();
+    let log = String::from_utf8_lossy(&output.stdout);
+    // Each commit reads `\0<date>\0<author>\n\n<file>\n<file>...`
+    for commit in log.split('\0').skip(1).collect::<Vec<_>>().chunks(2) {
+        let [date, rest] = commit else {
+            continue;
+        };
+        let mut lines = rest.lines();
+        let author = lines.next().unwrap_or_default();
+
+        for file in lines.filter(|line| !line.is_empty()) {
+            let entry = history.entry(file.to_string()).or_default();
+            // Commits come newest first, so the first one seen is the latest
+            if entry.last_modified.is_empty() {
+                entry.last_modified = date.to_string();
+            }
+            if !entry.authors.iter().any(|a| a == author) {
+                entry.authors.push(author.to_string());
+            }
+        }
+    }
+
+    Ok(history)
+}
@@ -0,0 +1,35 @@
+use std::io;
+use std::process::Command;
+
+/// Runs each of a build stage's hook commands through the shell, in order,
+/// with `env` added to their environment. The first one to fail stops the
+/// build.
+pub fn run(stage: &str, commands: &[String], env: &[(&str, &str)]) -> io::Result<()> {
+    for command in commands {
+        println!("  🪝 Running {} hook: {}", stage, command);
+        let status = shell(command)
+            .envs(env.iter().map(|(key, value)| (key, value)))
+            .status()?;
+        if !status.success() {
+            return Err(io::Error::other(format!(
+                "{} hook '{}' failed ({})",
+                stage, command, status
+            )));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(windows)]
+fn shell(command: &str) -> Command {
+    let mut shell = Command::new("cmd");
+    shell.arg("/C").arg(command);
+    shell
+}
+
+#[cfg(not(windows))]
+fn shell(command: &str) -> Command {
+    let mut shell = Command::new("sh");
+    shell.arg("-c").arg(command);
+    shell
+}
@@ -0,0 +1,217 @@
+use std::collections::HashMap;
+
+use crate::check::{self, Tag};
+
+/// Elements that never have an end tag
+const VOID: [&str; 14] = [
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "source", "track",
+    "wbr", "param",
+];
+
+/// Elements whose end tag may be left out, closed by whatever comes next
+const OPTIONAL_END: [&str; 17] = [
+    "html", "head", "body", "p", "li", "dt", "dd", "tr", "td", "th", "thead", "tbody", "tfoot",
+    "option", "optgroup", "colgroup", "caption",
+];
+
+/// Elements that close an open `<p>` when they start
+const CLOSES_PARAGRAPH: [&str; 28] = [
+    "address",
+    "article",
+    "aside",
+    "blockquote",
+    "details",
+    "div",
+    "dl",
+    "fieldset",
+    "figcaption",
+    "figure",
+    "footer",
+    "form",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "header",
+    "hr",
+    "main",
+    "nav",
+    "ol",
+    "p",
+    "pre",
+    "section",
+    "table",
+    "ul",
+];
+
+/// Elements that can't contain another of their kind
+const NOT_NESTED: [&str; 3] = ["a", "button", "form"];
+
+/// Finds what in a rendered page browsers would have to guess about:
+/// elements never closed, end tags with nothing to close, duplicate `id`s
+/// and elements where they can't go. Returns the problems with the line
+/// they're on, in page order.
+pub fn problems(html: &str) -> Vec<(usize, String)> {
+    let mut problems = Vec::new();
+    let mut ids: HashMap<&str, usize> = HashMap::new();
+    // Open elements, innermost last
+    let mut open: Vec<&Tag> = Vec::new();
+    let tags = check::tags(html);
+    let line = |tag: &Tag| check::line_number(html, tag.start);
+
+    for tag in &tags {
+        if tag.closing {
+            close(tag, &mut open, &mut problems, &line);
+            continue;
+        }
+
+        if let Some(id) = check::attribute(tag.text, "id").filter(|id| !id.is_empty()) {
+            if let Some(first) = ids.get(id) {
+                problems.push((
+                    line(tag),
+                    format!("id \"{}\" is already used on line {}", id, first),
+                ));
+            } else {
+                ids.insert(id, line(tag));
+            }
+        }
+
+        let name = tag.name.as_str();
+        // An open `<p>`, `<li>` and the like ends where a sibling starts
+        while let Some(top) = open.last()
+            && closes_implicitly(&top.name, name)
+        {
+            open.pop();
+        }
+
+        let in_foreign = open
+            .iter()
+            .any(|tag| tag.name == "svg" || tag.name == "math");
+        if NOT_NESTED.contains(&name)
+            && let Some(outer) = open.iter().rev().find(|open| open.name == name)
+        {
+            problems.push((
+                line(tag),
+                format!("<{}> inside the <{}> from line {}", name, name, line(outer)),
+            ));
+        }
+        if name == "li"
+            && !open
+                .last()
+                .is_some_and(|parent| ["ul", "ol", "menu"].contains(&parent.name.as_str()))
+        {
+            problems.push((line(tag), "<li> outside a list".to_string()));
+        }
+
+        let self_closing =
+            tag.text.ends_with("/>") && (in_foreign || name == "svg" || name == "math");
+        if !VOID.contains(&name) && !self_closing {
+            open.push(tag);
+        }
+    }
+
+    for tag in open {
+        if !OPTIONAL_END.contains(&tag.name.as_str()) {
+            problems.push((line(tag), format!("<{}> is never closed", tag.name)));
+        }
+    }
+    problems.sort_by_key(|(line, _)| *line);
+    problems
+}
+
+/// Closes the open element `tag` ends, reporting the elements inside it
+/// left open, or `tag` if there's nothing for it to close.
+fn close<'a>(
+    tag: &Tag,
+    open: &mut Vec<&'a Tag<'a>>,
+    problems: &mut Vec<(usize, String)>,
+    line: &impl Fn(&Tag) -> usize,
+) {
+    let Some(position) = open.iter().rposition(|open| open.name == tag.name) else {
+        let message = if tag.name == "p" {
+            "</p> with no <p> open; a block element inside a <p> ends it early".to_string()
+        } else if VOID.contains(&tag.name.as_str()) {
+            format!("</{}>, which never has an end tag", tag.name)
+        } else {
+            format!("</{}> with no <{}> open", tag.name, tag.name)
+        };
+        problems.push((line(tag), message));
+        return;
+    };
+
+    for unclosed in open.drain(position..).skip(1) {
+        if !OPTIONAL_END.contains(&unclosed.name.as_str()) {
+            problems.push((
+                line(unclosed),
+                format!(
+                    "<{}> is never closed before the </{}> on line {}",
+                    unclosed.name,
+                    tag.name,
+                    line(tag)
+                ),
+            ));
+        }
+    }
+}
+
+/// Whether an open `current` element ends where a `next` one starts.
+fn closes_implicitly(current: &str, next: &str) -> bool {
+    match current {
+        "p" => CLOSES_PARAGRAPH.contains(&next),
+        "li" => next == "li",
+        "dt" | "dd" => next == "dt" || next == "dd",
+        "tr" => next == "tr" || next == "tbody" || next == "tfoot",
+        "td" | "th" => ["td", "th", "tr", "tbody", "tfoot"].contains(&next),
+        "thead" | "tbody" => next == "tbody" || next == "tfoot",
+        "option" => next == "option" || next == "optgroup",
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn problems_accepts_valid_html_with_optional_end_tags() {
+        let html = concat!(
+            "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>x</title>\n",
+            "<body><p>One<p>Two<ul><li>a<li>b</ul>\n",
+            "<table><tr><td>1<td>2<tr><td>3</table>\n",
+            "<svg><path d=\"M0 0\"/></svg><br><img src=\"a.png\">\n",
+        );
+        assert_eq!(problems(html), []);
+    }
+
+    #[test]
+    fn problems_reports_what_browsers_would_guess_at() {
+        let html = concat!(
+            "<div id=\"a\"><span>\n",
+            "</div>\n",
+            "<p id=\"a\"><div>block</div></p>\n",
+            "<a href=\"/\"><a href=\"/b\">b</a></a>\n",
+            "<li>loose</li></br>\n",
+            "<section>\n",
+        );
+        assert_eq!(
+            problems(html),
+            [
+                (
+                    1,
+                    "<span> is never closed before the </div> on line 2".to_string()
+                ),
+                (3, "id \"a\" is already used on line 1".to_string()),
+                (
+                    3,
+                    "</p> with no <p> open; a block element inside a <p> ends it early".to_string()
+                ),
+                (4, "<a> inside the <a> from line 4".to_string()),
+                (5, "<li> outside a list".to_string()),
+                (5, "</br>, which never has an end tag".to_string()),
+                (6, "<section> is never closed".to_string()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,88 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Glob patterns for content files the build should pretend don't exist,
+/// gathered from the `ignore` setting and a `.breadignore` file.
+///
+/// Patterns follow a small subset of `.gitignore` syntax: `*` and `?` match
+/// within a single path segment and `**` across segments. A pattern without
+/// a `/` matches a file or directory name anywhere in the tree; one with a
+/// `/` is matched against the whole path relative to the content directory.
+/// A trailing `/` restricts a pattern to directories.
+#[derive(Debug, Default)]
+pub struct IgnoreRules {
+    patterns: Vec<String>,
+}
+
+impl IgnoreRules {
+    pub fn new(patterns: &[String]) -> Self {
+        IgnoreRules {
+            patterns: patterns.to_vec(),
+        }
+    }
+
+    /// Combines the configured patterns with those in `ignore_file`, which
+    /// holds one pattern per line with `#` starting a comment.
+    pub fn load(patterns: &[String], ignore_file: &Path) -> io::Result<Self> {
+        let mut rules = IgnoreRules::new(patterns);
+
+        if ignore_file.exists() {
+            let text = fs::read_to_string(ignore_file)?;
+            rules.patterns.extend(
+                text.lines()
+                    .map(str::trim)
+                    .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                    .map(str::to_string),
+            );
+        }
+
+        Ok(rules)
+    }
+
+    /// Whether `relative` (a `/`-separated path inside the content directory)
+    /// should be skipped.
+    pub fn is_ignored(&self, relative: &str, is_dir: bool) -> bool {
+        let name = relative.rsplit('/').next().unwrap_or(relative);
+
+        self.patterns.iter().any(|pattern| {
+            let (pattern, dir_only) = match pattern.strip_suffix('/') {
+                Some(pattern) => (pattern, true),
+                None => (pattern.as_str(), false),
+            };
+            if dir_only && !is_dir {
+                return false;
+            }
+
+            if pattern.contains('/') {
+                glob_match(pattern.trim_start_matches('/'), relative)
+            } else {
+                glob_match(pattern, name)
+            }
+        })
+    }
+}
+
+fn glob_match(pattern: &str, text: &str) -> bool {
+    match_bytes(pattern.as_bytes(), text.as_bytes())
+}
+
+fn match_bytes(pattern: &[u8], text: &[u8]) -> bool {
+    match pattern {
+        [] => text.is_empty(),
+        [b'*', b'*', rest @ ..] => {
+            // `**/` may also match no directories at all
+            let rest_after_slash = rest.strip_prefix(b"/").unwrap_or(rest);
+            (0..=text.len())
+                .any(|i| match_bytes(rest, &text[i..]) || match_bytes(rest_after_slash, &text[i..]))
+        }
+        [b'*', rest @ ..] => {
+            let segment_end = text.iter().position(|&b| b == b'/').unwrap_or(text.len());
+            (0..=segment_end).any(|i| match_bytes(rest, &text[i..]))
+        }
+        [b'?', rest @ ..] => {
+            matches!(text, [c, tail @ ..] if *c != b'/' && match_bytes(rest, tail))
+        }
+        [p, rest @ ..] => matches!(text, [c, tail @ ..] if c == p && match_bytes(rest, tail)),
+    }
+}
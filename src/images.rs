@@ -0,0 +1,222 @@
+use image::imageops::FilterType;
+use image::{DynamicImage, ImageFormat};
+use serde::Serialize;
+use std::io;
+use std::path::Path;
+
+use crate::config::ImagesConfig;
+use crate::sync;
+
+/// What the image pipeline will publish for one source image.
+#[derive(Debug, Clone, Serialize)]
+pub struct ImageInfo {
+    /// Dimensions of the published image, after any downscaling
+    pub width: u32,
+    pub height: u32,
+    /// Whether the original is larger than `max_width` and gets downscaled
+    pub resized: bool,
+    /// Path of the WebP copy, relative to the output root
+    pub webp: Option<String>,
+    /// Smaller copies for `srcset`, narrowest first
+    pub variants: Vec<ImageVariant>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ImageVariant {
+    pub width: u32,
+    pub height: u32,
+    pub path: String,
+    pub webp: Option<String>,
+}
+
+const OPTIMIZABLE_EXTENSIONS: [&str; 3] = ["png", "jpg", "jpeg"];
+
+/// Reads an image's dimensions (from its header only) and works out what the
+/// pipeline will do with it. Returns `None` for files the pipeline skips:
+/// non-raster formats, and anything outside the configured directories.
+pub fn plan(path: &Path, relative: &str, config: &ImagesConfig) -> io::Result<Option<ImageInfo>> {
+    if !config.optimize {
+        return Ok(None);
+    }
+
+    let extension = path
+        .extension()
+        .and_then(|s| s.to_str())
+        .map(|s| s.to_ascii_lowercase())
+        .unwrap_or_default();
+    if !OPTIMIZABLE_EXTENSIONS.contains(&extension.as_str()) {
+        return Ok(None);
+    }
+
+    let in_configured_dir = config.directories.is_empty()
+        || config
+            .directories
+            .iter()
+            .any(|dir| relative.starts_with(&format!("{}/", dir.trim_matches('/'))));
+    if !in_configured_dir {
+        return Ok(None);
+    }
+
+    let (width, height) = image::image_dimensions(path).map_err(|e| image_error(path, e))?;
+    let resized = width > config.max_width;
+    let (width, height) = if resized {
+        (
+            config.max_width,
+            scale_height(width, height, config.max_width),
+        )
+    } else {
+        (width, height)
+    };
+
+    let (stem, extension) = relative.rsplit_once('.').unwrap_or((relative, ""));
+    let webp = config.webp.then(|| format!("{}.webp", stem));
+
+    let mut variant_widths: Vec<u32> = config
+        .widths
+        .iter()
+        .copied()
+        .filter(|w| *w > 0 && *w < width)
+        .collect();
+    variant_widths.sort_unstable();
+    variant_widths.dedup();
+
+    let variants = variant_widths
+        .into_iter()
+        .map(|w| ImageVariant {
+            width: w,
+            height: scale_height(width, height, w),
+            path: format!("{}-{}w.{}", stem, w, extension),
+            webp: config.webp.then(|| format!("{}-{}w.webp", stem, w)),
+        })
+        .collect();
+
+    Ok(Some(ImageInfo {
+        width,
+        height,
+        resized,
+        webp,
+        variants,
+    }))
+}
+
+fn scale_height(width: u32, height: u32, new_width: u32) -> u32 {
+    let scaled = u64::from(height) * u64::from(new_width) / u64::from(width);
+    (scaled as u32).max(1)
+}
+
+/// Writes the optimized versions of `source` into `output_dir`: the image
+/// itself at `relative` (downscaled if it was oversized), its WebP copy, and
+/// the narrower `srcset` variants.
+pub fn process(
+    source: &Path,
+    relative: &str,
+    info: &ImageInfo,
+    output_dir: &Path,
+) -> io::Result<()> {
+    if !info.resized && info.webp.is_none() && info.variants.is_empty() {
+        return Ok(());
+    }
+
+    let mut img = image::open(source).map_err(|e| image_error(source, e))?;
+
+    if info.resized {
+        img = img.resize(info.width, info.height, FilterType::Lanczos3);
+        save(&img, &output_dir.join(relative))?;
+        println!(
+            "  🖼  Resized: {} to {}x{}",
+            relative, info.width, info.height
+        );
+    }
+
+    if let Some(webp) = &info.webp {
+        save_webp(&img, &output_dir.join(webp))?;
+        println!("  🖼  Encoded: {}", webp);
+    }
+
+    for variant in &info.variants {
+        let scaled = img.resize(variant.width, variant.height, FilterType::Lanczos3);
+        save(&scaled, &output_dir.join(&variant.path))?;
+        if let Some(webp) = &variant.webp {
+            save_webp(&scaled, &output_dir.join(webp))?;
+        }
+        println!("  🖼  Variant: {}", variant.path);
+    }
+
+    Ok(())
+}
+
+/// Writes `img` to `dest` in the format its extension names, as a new file
+/// rather than through any hard link already there.
+fn save(img: &DynamicImage, dest: &Path) -> io::Result<()> {
+    sync::unlink(dest)?;
+    img.save(dest).map_err(|e| image_error(dest, e))
+}
+
+fn save_webp(img: &DynamicImage, dest: &Path) -> io::Result<()> {
+    sync::unlink(dest)?;
+    // The WebP encoder only takes 8-bit RGB(A)
+    DynamicImage::ImageRgba8(img.to_rgba8())
+        .save_with_format(dest, ImageFormat::WebP)
+        .map_err(|e| image_error(dest, e))
+}
+
+fn image_error(path: &Path, error: image::ImageError) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("{}: {}", path.display(), error),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::scratch_dir;
+    use std::fs;
+
+    fn config() -> ImagesConfig {
+        ImagesConfig {
+            optimize: true,
+            max_width: 20,
+            webp: false,
+            directories: Vec::new(),
+            widths: vec![10, 30],
+        }
+    }
+
+    #[test]
+    fn plan_downscales_oversized_images_and_drops_wider_variants() {
+        let dir = scratch_dir("images-plan");
+        let path = dir.join("photo.png");
+        image::RgbImage::new(40, 4).save(&path).unwrap();
+
+        let info = plan(&path, "img/photo.png", &config()).unwrap().unwrap();
+        assert!(info.resized);
+        assert_eq!((info.width, info.height), (20, 2));
+        assert_eq!(info.variants.len(), 1);
+        assert_eq!(info.variants[0].path, "img/photo-10w.png");
+
+        let elsewhere = ImagesConfig {
+            directories: vec!["photos".to_string()],
+            ..config()
+        };
+        assert!(plan(&path, "img/photo.png", &elsewhere).unwrap().is_none());
+    }
+
+    #[test]
+    fn process_writes_a_new_file_over_a_hard_link() {
+        let dir = scratch_dir("images-process");
+        let (source, output) = (dir.join("photo.png"), dir.join("output"));
+        fs::create_dir_all(&output).unwrap();
+        image::RgbImage::new(40, 4).save(&source).unwrap();
+        fs::hard_link(&source, output.join("photo.png")).unwrap();
+
+        let info = plan(&source, "photo.png", &config()).unwrap().unwrap();
+        process(&source, "photo.png", &info, &output).unwrap();
+
+        assert_eq!(image::image_dimensions(&source).unwrap(), (40, 4));
+        assert_eq!(
+            image::image_dimensions(output.join("photo.png")).unwrap(),
+            (20, 2)
+        );
+    }
+}
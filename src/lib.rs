@@ -0,0 +1,3277 @@
+//! Bread, a minimal static site generator, as a library. `build_site` runs
+//! the whole build; programs embedding it can hook into the pipeline by
+//! registering a [`Plugin`].
+
+mod activitypub;
+mod assets;
+mod authors;
+mod blogroll;
+mod check;
+pub mod cli;
+pub mod config;
+mod data;
+mod dates;
+pub mod deploy;
+mod diff;
+mod emails;
+mod emoji;
+mod external_links;
+mod feeds;
+mod formats;
+mod gemini;
+mod history;
+mod hooks;
+mod html_lint;
+mod ignore;
+mod images;
+mod links;
+mod lint;
+mod markdown;
+mod menus;
+mod minify;
+mod notebook;
+mod plugins;
+mod protect;
+mod prune;
+pub mod publish;
+mod relative_urls;
+mod report;
+mod search;
+mod shortcodes;
+mod sitemap;
+mod social;
+mod staging;
+mod sync;
+mod templates;
+pub mod theme;
+mod walk;
+mod wasm;
+mod watch;
+mod well_known;
+
+pub use plugins::{Plugin, Plugins};
+
+use assets::AssetManifest;
+use authors::Author;
+use config::{Config, FeedContent, IndieWebConfig, MarkdownConfig, PostSort, Severity};
+use formats::InputFormat;
+use history::FileHistory;
+use ignore::IgnoreRules;
+use images::ImageInfo;
+use links::LinkGraph;
+use markdown::{
+    RenderContext, RenderedMarkdown, markdown_to_html, plain_text_body, reading_time, slugify,
+    summary_source, toc_to_html, wikilink_key, word_count,
+};
+use menus::{MenuEntry, MenuItem};
+use report::BuildReport;
+use serde::Serialize;
+use std::cell::{Cell, RefCell};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+use sync::FileSync;
+use tinytemplate::TinyTemplate;
+use tinytemplate::format_unescaped;
+use walk::Walk;
+
+/// What page templates render with. Plain text like titles, dates and tags
+/// comes escaped for HTML, as templates insert values as they are; `raw` has
+/// the unescaped text for places that aren't HTML, like JSON-LD. Fields
+/// holding HTML, like `content`, `toc` and `tags`, are passed through.
+#[derive(Serialize, Debug, Default)]
+struct PageContext {
+    #[serde(serialize_with = "escaped")]
+    title: String,
+    /// Where the posts listing is, see `Config::listing_url`; empty if there
+    /// is none
+    posts_url: String,
+    /// Where the site is served from, like `/bread`, for site URLs to go
+    /// after; empty at the root
+    base_path: String,
+    content: String,
+    tags: String,
+    #[serde(serialize_with = "escaped")]
+    keywords: String,
+    #[serde(serialize_with = "escaped")]
+    date: String,
+    toc: String,
+    word_count: usize,
+    reading_time: usize,
+    prev_url: String,
+    #[serde(serialize_with = "escaped")]
+    prev_title: String,
+    next_url: String,
+    #[serde(serialize_with = "escaped")]
+    next_title: String,
+    related_posts: Vec<PostLink>,
+    /// Pages linking to this one
+    backlinks: Vec<PostLink>,
+    assets: BTreeMap<String, String>,
+    #[serde(serialize_with = "escaped")]
+    series: String,
+    series_url: String,
+    series_parts: Vec<SeriesPart>,
+    series_position: usize,
+    /// From the `description:` frontmatter, or the start of the page's text
+    #[serde(serialize_with = "escaped")]
+    description: String,
+    /// Full URL of the page
+    canonical_url: String,
+    /// Full URL of the social preview image, if there is one
+    image: String,
+    #[serde(serialize_with = "escaped")]
+    site_name: String,
+    /// Ready-made canonical link, description, Open Graph and Twitter Card tags
+    meta_tags: String,
+    /// Keep search engines from indexing the page, for unlisted pages
+    noindex: bool,
+    /// Load KaTeX to typeset the page's math
+    math: bool,
+    /// Load Mermaid to draw the page's diagrams
+    mermaid: bool,
+    /// Language of the page, if the site lists its languages
+    lang: String,
+    /// When the page's source was last committed, if `git_history` is on
+    #[serde(serialize_with = "escaped")]
+    last_modified: String,
+    /// Who has committed to the page's source, most recent first
+    #[serde(serialize_with = "escaped_list")]
+    git_authors: Vec<String>,
+    /// Where to edit the page's source, if the site's repository is configured
+    edit_url: String,
+    authors: Vec<Author>,
+    /// Everything in `data/`, see `data::load`
+    data: serde_json::Value,
+    /// Site menus by name, see `menus::for_page`
+    menus: BTreeMap<String, Vec<MenuItem>>,
+    /// The same page in the site's other languages
+    translations: Vec<Translation>,
+    /// The page in formats other than HTML, see `AlternatesConfig`
+    alternates: Vec<Alternate>,
+    /// Frontmatter keys bread doesn't use itself, see `Frontmatter::extra`
+    extra: BTreeMap<String, serde_json::Value>,
+    /// Start of the URLs of pages in this page's language, like `/de`; empty
+    /// for the default language
+    language_prefix: String,
+    raw: RawText,
+}
+
+/// A page's text without escaping, see `PageContext`.
+#[derive(Serialize, Debug, Default)]
+struct RawText {
+    title: String,
+    description: String,
+}
+
+impl RawText {
+    fn of(context: &PageContext) -> Self {
+        RawText {
+            title: context.title.clone(),
+            description: context.description.clone(),
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone)]
+struct Translation {
+    lang: String,
+    url: String,
+    /// Full URL, for `hreflang` links
+    absolute_url: String,
+}
+
+/// A version of a page in another format.
+#[derive(Serialize, Debug, Clone)]
+struct Alternate {
+    /// MIME type, like `text/plain`
+    media_type: String,
+    url: String,
+}
+
+#[derive(Serialize, Debug)]
+struct SeriesPart {
+    #[serde(serialize_with = "escaped")]
+    title: String,
+    url: String,
+    part: usize,
+    current: bool,
+}
+
+#[derive(Serialize, Debug)]
+struct PostLink {
+    #[serde(serialize_with = "escaped")]
+    title: String,
+    url: String,
+    #[serde(serialize_with = "escaped")]
+    date: String,
+}
+
+/// The posts of one year, newest first.
+#[derive(Serialize)]
+struct YearPosts<'a> {
+    year: String,
+    posts: Vec<&'a PostMetadata>,
+}
+
+/// What standalone template pages render with: the whole site rather than
+/// a single page.
+#[derive(Serialize)]
+struct SiteContext<'a> {
+    #[serde(serialize_with = "escaped")]
+    title: String,
+    #[serde(serialize_with = "escaped")]
+    site_name: String,
+    canonical_url: String,
+    lang: String,
+    language_prefix: String,
+    base_path: String,
+    posts_url: String,
+    /// Newest first
+    posts: &'a [PostMetadata],
+    /// The newest `posts.recent` posts
+    recent: &'a [PostMetadata],
+    /// The posts in each order `posts.sort` can take, by its name: `date`,
+    /// `title` and `weight`
+    sorted: BTreeMap<&'static str, Vec<&'a PostMetadata>>,
+    /// Posts by year, newest first; undated posts are left out
+    years: Vec<YearPosts<'a>>,
+    tags: Vec<TagPosts<'a>>,
+    /// The posts of each tag by its slug, for `{{ for post in tagged.rust }}`
+    tagged: BTreeMap<String, Vec<&'a PostMetadata>>,
+    data: serde_json::Value,
+    menus: BTreeMap<String, Vec<MenuItem>>,
+    assets: BTreeMap<String, String>,
+}
+
+#[derive(Serialize, Debug)]
+struct PostsContext {
+    language_prefix: String,
+    base_path: String,
+    posts_url: String,
+    data: serde_json::Value,
+    menus: BTreeMap<String, Vec<MenuItem>>,
+    post_count: usize,
+    posts: String,
+    tag_options: String,
+    assets: BTreeMap<String, String>,
+}
+
+#[derive(Serialize, Debug)]
+struct TagContext {
+    #[serde(serialize_with = "escaped")]
+    tag: String,
+    slug: String,
+    language_prefix: String,
+    base_path: String,
+    posts_url: String,
+    /// The tag's feed, if feeds are written
+    feed_url: String,
+    data: serde_json::Value,
+    menus: BTreeMap<String, Vec<MenuItem>>,
+    posts: String,
+    assets: BTreeMap<String, String>,
+}
+
+/// The settings at the top of a content file, between `---` lines.
+#[derive(Debug, Default)]
+pub struct Frontmatter {
+    pub title: Option<String>,
+    pub date: Option<String>,
+    pub tags: Option<Vec<String>>,
+    pub slug: Option<String>,
+    pub toc: Option<bool>,
+    pub summary: Option<String>,
+    pub series: Option<String>,
+    pub series_part: Option<usize>,
+    /// Position in the posts listing when it's sorted by weight
+    pub weight: Option<i32>,
+    /// Keep the post at the top of the posts listing
+    pub pinned: bool,
+    /// Leave the page out of the build unless `drafts` is on
+    pub draft: bool,
+    /// When the page goes live: builds before then leave it out, like a
+    /// draft. Stands in for `date` when there's none.
+    pub publish_date: Option<String>,
+    /// Publish the page but leave it out of listings, feeds, the sitemap and
+    /// the search index, so only people given the link find it
+    pub unlisted: bool,
+    /// Publish the page's content encrypted, see `ProtectedConfig`
+    pub protected: bool,
+    /// The password unlocking a protected page, if not the site's
+    pub password: Option<String>,
+    /// From `kind: post|page`; see `PageKind::of`
+    pub kind: Option<PageKind>,
+    /// For `.html` content files, whether to wrap them in the page template
+    /// (the default) or publish them as written, see `html_with_frontmatter`
+    pub wrap: Option<bool>,
+    pub description: Option<String>,
+    pub image: Option<String>,
+    /// Old URLs that should redirect to this page
+    pub aliases: Vec<String>,
+    /// From `author:` or `authors:`, keys of the `[authors]` table or names
+    pub authors: Vec<String>,
+    /// Menus to list the page in, from `menu:`
+    pub menus: Vec<String>,
+    /// The page's label in menus, if not its title
+    pub menu_name: Option<String>,
+    pub menu_weight: Option<i32>,
+    /// `markdown.<option>: true|false` overrides of the site's markdown settings
+    pub markdown: Vec<(String, bool)>,
+    /// Keys bread doesn't know, for templates as `extra.<key>`: `true` and
+    /// `false` as booleans, `- item` lines after the key as a list, anything
+    /// else as a string
+    pub extra: BTreeMap<String, serde_json::Value>,
+}
+
+/// The frontmatter keys bread reads itself, besides `markdown.<option>`.
+const FRONTMATTER_KEYS: [&str; 26] = [
+    "title",
+    "date",
+    "slug",
+    "toc",
+    "summary",
+    "description",
+    "image",
+    "series",
+    "series_part",
+    "weight",
+    "pinned",
+    "unlisted",
+    "draft",
+    "publish_date",
+    "protected",
+    "password",
+    "wrap",
+    "kind",
+    "aliases",
+    "author",
+    "authors",
+    "menu",
+    "menu_name",
+    "menu_weight",
+    "math",
+    "tags",
+];
+
+impl Frontmatter {
+    fn parse(content: &str) -> (Self, &str) {
+        let mut frontmatter = Frontmatter::default();
+        if !content.starts_with("---") {
+            return (frontmatter, content);
+        }
+        let Some(end_pos) = content[3..].find("\n---") else {
+            return (frontmatter, content);
+        };
+        let fm_section = &content[3..3 + end_pos];
+        let markdown_content = &content[3 + end_pos + 4..];
+
+        let lines = fm_section.lines();
+        let mut current_key: Option<&str> = None;
+        let mut tag_list: Vec<String> = Vec::new();
+
+        for line in lines {
+            let trimmed = line.trim();
+
+            if trimmed.is_empty() {
+                continue;
+            }
+
+            if let Some(item) = trimmed.strip_prefix('-') {
+                let item = item.trim().to_string();
+                if item.is_empty() {
+                    continue;
+                }
+                match current_key {
+                    Some("tags") => tag_list.push(item),
+                    Some("aliases") => frontmatter.aliases.push(item),
+                    Some("authors") => frontmatter.authors.push(item),
+                    Some("menu") => frontmatter.menus.push(item),
+                    Some(key) => {
+                        if let Some(serde_json::Value::Array(items)) =
+                            frontmatter.extra.get_mut(key)
+                        {
+                            items.push(serde_json::Value::String(item));
+                        }
+                    }
+                    None => {}
+                }
+                continue;
+            }
+
+            if let Some(colon_pos) = trimmed.find(':') {
+                if current_key == Some("tags") && !tag_list.is_empty() {
+                    frontmatter.tags = Some(tag_list.clone());
+                    tag_list.clear();
+                }
+
+                let key = trimmed[..colon_pos].trim();
+                let value = trimmed[colon_pos + 1..].trim();
+
+                current_key = Some(key);
+
+                match key {
+                    "title" => frontmatter.title = Some(value.to_string()),
+                    "date" => frontmatter.date = Some(value.to_string()),
+                    "slug" => frontmatter.slug = Some(value.to_string()),
+                    "toc" => frontmatter.toc = Some(value == "true"),
+                    "summary" => frontmatter.summary = Some(value.to_string()),
+                    "description" => frontmatter.description = Some(value.to_string()),
+                    "image" => frontmatter.image = Some(value.to_string()),
+                    "series" => frontmatter.series = Some(value.to_string()),
+                    "series_part" => frontmatter.series_part = value.parse().ok(),
+                    "weight" => frontmatter.weight = value.parse().ok(),
+                    "pinned" => frontmatter.pinned = value == "true",
+                    "unlisted" => frontmatter.unlisted = value == "true",
+                    "draft" => frontmatter.draft = value == "true",
+                    "publish_date" => frontmatter.publish_date = Some(value.to_string()),
+                    "protected" => frontmatter.protected = value == "true",
+                    "password" => frontmatter.password = Some(value.to_string()),
+                    "wrap" => frontmatter.wrap = Some(value == "true"),
+                    "kind" => match value {
+                        "post" => frontmatter.kind = Some(PageKind::Post),
+                        "page" => frontmatter.kind = Some(PageKind::Page),
+                        _ => {}
+                    },
+                    // Either `[/a/, /b.html]` or a `- /a/` list on the following lines
+                    "aliases" => frontmatter.aliases.extend(inline_list(value)),
+                    "author" => frontmatter.authors.push(value.to_string()),
+                    "authors" => frontmatter.authors.extend(inline_list(value)),
+                    "menu" => frontmatter.menus.extend(inline_list(value)),
+                    "menu_name" => frontmatter.menu_name = Some(value.to_string()),
+                    "menu_weight" => frontmatter.menu_weight = value.parse().ok(),
+                    // Shorthand for `markdown.math`
+                    "math" => frontmatter
+                        .markdown
+                        .push(("math".to_string(), value == "true")),
+                    _ if key.starts_with("markdown.") => {
+                        frontmatter
+                            .markdown
+                            .push((key["markdown.".len()..].to_string(), value == "true"));
+                        current_key = None;
+                    }
+                    "tags" => {
+                        if !value.is_empty() {
+                            frontmatter.tags = Some(
+                                value
+                                    .split(',')
+                                    .map(|s| s.trim().to_string())
+                                    .filter(|s| !s.is_empty())
+                                    .collect(),
+                            );
+                            current_key = None;
+                        }
+                    }
+                    _ => {
+                        let value = match value {
+                            "" => serde_json::Value::Array(Vec::new()),
+                            "true" => serde_json::Value::Bool(true),
+                            "false" => serde_json::Value::Bool(false),
+                            _ => serde_json::Value::String(value.to_string()),
+                        };
+                        frontmatter.extra.insert(key.to_string(), value);
+                    }
+                }
+            }
+        }
+
+        // Don't forget to save tags if we were collecting them at the end
+        if current_key == Some("tags") && !tag_list.is_empty() {
+            frontmatter.tags = Some(tag_list);
+        }
+
+        (frontmatter, markdown_content.trim_start())
+    }
+
+    /// The site's markdown settings with this page's overrides applied.
+    fn markdown_config(&self, site_config: &MarkdownConfig) -> MarkdownConfig {
+        let mut config = site_config.clone();
+        for (option, value) in &self.markdown {
+            config.set(option, *value);
+        }
+        config
+    }
+}
+
+/// The key bread knows that `key`, one it doesn't, is most likely a typo of,
+/// if any is close enough. Custom keys for templates rarely are.
+fn misspelled_key(key: &str) -> Option<&'static str> {
+    FRONTMATTER_KEYS
+        .iter()
+        .map(|known| (edit_distance(key, known), *known))
+        .filter(|(distance, _)| *distance <= 2)
+        .min()
+        .map(|(_, known)| known)
+}
+
+/// How many single-character insertions, deletions or substitutions turn
+/// `a` into `b`.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, a_char) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a_char != *b_char);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+/// Whether a content file is a post, dated and listed in `posts.html`, the
+/// feeds and the archives, or a standalone page like "About" that only
+/// renders with the base template.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PageKind {
+    Post,
+    Page,
+}
+
+impl PageKind {
+    /// The kind a page's frontmatter gives it, otherwise `Page` for HTML
+    /// files and files in a `pages/` directory of the content, and `Post`
+    /// for everything else.
+    fn of(md_file: &Path, frontmatter: &Frontmatter, content_dir: &Path) -> Self {
+        if let Some(kind) = frontmatter.kind {
+            return kind;
+        }
+        let in_pages_dir = relative_dir(md_file, content_dir)
+            .components()
+            .any(|c| c.as_os_str() == "pages");
+        if in_pages_dir || is_html(md_file) {
+            PageKind::Page
+        } else {
+            PageKind::Post
+        }
+    }
+}
+
+/// The items of a `[a, b]` frontmatter list.
+fn inline_list(value: &str) -> impl Iterator<Item = String> + '_ {
+    value
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .split(',')
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+}
+
+#[derive(Serialize, Debug, Clone)]
+struct PostMetadata {
+    #[serde(serialize_with = "escaped")]
+    title: String,
+    #[serde(serialize_with = "escaped")]
+    date: String,
+    #[serde(serialize_with = "escaped_list")]
+    tags: Vec<String>,
+    url: String,
+    word_count: usize,
+    reading_time: usize,
+    summary: String,
+    #[serde(serialize_with = "escaped_option")]
+    series: Option<String>,
+    series_part: Option<usize>,
+    lang: String,
+    #[serde(serialize_with = "escaped_list")]
+    authors: Vec<String>,
+    weight: i32,
+    pinned: bool,
+    /// Frontmatter keys bread doesn't use itself, see `Frontmatter::extra`
+    extra: BTreeMap<String, serde_json::Value>,
+    /// The whole post rendered, for feeds with `content = "full"`
+    #[serde(skip)]
+    content: String,
+}
+
+/// The posts carrying one tag, newest first. Tags differing only in case or
+/// punctuation are merged, under the spelling seen first.
+#[derive(Serialize)]
+struct TagPosts<'a> {
+    #[serde(serialize_with = "escaped")]
+    name: String,
+    slug: String,
+    posts: Vec<&'a PostMetadata>,
+}
+
+/// Everything the pages of a single build share.
+struct Site<'a> {
+    /// Where content is read from, see `Config::content_roots`
+    content_dirs: &'a [PathBuf],
+    output_dir: &'a Path,
+    config: &'a Config,
+    tt: TinyTemplate<'a>,
+    template_dir: &'a Path,
+    /// The text of every template in `tt` by name, for error messages
+    template_sources: BTreeMap<String, &'a str>,
+    /// Posts by language, each sorted newest first
+    posts: BTreeMap<String, Vec<PostMetadata>>,
+    /// Every translated page's versions, by translation key (see
+    /// `PageLocation`)
+    translations: BTreeMap<String, Vec<Translation>>,
+    /// Published asset paths keyed for templates, see `AssetManifest::template_map`
+    assets: BTreeMap<String, String>,
+    /// The contents of `data/`, for templates
+    data: serde_json::Value,
+    /// Items of every menu, see `menus::collect`
+    menus: Vec<MenuEntry>,
+    /// Raster images known to the image pipeline, for responsive markup
+    images: &'a BTreeMap<String, ImageInfo>,
+    /// Shortcode templates, keyed by the name they're registered with in `tt`
+    shortcodes: &'a BTreeMap<String, String>,
+    /// Page URLs by wikilink key, see `collect_wikilink_targets`
+    wikilinks: BTreeMap<String, String>,
+    /// Links between pages, see `collect_link_graph`
+    link_graph: LinkGraph,
+    /// Commit history of content files by content-relative path, when
+    /// `git_history` is on
+    history: BTreeMap<String, FileHistory>,
+    base_path: String,
+    /// Whether `templates/404.html` exists, registered as `404`
+    has_not_found_template: bool,
+    plugins: &'a Plugins,
+    /// HTML written so far, for the build report
+    bytes_written: Cell<u64>,
+    /// HTML converted from other formats by source path, so each file is
+    /// only converted once per build
+    converted: RefCell<BTreeMap<PathBuf, String>>,
+    /// Whether any page has code blocks with copy buttons, which need
+    /// `COPY_CODE_SCRIPT`
+    has_copyable_code: Cell<bool>,
+    /// Whether any page is protected, which needs `protect::DECRYPT_SCRIPT`
+    has_protected: Cell<bool>,
+}
+
+impl Site<'_> {
+    /// The content directory `path` is in.
+    fn content_dir(&self, path: &Path) -> &Path {
+        content_root(path, self.content_dirs)
+    }
+
+    /// The posts written in `lang`, newest first.
+    fn posts_for(&self, lang: &str) -> &[PostMetadata] {
+        self.posts.get(lang).map_or(&[], Vec::as_slice)
+    }
+
+    /// The URL of the posts listing in `lang`, if the site has one and its
+    /// template.
+    fn listing_url(&self, lang: &str) -> Option<String> {
+        let template = &self.config.posts.listing_template;
+        self.config
+            .listing_url(lang)
+            .filter(|_| self.template_sources.contains_key(template))
+    }
+
+    /// `listing_url` for templates, empty without a listing.
+    fn posts_url(&self, lang: &str) -> String {
+        self.listing_url(lang).unwrap_or_default()
+    }
+
+    /// `base_path` as templates get it, without the trailing slash.
+    fn link_prefix(&self) -> String {
+        self.base_path.trim_end_matches('/').to_string()
+    }
+
+    /// The site's menus as seen from the page at `url`.
+    fn menus_for(&self, lang: &str, url: &str) -> BTreeMap<String, Vec<MenuItem>> {
+        menus::for_page(&self.menus, lang, url)
+    }
+
+    /// The template for pages in `dir`, relative to the content directory:
+    /// the `base.html` of the closest matching directory under the template
+    /// directory, see `templates::load`, or else `base`.
+    fn page_template(&self, dir: &Path) -> String {
+        dir.ancestors()
+            .filter(|dir| !dir.as_os_str().is_empty())
+            .map(|dir| format!("{}/base", dir.to_string_lossy().replace('\\', "/")))
+            .find(|name| self.template_sources.contains_key(name))
+            .unwrap_or_else(|| "base".to_string())
+    }
+
+    /// Where the template registered as `name` goes in the template
+    /// directory.
+    fn template_path(&self, name: &str) -> PathBuf {
+        self.template_dir.join(format!("{}.html", name))
+    }
+
+    /// Renders the template registered as `name`, with errors pointing into
+    /// its file. Templates are only required once something renders with
+    /// them.
+    fn render<C: Serialize>(&self, name: &str, context: &C) -> io::Result<String> {
+        if !self.template_sources.contains_key(name) {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("{} is missing", self.template_path(name).display()),
+            ));
+        }
+        self.tt
+            .render(name, context)
+            .map_err(|e| templates::error(&e, name, self.template_dir, &self.template_sources))
+    }
+
+    /// Renders the body of the content file at `path` to HTML as its format
+    /// calls for: markdown with bread's renderer, HTML as it is, and other
+    /// formats with their converter.
+    fn render_source(
+        &self,
+        path: &Path,
+        source: &str,
+        markdown_config: &MarkdownConfig,
+        url: &str,
+    ) -> io::Result<RenderedMarkdown> {
+        let command = match InputFormat::of(path) {
+            Some(InputFormat::Html) => return Ok(RenderedMarkdown::from_html(source.to_string())),
+            Some(InputFormat::AsciiDoc) => &self.config.formats.asciidoc_command,
+            Some(InputFormat::Org) => &self.config.formats.org_command,
+            Some(InputFormat::Markdown | InputFormat::Notebook) | None => {
+                return Ok(markdown_to_html(
+                    source,
+                    markdown_config,
+                    &self.render_context(url),
+                ));
+            }
+        };
+
+        if let Some(html) = self.converted.borrow().get(path) {
+            return Ok(RenderedMarkdown::from_html(html.clone()));
+        }
+        let html = formats::convert(command, source, path)?;
+        self.converted
+            .borrow_mut()
+            .insert(path.to_path_buf(), html.clone());
+        Ok(RenderedMarkdown::from_html(html))
+    }
+
+    fn render_context<'s>(&'s self, page_url: &'s str) -> RenderContext<'s> {
+        RenderContext {
+            images: self.images,
+            wikilinks: &self.wikilinks,
+            base_path: &self.base_path,
+            page_url,
+        }
+    }
+}
+
+/// What a rendered page leaves behind for site-wide outputs like the search index.
+#[derive(Debug)]
+struct BuiltPage {
+    title: String,
+    url: String,
+    date: String,
+    /// From git, if `git_history` is on
+    last_modified: String,
+    tags: Vec<String>,
+    text: String,
+    word_count: usize,
+    aliases: Vec<String>,
+    /// Kept out of the sitemap and search index, see `Frontmatter::unlisted`
+    unlisted: bool,
+}
+
+/// Where a content file is published, and in which language.
+struct PageLocation {
+    /// Output directory, relative to the output root
+    dir: PathBuf,
+    /// The HTML filename: the page's `slug`, or its file stem
+    filename: String,
+    url: String,
+    lang: String,
+    /// Shared by all translations of a page: its path in the content
+    /// directory without the language directory or suffix and extension
+    translation_key: String,
+}
+
+/// Works out where a content file goes. With several `languages`
+/// configured, a leading `<lang>/` directory or a `.<lang>` suffix on the
+/// file stem sets the page's language, and pages not in the default
+/// language move under `<lang>/`.
+fn page_location(
+    md_file: &Path,
+    frontmatter: &Frontmatter,
+    content_dir: &Path,
+    config: &Config,
+) -> PageLocation {
+    let mut segments: Vec<String> = relative_dir(md_file, content_dir)
+        .iter()
+        .map(|segment| segment.to_string_lossy().into_owned())
+        .collect();
+    let mut stem = md_file
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("output");
+    let mut lang = config.default_language().to_string();
+
+    if let Some(first) = segments.first()
+        && config.languages.contains(first)
+    {
+        lang = segments.remove(0);
+    } else if let Some((name, suffix)) = stem.rsplit_once('.')
+        && config.languages.iter().any(|l| l == suffix)
+    {
+        lang = suffix.to_string();
+        stem = name;
+    }
+
+    let translation_key = segments
+        .iter()
+        .map(String::as_str)
+        .chain([stem])
+        .collect::<Vec<_>>()
+        .join("/");
+    let slug = frontmatter.slug.as_deref().unwrap_or(stem);
+    let mut filename = format!("{}.html", slug);
+
+    let mut dir = PathBuf::from(config.language_prefix(&lang).trim_start_matches('/'));
+    let permalink = if PageKind::of(md_file, frontmatter, content_dir) == PageKind::Post
+        && !is_index_page(md_file, frontmatter)
+        && !is_not_found_page(md_file, content_dir)
+    {
+        expand_permalink(&config.posts.permalink, frontmatter, slug, &segments)
+    } else {
+        None
+    };
+    let url = match permalink {
+        Some(path) if path.ends_with('/') => {
+            dir.push(path.trim_matches('/'));
+            filename = "index.html".to_string();
+            format!("/{}/", dir.display()).replace("//", "/")
+        }
+        Some(path) => {
+            let (parent, name) = path.rsplit_once('/').unwrap_or(("", &path));
+            if !parent.is_empty() {
+                dir.push(parent);
+            }
+            filename = if name.contains('.') {
+                name.to_string()
+            } else {
+                format!("{}.html", name)
+            };
+            page_url(&dir, &filename)
+        }
+        None => {
+            dir.extend(&segments);
+            page_url(&dir, &filename)
+        }
+    };
+
+    PageLocation {
+        dir,
+        filename,
+        url,
+        lang,
+        translation_key,
+    }
+}
+
+/// The path `posts.permalink` gives a post, relative to its language's
+/// prefix, or `None` if there's no pattern or it needs a date the post
+/// doesn't have. `segments` are the directories the post is in.
+fn expand_permalink(
+    pattern: &str,
+    frontmatter: &Frontmatter,
+    slug: &str,
+    segments: &[String],
+) -> Option<String> {
+    if pattern.is_empty() {
+        return None;
+    }
+    let date = frontmatter.date.as_deref().and_then(dates::parse);
+    let mut path = pattern.to_string();
+    for (placeholder, format) in [(":year", "%Y"), (":month", "%m"), (":day", "%d")] {
+        if path.contains(placeholder) {
+            path = path.replace(placeholder, &date?.format(format).to_string());
+        }
+    }
+    let title = frontmatter
+        .title
+        .as_deref()
+        .map(slugify)
+        .unwrap_or_default();
+    let section = segments.first().map(String::as_str).unwrap_or_default();
+    let path = path
+        .replace(":slug", slug)
+        .replace(":title", if title.is_empty() { slug } else { &title })
+        .replace(":section", section);
+
+    // Placeholders that came out empty, like the section of a top-level
+    // post, don't leave empty directories behind
+    let trailing_slash = path.ends_with('/');
+    let mut path = path
+        .split('/')
+        .filter(|segment| !segment.is_empty())
+        .collect::<Vec<_>>()
+        .join("/");
+    if path.is_empty() {
+        return None;
+    }
+    if trailing_slash {
+        path.push('/');
+    }
+    Some(path)
+}
+
+/// Whether a content file is an index page, which is never a listed post,
+/// by its `slug` or file name.
+fn is_index_page(md_file: &Path, frontmatter: &Frontmatter) -> bool {
+    frontmatter
+        .slug
+        .as_deref()
+        .or_else(|| md_file.file_stem().and_then(|s| s.to_str()))
+        .is_some_and(|name| name.contains("index"))
+}
+
+/// The path of the page at `url` without its `.html`, with `index` for URLs
+/// ending in a directory: `/a/b/` gives `/a/b/index`. Files published next
+/// to a page, like its plain text version, are named after it.
+fn url_stem(url: &str) -> String {
+    if url.ends_with('/') {
+        format!("{}index", url)
+    } else {
+        url.strip_suffix(".html").unwrap_or(url).to_string()
+    }
+}
+
+/// The one of `roots` that `path` is in, the deepest if they're nested, or
+/// else the first.
+fn content_root<'a>(path: &Path, roots: &'a [PathBuf]) -> &'a Path {
+    roots
+        .iter()
+        .filter(|root| path.starts_with(root))
+        .max_by_key(|root| root.components().count())
+        .or(roots.first())
+        .map_or(Path::new(""), PathBuf::as_path)
+}
+
+/// The directory of a content file relative to the content root.
+fn relative_dir<'a>(input_path: &'a Path, content_dir: &Path) -> &'a Path {
+    input_path
+        .parent()
+        .and_then(|p| p.strip_prefix(content_dir).ok())
+        .unwrap_or(Path::new(""))
+}
+
+fn page_url(relative_path: &Path, output_filename: &str) -> String {
+    if relative_path.as_os_str().is_empty() {
+        format!("/{}", output_filename)
+    } else {
+        format!("/{}/{}", relative_path.display(), output_filename)
+    }
+}
+
+/// Reads a content file, splitting it into its frontmatter and markdown (or
+/// whatever else the body is written in), and lets plugins adjust both.
+fn read_page(path: &Path, plugins: &Plugins) -> io::Result<(Frontmatter, String)> {
+    let mut content = fs::read_to_string(path)?;
+    if InputFormat::of(path) == Some(InputFormat::Notebook) {
+        content = notebook::to_markdown(&content).map_err(|e| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{}: {}", path.display(), e),
+            )
+        })?;
+    }
+    let (mut frontmatter, markdown) = Frontmatter::parse(&content);
+    match InputFormat::of(path) {
+        Some(InputFormat::AsciiDoc) => formats::read_asciidoc_header(markdown, &mut frontmatter),
+        Some(InputFormat::Org) => formats::read_org_keywords(markdown, &mut frontmatter),
+        _ => {}
+    }
+    if frontmatter.date.is_none() {
+        frontmatter.date = frontmatter.publish_date.clone();
+    }
+    let mut markdown = markdown.to_string();
+    plugins.after_frontmatter(path, &mut frontmatter, &mut markdown)?;
+    Ok((frontmatter, markdown))
+}
+
+/// The body of a page as it's rendered: its shortcodes expanded and, with
+/// `strip_title_heading`, a markdown H1 repeating its title taken out.
+fn page_body(
+    md_file: &Path,
+    frontmatter: &Frontmatter,
+    markdown: &str,
+    site: &Site,
+) -> io::Result<String> {
+    let body = expand_shortcodes(markdown, md_file, site)?;
+    let config = frontmatter.markdown_config(&site.config.markdown);
+    match &frontmatter.title {
+        Some(title)
+            if config.strip_title_heading
+                && InputFormat::of(md_file).is_none_or(InputFormat::is_markdown) =>
+        {
+            Ok(markdown::without_title_heading(&body, title, &config).to_string())
+        }
+        _ => Ok(body),
+    }
+}
+
+fn process_markdown_file(input_path: &Path, site: &Site) -> io::Result<BuiltPage> {
+    let (frontmatter, markdown_content) = read_page(input_path, site.plugins)?;
+    let markdown_content = &page_body(input_path, &frontmatter, &markdown_content, site)?;
+
+    let location = page_location(
+        input_path,
+        &frontmatter,
+        site.content_dir(input_path),
+        site.config,
+    );
+    let url = location.url;
+
+    let markdown_config = frontmatter.markdown_config(&site.config.markdown);
+    let mut rendered_markdown =
+        site.render_source(input_path, markdown_content, &markdown_config, &url)?;
+    site.plugins
+        .after_markdown(input_path, &mut rendered_markdown.html)?;
+
+    // Pages not written in markdown are counted and indexed by their HTML;
+    // protected pages aren't indexed at all
+    let is_markdown = InputFormat::of(input_path).is_none_or(InputFormat::is_markdown);
+    let text = if frontmatter.protected {
+        String::new()
+    } else if is_markdown {
+        plain_text_body(markdown_content, &markdown_config)
+    } else {
+        formats::html_text(&rendered_markdown.html)
+    };
+    let words = if is_markdown {
+        word_count(markdown_content)
+    } else {
+        formats::html_text(&rendered_markdown.html)
+            .split_whitespace()
+            .count()
+    };
+
+    let copyable_code = rendered_markdown.has_copyable_code;
+    if frontmatter.protected {
+        rendered_markdown.html =
+            protect_content(input_path, &frontmatter, &rendered_markdown.html, site)?;
+    }
+
+    let output_subdir = site.output_dir.join(&location.dir);
+    if !output_subdir.exists() {
+        fs::create_dir_all(&output_subdir)?;
+    }
+
+    let output_path = output_subdir.join(&location.filename);
+
+    if frontmatter.title.is_none() {
+        warn(site.config, format!("{}: no title", input_path.display()))?;
+    }
+    for key in frontmatter.extra.keys() {
+        if let Some(known) = misspelled_key(key) {
+            warn(
+                site.config,
+                format!(
+                    "{}: unknown frontmatter key '{}', did you mean '{}'?",
+                    input_path.display(),
+                    key,
+                    known
+                ),
+            )?;
+        }
+    }
+    let title = frontmatter.title.unwrap_or_else(|| "Untitled".to_string());
+    let date = frontmatter.date.unwrap_or_default();
+    if !date.is_empty() && dates::parse(&date).is_none() {
+        warn(
+            site.config,
+            format!(
+                "{}: can't read date '{}', expected YYYY-MM-DD with an optional time",
+                input_path.display(),
+                date
+            ),
+        )?;
+    }
+    let tags = frontmatter.tags.unwrap_or_default();
+
+    let tags_html = tags
+        .iter()
+        .map(|tag| tag_span(tag, "tag p-category"))
+        .collect::<Vec<_>>()
+        .join("");
+
+    let toc = if frontmatter.toc.unwrap_or(true) && !frontmatter.protected {
+        toc_to_html(&rendered_markdown.toc)
+    } else {
+        String::new()
+    };
+
+    // Posts are sorted newest first, so the previous (older) post comes after
+    let posts = site.posts_for(&location.lang);
+    let position = posts.iter().position(|post| post.url == url);
+    let prev = position.and_then(|i| posts.get(i + 1));
+    let next = position.and_then(|i| i.checked_sub(1)).map(|i| &posts[i]);
+    if position.is_some() && date.is_empty() {
+        warn(
+            site.config,
+            format!("{}: post has no date", input_path.display()),
+        )?;
+    }
+
+    let source_path = relative_path(input_path, site.content_dir(input_path));
+    let history = site.history.get(&source_path);
+
+    let mut context = PageContext {
+        title: title.clone(),
+        content: rendered_markdown.html,
+        math: rendered_markdown.has_math,
+        mermaid: rendered_markdown.has_mermaid,
+        tags: tags_html,
+        keywords: tags.join(", "),
+        date: date.clone(),
+        toc,
+        word_count: words,
+        reading_time: reading_time(words),
+        prev_url: prev.map(|p| p.url.clone()).unwrap_or_default(),
+        prev_title: prev.map(|p| p.title.clone()).unwrap_or_default(),
+        next_url: next.map(|p| p.url.clone()).unwrap_or_default(),
+        next_title: next.map(|p| p.title.clone()).unwrap_or_default(),
+        related_posts: related_posts(&url, &tags, posts, site.config.posts.related),
+        backlinks: backlinks(&url, &site.link_graph),
+        assets: site.assets.clone(),
+        base_path: site.link_prefix(),
+        data: site.data.clone(),
+        menus: site.menus_for(&location.lang, &url),
+        description: frontmatter.description.clone().unwrap_or_else(|| {
+            let summary = if frontmatter.protected {
+                String::new()
+            } else if is_markdown {
+                plain_text_body(summary_source(markdown_content), &markdown_config)
+            } else {
+                text.clone()
+            };
+            truncate_words(&summary, DESCRIPTION_LENGTH)
+        }),
+        canonical_url: site.config.absolute_url(&url),
+        last_modified: history.map(|h| h.last_modified.clone()).unwrap_or_default(),
+        git_authors: history.map(|h| h.authors.clone()).unwrap_or_default(),
+        edit_url: site
+            .config
+            .repository
+            .edit_url(&source_path)
+            .unwrap_or_default(),
+        image: match frontmatter.image.as_deref() {
+            Some(image) => absolute_image_url(image, site.config),
+            None if site.config.social_cards.enabled && position.is_some() => {
+                let card = social_card_path(&url);
+                social::write_card(
+                    &title,
+                    &site.config.title,
+                    &site.config.social_cards,
+                    &site.output_dir.join(&card),
+                )?;
+                site.config.absolute_url(&card)
+            }
+            None if !site.config.image.is_empty() => {
+                absolute_image_url(&site.config.image, site.config)
+            }
+            None => String::new(),
+        },
+        site_name: site.config.title.clone(),
+        translations: site
+            .translations
+            .get(&location.translation_key)
+            .into_iter()
+            .flatten()
+            .filter(|translation| translation.url != url)
+            .cloned()
+            .collect(),
+        language_prefix: site.config.language_prefix(&location.lang),
+        authors: authors::resolve(
+            &frontmatter.authors,
+            site.config,
+            &site.config.language_prefix(&location.lang),
+        ),
+        posts_url: site.posts_url(&location.lang),
+        lang: location.lang,
+        noindex: frontmatter.unlisted,
+        extra: frontmatter.extra.clone(),
+        ..Default::default()
+    };
+    let page_path = url_stem(&url);
+    let mut alternates = Vec::new();
+    if site.config.alternates.text && !frontmatter.protected {
+        alternates.push(("text/plain", format!("{}.txt", page_path)));
+    }
+    if site.config.alternates.markdown
+        && !frontmatter.protected
+        && InputFormat::of(input_path) == Some(InputFormat::Markdown)
+    {
+        alternates.push(("text/markdown", format!("{}.md", page_path)));
+    }
+    context.alternates = alternates
+        .iter()
+        .map(|(media_type, url)| Alternate {
+            media_type: media_type.to_string(),
+            url: site.config.absolute_url(url),
+        })
+        .collect();
+    context.meta_tags = meta_tags(&context, site.config.default_language());
+    context.raw = RawText::of(&context);
+
+    if let Some(series) = frontmatter.series {
+        let parts = series_posts(&series, posts);
+        context.series_parts = parts
+            .iter()
+            .enumerate()
+            .map(|(i, post)| SeriesPart {
+                title: post.title.clone(),
+                url: post.url.clone(),
+                part: i + 1,
+                current: post.url == url,
+            })
+            .collect();
+        context.series_position = context
+            .series_parts
+            .iter()
+            .position(|part| part.current)
+            .map_or(0, |i| i + 1);
+        if site.config.posts.series_pages {
+            context.series_url = series_url(&series, &context.language_prefix);
+        }
+        context.series = series;
+    }
+
+    // `content/404.md` gets the dedicated template, if there is one
+    let template = if site.has_not_found_template
+        && is_not_found_page(input_path, site.content_dir(input_path))
+    {
+        "404".to_string()
+    } else {
+        site.page_template(relative_dir(input_path, site.content_dir(input_path)))
+    };
+    let mut rendered = site.render(&template, &context)?;
+    add_page_scripts(&mut rendered, copyable_code, frontmatter.protected, site);
+
+    write_html(&output_path, &rendered, site)?;
+    println!(
+        "  ✓ {} -> {}",
+        input_path.display(),
+        staging::shown(&output_path)
+    );
+
+    for (media_type, _) in &alternates {
+        let (extension, body) = if *media_type == "text/markdown" {
+            ("md", fs::read_to_string(input_path)?)
+        } else if is_markdown {
+            let document = markdown::plain_text_document(
+                markdown_content,
+                &markdown_config,
+                &site.render_context(&url),
+            );
+            ("txt", format!("{}\n\n{}", title, document))
+        } else {
+            ("txt", format!("{}\n\n{}\n", title, text))
+        };
+        fs::write(output_path.with_extension(extension), body)?;
+    }
+
+    for alias in &frontmatter.aliases {
+        write_redirect(alias, &url, site)?;
+    }
+
+    if site.config.gemini.enabled {
+        let body = if frontmatter.protected {
+            format!(
+                "This post is password protected.\n\n=> {} Read it on the web\n",
+                site.config.absolute_url(&url)
+            )
+        } else if is_markdown {
+            gemini::from_markdown(
+                markdown_content,
+                &markdown_config,
+                &site.render_context(&url),
+                site.config,
+            )
+        } else {
+            text.clone()
+        };
+        let path = gemini::page_path(&url, site.config, site.output_dir);
+        gemini::write_page(&title, &date, &body, &path)?;
+    }
+
+    Ok(BuiltPage {
+        title,
+        url,
+        date,
+        last_modified: context.last_modified,
+        tags,
+        text,
+        word_count: words,
+        aliases: frontmatter.aliases,
+        unlisted: frontmatter.unlisted,
+    })
+}
+
+/// A protected page's content encrypted with its password, or the site's,
+/// behind a form to unlock it.
+fn protect_content(
+    input_path: &Path,
+    frontmatter: &Frontmatter,
+    html: &str,
+    site: &Site,
+) -> io::Result<String> {
+    let password = frontmatter
+        .password
+        .as_deref()
+        .unwrap_or(&site.config.protected.password);
+    if password.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "{}: protected, but there's no password for it or in protected.password",
+                input_path.display()
+            ),
+        ));
+    }
+    protect::protect(html, password, site.config.protected.iterations)
+        .map_err(|e| io::Error::new(e.kind(), format!("{}: {}", input_path.display(), e)))
+}
+
+/// Writes the `CNAME` file GitHub Pages reads the custom domain from, and an
+/// empty `.nojekyll` so it doesn't run the output through Jekyll.
+fn write_github_pages_files(domain: &str, output_dir: &Path) -> io::Result<()> {
+    fs::write(output_dir.join("CNAME"), format!("{}\n", domain.trim()))?;
+    fs::write(output_dir.join(".nojekyll"), "")?;
+    println!("  🌐 Generated CNAME for {} and .nojekyll", domain.trim());
+    Ok(())
+}
+
+/// Whether `md_file` is `content/404.md`, the page served for missing URLs.
+/// It renders like any other page but is kept out of listings, feeds and
+/// the search index.
+fn is_not_found_page(md_file: &Path, content_dir: &Path) -> bool {
+    md_file == content_dir.join("404.md")
+}
+
+/// Renders `templates/404.html` on its own, for sites without a
+/// `content/404.md`.
+fn generate_not_found_page(site: &Site) -> io::Result<()> {
+    let mut context = PageContext {
+        title: "Page not found".to_string(),
+        canonical_url: site.config.absolute_url("404.html"),
+        site_name: site.config.title.clone(),
+        assets: site.assets.clone(),
+        base_path: site.link_prefix(),
+        data: site.data.clone(),
+        menus: site.menus_for(site.config.default_language(), "/404.html"),
+        posts_url: site.posts_url(site.config.default_language()),
+        ..Default::default()
+    };
+    context.raw = RawText::of(&context);
+    let rendered = site.render("404", &context)?;
+    write_html(&site.output_dir.join("404.html"), &rendered, site)?;
+    println!("  🚫 Generated 404.html");
+    Ok(())
+}
+
+/// Renders the top-level templates that aren't layouts, like
+/// `templates/now.html`, as pages of their own at the same path, with the
+/// posts, tags and data of the default language, see `SiteContext`. Templates whose page is
+/// already written from markdown are skipped. Returns the URLs written.
+fn generate_template_pages(site: &Site, pages: &[BuiltPage]) -> io::Result<Vec<String>> {
+    let lang = site.config.default_language();
+    let posts = site.posts_for(lang);
+    let mut urls = Vec::new();
+
+    for name in site.template_sources.keys() {
+        if name.contains('/')
+            || ["base", "404", "tag"].contains(&name.as_str())
+            || *name == site.config.posts.listing_template
+        {
+            continue;
+        }
+        let url = format!("/{}.html", name);
+        if pages.iter().any(|page| page.url == url) {
+            warn(
+                site.config,
+                format!(
+                    "{}: a page already renders to {}",
+                    site.template_path(name).display(),
+                    url
+                ),
+            )?;
+            continue;
+        }
+
+        let tags = collect_tags(posts);
+        let context = SiteContext {
+            title: site.config.title.clone(),
+            site_name: site.config.title.clone(),
+            canonical_url: site.config.absolute_url(&url),
+            lang: lang.to_string(),
+            language_prefix: site.config.language_prefix(lang),
+            posts_url: site.posts_url(lang),
+            base_path: site.link_prefix(),
+            posts,
+            recent: &posts[..site.config.posts.recent.min(posts.len())],
+            sorted: [
+                ("date", PostSort::Date),
+                ("title", PostSort::Title),
+                ("weight", PostSort::Weight),
+            ]
+            .into_iter()
+            .map(|(name, sort)| {
+                let mut sorted: Vec<&PostMetadata> = posts.iter().collect();
+                sort_posts(&mut sorted, sort);
+                (name, sorted)
+            })
+            .collect(),
+            years: group_by_year(posts),
+            tagged: tags
+                .iter()
+                .map(|tag| (tag.slug.clone(), tag.posts.clone()))
+                .collect(),
+            tags,
+            data: site.data.clone(),
+            menus: site.menus_for(lang, &url),
+            assets: site.assets.clone(),
+        };
+        let rendered = site.render(name, &context)?;
+        let output_path = site.output_dir.join(url.trim_start_matches('/'));
+        write_html(&output_path, &rendered, site)?;
+        println!("  🧱 Generated {}", staging::shown(&output_path));
+        urls.push(url);
+    }
+
+    Ok(urls)
+}
+
+/// Where an alias is written: `/old/` becomes `old/index.html`, and a path
+/// with no extension is treated as a directory too.
+fn alias_output_path(alias: &str) -> PathBuf {
+    let alias = alias.trim_start_matches('/');
+    let is_file = !alias.ends_with('/')
+        && alias
+            .rsplit('/')
+            .next()
+            .is_some_and(|name| name.contains('.'));
+    if is_file {
+        PathBuf::from(alias)
+    } else {
+        Path::new(alias).join("index.html")
+    }
+}
+
+/// Writes a stub page at `alias` sending visitors (and search engines, via
+/// the canonical link) on to `url`.
+fn write_redirect(alias: &str, url: &str, site: &Site) -> io::Result<()> {
+    let dest = alias_output_path(alias);
+    let target = if site.config.relative_urls {
+        relative_urls::relative_link(
+            &format!("{}{}", site.base_path, url.trim_start_matches('/')),
+            &dest.to_string_lossy().replace('\\', "/"),
+            &site.base_path,
+        )
+    } else {
+        site.config.absolute_url(url)
+    };
+    let target = escape_html(&target);
+    let html = format!(
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n    <meta charset=\"utf-8\">\n    \
+         <title>Redirecting…</title>\n    <link rel=\"canonical\" href=\"{0}\">\n    \
+         <meta http-equiv=\"refresh\" content=\"0; url={0}\">\n</head>\n<body>\n    \
+         <p>This page has moved to <a href=\"{0}\">{0}</a>.</p>\n</body>\n</html>\n",
+        target
+    );
+
+    let dest = site.output_dir.join(dest);
+    if let Some(parent) = dest.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(&dest, html)?;
+    println!("    ↪ {} -> {}", alias, url);
+    Ok(())
+}
+
+/// Writes a Netlify `_redirects` file with a permanent redirect for every
+/// page alias.
+fn write_netlify_redirects(
+    pages: &[BuiltPage],
+    config: &Config,
+    output_dir: &Path,
+) -> io::Result<()> {
+    let base_path = config.base_path();
+    let mut redirects = String::new();
+    for page in pages {
+        for alias in &page.aliases {
+            redirects.push_str(&format!(
+                "{}{} {}{} 301\n",
+                base_path,
+                alias.trim_start_matches('/'),
+                base_path,
+                page.url.trim_start_matches('/')
+            ));
+        }
+    }
+    fs::write(output_dir.join("_redirects"), redirects)?;
+    println!("  ↪ Generated _redirects");
+    Ok(())
+}
+
+/// Longest generated description, which is about what search engines show.
+const DESCRIPTION_LENGTH: usize = 160;
+
+/// Shortens `text` to at most `max` characters, cutting between words and
+/// marking the cut with an ellipsis.
+fn truncate_words(text: &str, max: usize) -> String {
+    if text.chars().count() <= max {
+        return text.to_string();
+    }
+    let cut: String = text.chars().take(max - 1).collect();
+    let cut = match cut.rfind(' ') {
+        Some(space) => &cut[..space],
+        None => &cut,
+    };
+    format!(
+        "{}…",
+        cut.trim_end_matches(|c: char| c.is_ascii_punctuation())
+    )
+}
+
+/// Where a post's generated social card goes: `/posts/hello.html` gets
+/// `og/posts-hello.png`.
+fn social_card_path(url: &str) -> String {
+    let name = url_stem(url);
+    format!("og/{}.png", name.trim_start_matches('/').replace('/', "-"))
+}
+
+fn absolute_image_url(image: &str, config: &Config) -> String {
+    if image.contains("://") {
+        image.to_string()
+    } else {
+        config.absolute_url(image)
+    }
+}
+
+/// The `<head>` tags describing a page to search engines and to social sites
+/// building link previews, and to the page's translations.
+fn meta_tags(context: &PageContext, default_language: &str) -> String {
+    let mut tags = vec![
+        format!(
+            "<link rel=\"canonical\" href=\"{}\">",
+            escape_html(&context.canonical_url)
+        ),
+        format!(
+            "<meta name=\"description\" content=\"{}\">",
+            escape_html(&context.description)
+        ),
+    ];
+
+    let mut property = |name: &str, value: &str| {
+        if !value.is_empty() {
+            let attribute = if name.starts_with("og:") {
+                "property"
+            } else {
+                "name"
+            };
+            tags.push(format!(
+                "<meta {}=\"{}\" content=\"{}\">",
+                attribute,
+                name,
+                escape_html(value)
+            ));
+        }
+    };
+    let og_type = if context.date.is_empty() {
+        "website"
+    } else {
+        "article"
+    };
+    property("og:type", og_type);
+    property("og:title", &context.title);
+    property("og:description", &context.description);
+    property("og:url", &context.canonical_url);
+    property("og:site_name", &context.site_name);
+    property("og:image", &context.image);
+    let card = if context.image.is_empty() {
+        "summary"
+    } else {
+        "summary_large_image"
+    };
+    property("twitter:card", card);
+    property("twitter:title", &context.title);
+    property("twitter:description", &context.description);
+    property("twitter:image", &context.image);
+    if context.noindex {
+        tags.push("<meta name=\"robots\" content=\"noindex\">".to_string());
+    }
+
+    // Point search engines at every language version, this one included,
+    // with the default language's as the fallback for everyone else
+    if !context.translations.is_empty() {
+        let mut alternate = |lang: &str, url: &str| {
+            tags.push(format!(
+                "<link rel=\"alternate\" hreflang=\"{}\" href=\"{}\">",
+                escape_html(lang),
+                escape_html(url)
+            ));
+        };
+        alternate(&context.lang, &context.canonical_url);
+        for translation in &context.translations {
+            alternate(&translation.lang, &translation.absolute_url);
+        }
+        let default_url = if context.lang == default_language {
+            Some(context.canonical_url.as_str())
+        } else {
+            context
+                .translations
+                .iter()
+                .find(|translation| translation.lang == default_language)
+                .map(|translation| translation.absolute_url.as_str())
+        };
+        if let Some(url) = default_url {
+            alternate("x-default", url);
+        }
+    }
+
+    for alternate in &context.alternates {
+        tags.push(format!(
+            "<link rel=\"alternate\" type=\"{}\" href=\"{}\">",
+            alternate.media_type,
+            escape_html(&alternate.url)
+        ));
+    }
+
+    tags.join("\n    ")
+}
+
+/// Picks up to `limit` other posts sharing the most tags with this one,
+/// newest first among equally related posts.
+fn related_posts(
+    url: &str,
+    tags: &[String],
+    posts: &[PostMetadata],
+    limit: usize,
+) -> Vec<PostLink> {
+    let slugs: Vec<String> = tags.iter().map(|tag| slugify(tag)).collect();
+    let mut scored: Vec<(usize, &PostMetadata)> = posts
+        .iter()
+        .filter(|post| post.url != url)
+        .map(|post| {
+            let shared = post
+                .tags
+                .iter()
+                .filter(|tag| slugs.contains(&slugify(tag)))
+                .count();
+            (shared, post)
+        })
+        .filter(|(shared, _)| *shared > 0)
+        .collect();
+
+    // `posts` is already sorted newest first and the sort is stable
+    scored.sort_by_key(|(shared, _)| std::cmp::Reverse(*shared));
+
+    scored
+        .into_iter()
+        .take(limit)
+        .map(|(_, post)| PostLink {
+            title: post.title.clone(),
+            url: post.url.clone(),
+            date: post.date.clone(),
+        })
+        .collect()
+}
+
+/// Groups posts, newest first, by the year of their date.
+fn group_by_year(posts: &[PostMetadata]) -> Vec<YearPosts<'_>> {
+    let mut years: Vec<YearPosts> = Vec::new();
+    for post in posts {
+        let Some(year) = post.date.get(..4) else {
+            continue;
+        };
+        match years.last_mut() {
+            Some(last) if last.year == year => last.posts.push(post),
+            _ => years.push(YearPosts {
+                year: year.to_string(),
+                posts: vec![post],
+            }),
+        }
+    }
+    years
+}
+
+/// A tag as pages show it: its name, with its slug for scripts to match on,
+/// so `Machine Learning` and `machine learning` are the same tag.
+fn tag_span(tag: &str, class: &str) -> String {
+    format!(
+        "<span class=\"{}\" data-tag=\"{}\">#{}</span>",
+        class,
+        slugify(tag),
+        escape_html(tag.trim())
+    )
+}
+
+/// Groups posts by tag, ordered by tag slug.
+fn collect_tags(posts: &[PostMetadata]) -> Vec<TagPosts<'_>> {
+    let mut tags: BTreeMap<String, TagPosts> = BTreeMap::new();
+
+    for post in posts {
+        for tag in &post.tags {
+            let slug = slugify(tag);
+            let entry = tags.entry(slug.clone()).or_insert_with(|| TagPosts {
+                name: tag.trim().to_string(),
+                slug,
+                posts: Vec::new(),
+            });
+            // A post listing the same tag twice only counts once
+            if !entry.posts.iter().any(|p| p.url == post.url) {
+                entry.posts.push(post);
+            }
+        }
+    }
+
+    tags.into_values().collect()
+}
+
+fn backlinks(url: &str, graph: &LinkGraph) -> Vec<PostLink> {
+    graph
+        .backlinks(url)
+        .into_iter()
+        .map(|source| PostLink {
+            title: graph.pages[source].title.clone(),
+            url: source.to_string(),
+            date: graph.pages[source].date.clone(),
+        })
+        .collect()
+}
+
+/// All posts in a series, ordered by `series_part` and then by date.
+fn series_posts<'a>(series: &str, posts: &'a [PostMetadata]) -> Vec<&'a PostMetadata> {
+    let mut parts: Vec<&PostMetadata> = posts
+        .iter()
+        .filter(|post| post.series.as_deref() == Some(series))
+        .collect();
+    parts.sort_by(|a, b| {
+        let a_part = a.series_part.unwrap_or(usize::MAX);
+        let b_part = b.series_part.unwrap_or(usize::MAX);
+        a_part.cmp(&b_part).then_with(|| a.date.cmp(&b.date))
+    });
+    parts
+}
+
+fn series_url(series: &str, language_prefix: &str) -> String {
+    format!("{}/series/{}.html", language_prefix, slugify(series))
+}
+
+fn tag_url(slug: &str, language_prefix: &str) -> String {
+    format!("{}/tags/{}.html", language_prefix, slug)
+}
+
+/// Writes a page for every tag in `lang` with the `tag` template, listing
+/// its posts the way the posts listing does, and returns their URLs.
+fn generate_tag_pages(site: &Site, lang: &str) -> io::Result<Vec<String>> {
+    let language_prefix = site.config.language_prefix(lang);
+    let mut urls = Vec::new();
+
+    for tag in collect_tags(site.posts_for(lang)) {
+        let url = tag_url(&tag.slug, &language_prefix);
+        let mut listed = tag.posts;
+        sort_for_listing(&mut listed, site.config.posts.sort);
+
+        let context = TagContext {
+            posts: listed.iter().map(|post| post_item(site, post)).collect(),
+            feed_url: if site.config.feeds.enabled {
+                format!("{}/tags/{}/rss.xml", language_prefix, tag.slug)
+            } else {
+                String::new()
+            },
+            tag: tag.name,
+            slug: tag.slug,
+            language_prefix: language_prefix.clone(),
+            posts_url: site.posts_url(lang),
+            base_path: site.link_prefix(),
+            data: site.data.clone(),
+            menus: site.menus_for(lang, &url),
+            assets: site.assets.clone(),
+        };
+
+        let rendered = site.render("tag", &context)?;
+        let output_path = site.output_dir.join(url.trim_start_matches('/'));
+        if let Some(parent) = output_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        write_html(&output_path, &rendered, site)?;
+        urls.push(url);
+    }
+    if !urls.is_empty() {
+        println!("  🏷 Generated {} tag page(s)", urls.len());
+    }
+
+    Ok(urls)
+}
+
+/// Writes an index page for every series in `lang`, listing its parts in
+/// order, and returns their URLs.
+fn generate_series_pages(site: &Site, lang: &str) -> io::Result<Vec<String>> {
+    let posts = site.posts_for(lang);
+    let language_prefix = site.config.language_prefix(lang);
+    let mut names: Vec<&str> = posts.iter().filter_map(|p| p.series.as_deref()).collect();
+    names.sort();
+    names.dedup();
+
+    if names.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let series_dir = site
+        .output_dir
+        .join(language_prefix.trim_start_matches('/'))
+        .join("series");
+    fs::create_dir_all(&series_dir)?;
+
+    let mut urls = Vec::new();
+
+    for name in names {
+        let url = series_url(name, &language_prefix);
+        let items: String = series_posts(name, posts)
+            .iter()
+            .map(|post| {
+                format!(
+                    "<li><a href=\"{}{}\">{}</a></li>\n",
+                    site.base_path,
+                    post.url.trim_start_matches('/'),
+                    escape_html(&post.title)
+                )
+            })
+            .collect();
+
+        let mut context = PageContext {
+            title: name.to_string(),
+            content: format!(
+                "<h1>{}</h1>\n<ol class=\"series-list\">\n{}</ol>\n",
+                escape_html(name),
+                items
+            ),
+            series: name.to_string(),
+            assets: site.assets.clone(),
+            base_path: site.link_prefix(),
+            data: site.data.clone(),
+            menus: site.menus_for(lang, &url),
+            language_prefix: language_prefix.clone(),
+            posts_url: site.posts_url(lang),
+            ..Default::default()
+        };
+        context.raw = RawText::of(&context);
+
+        let rendered = site.render("base", &context)?;
+        let output_path = site.output_dir.join(url.trim_start_matches('/'));
+        write_html(&output_path, &rendered, site)?;
+        println!("  📚 Generated {}", staging::shown(&output_path));
+        urls.push(url);
+    }
+
+    Ok(urls)
+}
+
+/// Whether a content file goes in the posts listing and feeds: anything but
+/// index pages, the 404 page, unlisted pages and `kind: page`.
+fn is_listed_post(md_file: &Path, frontmatter: &Frontmatter, content_dir: &Path) -> bool {
+    !(is_index_page(md_file, frontmatter)
+        || frontmatter.unlisted
+        || is_not_found_page(md_file, content_dir)
+        || PageKind::of(md_file, frontmatter, content_dir) == PageKind::Page)
+}
+
+fn collect_post_metadata(md_file: &Path, site: &Site) -> io::Result<Option<PostMetadata>> {
+    let (frontmatter, markdown_content) = read_page(md_file, site.plugins)?;
+    let markdown_content = &page_body(md_file, &frontmatter, &markdown_content, site)?;
+
+    let location = page_location(
+        md_file,
+        &frontmatter,
+        site.content_dir(md_file),
+        site.config,
+    );
+    if !is_listed_post(md_file, &frontmatter, site.content_dir(md_file)) {
+        return Ok(None);
+    }
+
+    let url = location.url;
+
+    // Summaries and feed content are shown out of context, so skip the
+    // permalinks
+    let mut summary_config = frontmatter.markdown_config(&site.config.markdown);
+    summary_config.heading_anchors = false;
+    let (words, summary) = if InputFormat::of(md_file).is_none_or(InputFormat::is_markdown) {
+        let summary_markdown = frontmatter
+            .summary
+            .as_deref()
+            .unwrap_or_else(|| summary_source(markdown_content));
+        let summary = markdown_to_html(
+            summary_markdown,
+            &summary_config,
+            &site.render_context(&url),
+        );
+        (word_count(markdown_content), summary.html)
+    } else {
+        let html = site
+            .render_source(md_file, markdown_content, &summary_config, &url)?
+            .html;
+        let summary = match frontmatter.summary.as_deref() {
+            Some(summary) => {
+                markdown_to_html(summary, &summary_config, &site.render_context(&url)).html
+            }
+            None => formats::first_paragraph(&html).to_string(),
+        };
+        let words = formats::html_text(&html).split_whitespace().count();
+        (words, summary)
+    };
+
+    // Only what the author wrote for it shows of a protected post
+    let summary = match frontmatter.summary.as_deref() {
+        _ if !frontmatter.protected => summary,
+        Some(summary) => {
+            markdown_to_html(summary, &summary_config, &site.render_context(&url)).html
+        }
+        None => String::new(),
+    };
+    let content = if site.config.feeds.content == FeedContent::Full && !frontmatter.protected {
+        site.render_source(md_file, markdown_content, &summary_config, &url)?
+            .html
+    } else {
+        String::new()
+    };
+
+    Ok(Some(PostMetadata {
+        title: frontmatter.title.unwrap_or_else(|| "Untitled".to_string()),
+        date: frontmatter.date.unwrap_or_default(),
+        tags: frontmatter.tags.unwrap_or_default(),
+        url,
+        word_count: words,
+        reading_time: reading_time(words),
+        summary,
+        series: frontmatter.series,
+        series_part: frontmatter.series_part,
+        lang: location.lang,
+        authors: frontmatter.authors,
+        weight: frontmatter.weight.unwrap_or(0),
+        pinned: frontmatter.pinned,
+        extra: frontmatter.extra,
+        content,
+    }))
+}
+
+/// Maps every page's title, file name and content-relative path (without
+/// extension) to its URL, for resolving wikilinks. Where names collide the
+/// first page found wins.
+fn collect_wikilink_targets(
+    md_files: &[PathBuf],
+    content_dirs: &[PathBuf],
+    config: &Config,
+    plugins: &Plugins,
+) -> io::Result<BTreeMap<String, String>> {
+    let mut targets = BTreeMap::new();
+
+    for md_file in md_files {
+        let (frontmatter, _) = read_page(md_file, plugins)?;
+        let content_dir = content_root(md_file, content_dirs);
+        let url = page_location(md_file, &frontmatter, content_dir, config).url;
+
+        let path = relative_path(&md_file.with_extension(""), content_dir);
+        let path = path.as_str();
+        let stem = path.rsplit('/').next().unwrap_or(path);
+
+        for name in frontmatter.title.as_deref().into_iter().chain([stem, path]) {
+            targets
+                .entry(wikilink_key(name))
+                .or_insert_with(|| url.clone());
+        }
+    }
+
+    Ok(targets)
+}
+
+/// Groups the pages of a multilingual site by translation key, listing each
+/// key's versions in the order of the configured languages. Empty unless the
+/// site has more than one language.
+fn collect_translations(
+    md_files: &[PathBuf],
+    site: &Site,
+) -> io::Result<BTreeMap<String, Vec<Translation>>> {
+    let mut translations: BTreeMap<String, Vec<Translation>> = BTreeMap::new();
+    if site.config.languages.len() < 2 {
+        return Ok(translations);
+    }
+
+    for md_file in md_files {
+        let (frontmatter, _) = read_page(md_file, site.plugins)?;
+        let location = page_location(
+            md_file,
+            &frontmatter,
+            site.content_dir(md_file),
+            site.config,
+        );
+        translations
+            .entry(location.translation_key)
+            .or_default()
+            .push(Translation {
+                absolute_url: site.config.absolute_url(&location.url),
+                lang: location.lang,
+                url: location.url,
+            });
+    }
+
+    let order = |lang: &str| site.config.languages.iter().position(|l| l == lang);
+    for versions in translations.values_mut() {
+        versions.sort_by_key(|translation| order(&translation.lang));
+    }
+
+    Ok(translations)
+}
+
+/// Renders every page ahead of time to find the links between
+/// pages, so each page can list the others linking to it.
+fn collect_link_graph(md_files: &[PathBuf], site: &Site) -> io::Result<LinkGraph> {
+    let mut graph = LinkGraph::default();
+    let mut pages = Vec::new();
+
+    for md_file in md_files {
+        let (frontmatter, markdown_content) = read_page(md_file, site.plugins)?;
+        let url = page_location(
+            md_file,
+            &frontmatter,
+            site.content_dir(md_file),
+            site.config,
+        )
+        .url;
+        graph.add_page(
+            &url,
+            frontmatter.title.as_deref().unwrap_or("Untitled"),
+            frontmatter.date.as_deref().unwrap_or_default(),
+        );
+
+        // A page that fails to render here fails again, and is reported, when
+        // it's rendered for real
+        let markdown_config = frontmatter.markdown_config(&site.config.markdown);
+        let Ok(rendered) = page_body(md_file, &frontmatter, &markdown_content, site)
+            .and_then(|markdown| site.render_source(md_file, &markdown, &markdown_config, &url))
+        else {
+            continue;
+        };
+        pages.push((url, rendered.links));
+    }
+
+    for (url, links) in &pages {
+        graph.add_links(url, links.iter().map(String::as_str), &site.base_path);
+    }
+
+    Ok(graph)
+}
+
+/// Expands the shortcodes in a page's markdown with the site's shortcode
+/// templates.
+fn expand_shortcodes(markdown: &str, path: &Path, site: &Site) -> io::Result<String> {
+    shortcodes::expand(markdown, &|call| {
+        let template = format!("shortcodes/{}", call.name);
+        if !site.shortcodes.contains_key(&template) {
+            return None;
+        }
+        Some(site.render(&template, call))
+    })
+    .map_err(|e| io::Error::new(e.kind(), format!("{}: {}", path.display(), e)))
+}
+
+/// Orders posts for the posts listing as the config asks, pinned posts
+/// first.
+fn sort_for_listing(posts: &mut [&PostMetadata], sort: PostSort) {
+    sort_posts(posts, sort);
+    posts.sort_by_key(|post| !post.pinned);
+}
+
+fn sort_posts(posts: &mut [&PostMetadata], sort: PostSort) {
+    match sort {
+        PostSort::Date => posts.sort_by(|a, b| b.date.cmp(&a.date)),
+        PostSort::Title => posts.sort_by_key(|post| post.title.to_lowercase()),
+        PostSort::Weight => posts.sort_by(|a, b| a.weight.cmp(&b.weight).then(b.date.cmp(&a.date))),
+    }
+}
+
+/// A post's entry in the posts listing and on tag pages.
+fn post_item(site: &Site, post: &PostMetadata) -> String {
+    let tags_html = post
+        .tags
+        .iter()
+        .map(|tag| tag_span(tag, "tag clickable-tag p-category"))
+        .collect::<Vec<_>>()
+        .join("");
+
+    let summary_html = if post.summary.is_empty() {
+        String::new()
+    } else {
+        format!(
+            "<div class=\"post-summary p-summary\">{}</div>",
+            post.summary.trim_end()
+        )
+    };
+
+    format!(
+        r#"          <div class="post-item h-entry">
+            <h2>{}<a class="u-url p-name" href="{}{}">{}</a></h2>
+            <div class="post-meta">
+              <time class="post-date dt-published" datetime="{}">{}</time>
+              <span class="post-reading-time" title="{} words">{} min read</span>
+              <span class="post-tags">{}</span>
+            </div>
+            {}
+          </div>
+"#,
+        if post.pinned {
+            "<span class=\"pinned\" title=\"Pinned\">📌</span> "
+        } else {
+            ""
+        },
+        site.base_path,
+        post.url.trim_start_matches('/'),
+        escape_html(&post.title),
+        escape_html(&post.date),
+        dates::format(&post.date, &site.config.dates.format),
+        post.word_count,
+        post.reading_time,
+        tags_html,
+        summary_html
+    )
+}
+
+/// Writes the posts listing for `lang` to `posts.listing_path`, under its
+/// language prefix.
+fn generate_posts_page(site: &Site, lang: &str) -> io::Result<()> {
+    let posts = site.posts_for(lang);
+    let language_prefix = site.config.language_prefix(lang);
+    let mut listed: Vec<&PostMetadata> = posts.iter().collect();
+    sort_for_listing(&mut listed, site.config.posts.sort);
+    let post_html: String = listed.iter().map(|post| post_item(site, post)).collect();
+
+    let tag_options: String = collect_tags(posts)
+        .iter()
+        .map(|tag| {
+            format!(
+                r#"        <option value="{}">#{}</option>"#,
+                tag.slug,
+                escape_html(&tag.name)
+            )
+        })
+        .collect();
+
+    let posts_url = site.posts_url(lang);
+    let posts_context = PostsContext {
+        language_prefix: language_prefix.clone(),
+        posts_url: posts_url.clone(),
+        post_count: posts.len(),
+        posts: post_html,
+        tag_options,
+        assets: site.assets.clone(),
+        base_path: site.link_prefix(),
+        data: site.data.clone(),
+        menus: site.menus_for(lang, &posts_url),
+    };
+
+    let rendered = site.render(&site.config.posts.listing_template, &posts_context)?;
+
+    let output_path = site
+        .output_dir
+        .join(language_prefix.trim_start_matches('/'))
+        .join(site.config.posts.listing_path.trim_start_matches('/'));
+    if let Some(parent) = output_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    write_html(&output_path, &rendered, site)?;
+    println!("  📝 Generated {}", staging::shown(&output_path));
+
+    Ok(())
+}
+
+/// Lets the build go on without an optional part of the output, like the
+/// feeds, when writing it fails, warning that it's `skipped`. Returns whether
+/// it was written.
+fn skip_on_error(config: &Config, skipped: &str, result: io::Result<()>) -> io::Result<bool> {
+    match result {
+        Ok(()) => Ok(true),
+        Err(e) => {
+            warn(config, format!("Skipped {}: {}", skipped, e))?;
+            Ok(false)
+        }
+    }
+}
+
+/// Reports a problem with the site that the build can get past, or fails the
+/// build with it in strict mode.
+fn warn(config: &Config, message: String) -> io::Result<()> {
+    if config.strict {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, message));
+    }
+    println!("  ⚠ {}", message);
+    Ok(())
+}
+
+/// Where the script adding copy buttons to code blocks is published
+const COPY_CODE_SCRIPT: &str = "copy-code.js";
+
+const COPY_CODE_JS: &str = include_str!("copy_code.js");
+
+/// Loads the scripts `html`, a rendered page, needs for what its content
+/// has: copy buttons on its code blocks when `copyable_code`, and the
+/// password prompt when it's `protected`.
+fn add_page_scripts(html: &mut String, copyable_code: bool, protected: bool, site: &Site) {
+    if copyable_code {
+        let script = format!(
+            "<script src=\"{}{}\" defer></script>",
+            site.base_path, COPY_CODE_SCRIPT
+        );
+        insert_before(html, "</head>", &script);
+        site.has_copyable_code.set(true);
+    }
+    if protected {
+        let script = format!(
+            "<script src=\"{}{}\" defer></script>",
+            site.base_path,
+            protect::DECRYPT_SCRIPT
+        );
+        insert_before(html, "</head>", &script);
+        site.has_protected.set(true);
+    }
+}
+
+/// Writes a rendered HTML page, after plugins have seen it, minifying it
+/// first if the site asks for it.
+fn write_html(path: &Path, html: &str, site: &Site) -> io::Result<()> {
+    let mut html = html.to_string();
+    site.plugins.before_write(path, &mut html)?;
+    let snippets = &site.config.snippets;
+    if snippets.profiles.contains(&site.config.profile) {
+        insert_before(&mut html, "</head>", &snippets.head);
+        insert_before(&mut html, "</body>", &snippets.body);
+    }
+    insert_before(&mut html, "</head>", &indieweb_links(&site.config.indieweb));
+    if site.config.lint_html {
+        lint_html(path, &html, site)?;
+    }
+    if site.config.external_links.enabled {
+        html = external_links::decorate(&html, site.config);
+    }
+    if site.config.relative_urls {
+        let page = relative_path(path, site.output_dir);
+        html = relative_urls::rewrite(&html, &page, &site.base_path);
+    }
+    if site.config.obfuscate_emails {
+        html = emails::obfuscate(&html);
+    }
+    if site.config.minify {
+        html = minify::minify_html(&html);
+    }
+    site.bytes_written
+        .set(site.bytes_written.get() + html.len() as u64);
+    fs::write(path, html)
+}
+
+/// The `<link>`s telling other sites where to send webmentions and
+/// pingbacks for the page.
+fn indieweb_links(indieweb: &IndieWebConfig) -> String {
+    [
+        ("webmention", &indieweb.webmention),
+        ("pingback", &indieweb.pingback),
+    ]
+    .iter()
+    .filter(|(_, endpoint)| !endpoint.is_empty())
+    .map(|(rel, endpoint)| format!("<link rel=\"{}\" href=\"{}\">", rel, escape_html(endpoint)))
+    .collect::<Vec<_>>()
+    .join("\n")
+}
+
+/// Warns about the problems `html_lint` finds in the page at `path`, all in
+/// one warning.
+fn lint_html(path: &Path, html: &str, site: &Site) -> io::Result<()> {
+    let problems = html_lint::problems(html);
+    if problems.is_empty() {
+        return Ok(());
+    }
+    let mut message = format!(
+        "{}: {} HTML problem(s)",
+        relative_path(path, site.output_dir),
+        problems.len()
+    );
+    for (line, problem) in problems {
+        message.push_str(&format!("\n      line {}: {}", line, problem));
+    }
+    warn(site.config, message)
+}
+
+/// Puts `snippet` on a line of its own before the last `tag` in `html`, if
+/// there is one.
+fn insert_before(html: &mut String, tag: &str, snippet: &str) {
+    if snippet.is_empty() {
+        return;
+    }
+    if let Some(pos) = html.rfind(tag) {
+        html.insert_str(pos, &format!("{}\n", snippet.trim()));
+    }
+}
+
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    tinytemplate::escape(text, &mut escaped);
+    escaped
+}
+
+/// Serializes plain text for templates escaped for HTML, see `PageContext`.
+fn escaped<S: serde::Serializer>(text: &str, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&escape_html(text))
+}
+
+fn escaped_list<S: serde::Serializer>(items: &[String], serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_seq(items.iter().map(|item| escape_html(item)))
+}
+
+fn escaped_option<S: serde::Serializer>(
+    text: &Option<String>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    match text {
+        Some(text) => serializer.serialize_some(&escape_html(text)),
+        None => serializer.serialize_none(),
+    }
+}
+
+/// `path` relative to `base`, `/`-separated.
+fn relative_path(path: &Path, base: &Path) -> String {
+    path.strip_prefix(base)
+        .unwrap_or(path)
+        .to_string_lossy()
+        .replace('\\', "/")
+}
+
+fn is_html(path: &Path) -> bool {
+    path.extension().and_then(|s| s.to_str()) == Some("html")
+}
+
+/// The frontmatter and body of an `.html` file in the content directory
+/// that starts with frontmatter. Those are wrapped in the page template like
+/// markdown pages, or with `wrap: false` published without the frontmatter;
+/// HTML files without any are copied as they are.
+fn html_with_frontmatter(path: &Path) -> io::Result<Option<(Frontmatter, String)>> {
+    if !is_html(path) {
+        return Ok(None);
+    }
+    let content = fs::read_to_string(path)?;
+    if !content.starts_with("---") {
+        return Ok(None);
+    }
+    let (frontmatter, body) = Frontmatter::parse(&content);
+    Ok(Some((frontmatter, body.to_string())))
+}
+
+/// Leaves out the pages marked `draft: true`, and those with a
+/// `publish_date` still to come, unless the config builds drafts, and the
+/// pages that can't be read, which count as failed. Of pages that would be
+/// written to the same place, like the same file in two content
+/// directories, only the first is kept.
+fn readable_pages(
+    md_files: Vec<PathBuf>,
+    content_dirs: &[PathBuf],
+    config: &Config,
+    plugins: &Plugins,
+    report: &mut BuildReport,
+) -> io::Result<Vec<PathBuf>> {
+    let mut readable = Vec::new();
+    let mut outputs: BTreeMap<PathBuf, PathBuf> = BTreeMap::new();
+    for md_file in md_files {
+        match read_page(&md_file, plugins) {
+            Ok((frontmatter, _)) if frontmatter.draft && !config.drafts => {
+                println!("  ✏ Skipped draft {}", md_file.display());
+            }
+            Ok((frontmatter, _)) if is_scheduled(&frontmatter) && !config.drafts => {
+                println!(
+                    "  ⏰ Skipped {} until {}",
+                    md_file.display(),
+                    frontmatter.publish_date.unwrap_or_default()
+                );
+            }
+            Ok((frontmatter, _)) => {
+                let content_dir = content_root(&md_file, content_dirs);
+                let location = page_location(&md_file, &frontmatter, content_dir, config);
+                let output = location.dir.join(&location.filename);
+                if let Some(first) = outputs.get(&output) {
+                    warn(
+                        config,
+                        format!(
+                            "{}: skipped, {} is already written to {}",
+                            md_file.display(),
+                            first.display(),
+                            output.display()
+                        ),
+                    )?;
+                    continue;
+                }
+                outputs.insert(output, md_file.clone());
+                readable.push(md_file);
+            }
+            Err(e) => page_failed(config, report, &md_file, e)?,
+        }
+    }
+    Ok(readable)
+}
+
+/// Whether a page's `publish_date` is still to come.
+fn is_scheduled(frontmatter: &Frontmatter) -> bool {
+    frontmatter
+        .publish_date
+        .as_deref()
+        .and_then(dates::parse)
+        .is_some_and(|date| date > publish::now())
+}
+
+/// Records a page that failed to render so the build can go on with the
+/// others, or fails the build with it when the config asks to fail fast.
+fn page_failed(
+    config: &Config,
+    report: &mut BuildReport,
+    path: &Path,
+    error: io::Error,
+) -> io::Result<()> {
+    if config.fail_fast {
+        return Err(error);
+    }
+    let failure = report.add_failure(path, &error);
+    println!("  ❌ Failed {}: {}", failure.path, failure.error);
+    Ok(())
+}
+
+/// Finds the pages to render under `dir`: markdown, AsciiDoc, org and
+/// notebook files, and HTML files with frontmatter to wrap in the page
+/// template. Symlinked directories are followed as `walk` says.
+fn find_markdown_files(
+    dir: &Path,
+    content_dir: &Path,
+    ignore: &IgnoreRules,
+    walk: &mut Walk,
+) -> io::Result<Vec<PathBuf>> {
+    let mut md_files = Vec::new();
+
+    if !dir.is_dir() {
+        return Ok(md_files);
+    }
+
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+
+        if ignore.is_ignored(&relative_path(&path, content_dir), path.is_dir()) {
+            continue;
+        }
+
+        if path.is_dir() {
+            let found = walk.descend(&path, |walk| {
+                find_markdown_files(&path, content_dir, ignore, walk)
+            })?;
+            md_files.extend(found.unwrap_or_default());
+        } else if InputFormat::of(&path).is_some_and(InputFormat::is_page)
+            || html_with_frontmatter(&path)?.is_some_and(|(fm, _)| fm.wrap != Some(false))
+        {
+            md_files.push(path);
+        }
+    }
+
+    Ok(md_files)
+}
+
+/// Whether `path` is left out of the content: hidden or matching `ignore`.
+fn is_skipped_content(path: &Path, content_dir: &Path, ignore: &IgnoreRules) -> bool {
+    let hidden = path
+        .file_name()
+        .and_then(|s| s.to_str())
+        .is_some_and(|name| name.starts_with('.'));
+    hidden || ignore.is_ignored(&relative_path(path, content_dir), path.is_dir())
+}
+
+/// Plans the image pipeline's work for the images under `content_dir`, the
+/// ones `copy_content_assets` will publish, adding them to `manifest`.
+fn plan_content_images(
+    dir: &Path,
+    content_dir: &Path,
+    ignore: &IgnoreRules,
+    walk: &mut Walk,
+    config: &Config,
+    manifest: &mut AssetManifest,
+) -> io::Result<()> {
+    if !dir.is_dir() {
+        return Ok(());
+    }
+
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if is_skipped_content(&path, content_dir, ignore) {
+            continue;
+        }
+
+        if path.is_dir() {
+            walk.descend(&path, |walk| {
+                plan_content_images(&path, content_dir, ignore, walk, config, manifest)
+            })?;
+        } else {
+            let relative = relative_path(&path, content_dir);
+            manifest.add_content_image(&path, &relative, &config.images)?;
+        }
+    }
+    Ok(())
+}
+
+/// Copies every file under `content_dir` that isn't a page (images, PDFs and the
+/// like kept next to the posts that use them) to the same relative location
+/// in `output_dir`, so relative references from those pages keep resolving.
+/// Hidden files are left behind, and the `images` the pipeline handles are
+/// optimized on the way.
+fn copy_content_assets(
+    dir: &Path,
+    content_dir: &Path,
+    output_dir: &Path,
+    ignore: &IgnoreRules,
+    walk: &mut Walk,
+    sync: &FileSync,
+    images: &BTreeMap<String, ImageInfo>,
+) -> io::Result<()> {
+    if !dir.is_dir() {
+        return Ok(());
+    }
+
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if is_skipped_content(&path, content_dir, ignore) {
+            continue;
+        }
+
+        let relative = path.strip_prefix(content_dir).unwrap_or(&path);
+        if path.is_dir() {
+            walk.descend(&path, |walk| {
+                copy_content_assets(&path, content_dir, output_dir, ignore, walk, sync, images)
+            })?;
+        } else if let Some((frontmatter, body)) = html_with_frontmatter(&path)? {
+            // Wrapped pages are rendered with the markdown ones
+            if frontmatter.wrap == Some(false) {
+                let dest_path = output_dir.join(relative);
+                if let Some(parent) = dest_path.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                sync::unlink(&dest_path)?;
+                fs::write(&dest_path, body)?;
+                println!("  📎 Copied: {}", relative.display());
+            }
+        } else if !InputFormat::of(&path).is_some_and(InputFormat::is_page) {
+            let dest_path = output_dir.join(relative);
+            if let Some(parent) = dest_path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            let key = relative_path(&path, content_dir);
+            if let Some(info) = images.get(&key) {
+                sync::copy_file(&path, &dest_path)?;
+                println!("  📎 Copied: {}", relative.display());
+                images::process(&path, &key, info, output_dir)?;
+            } else if sync.copy(&path, output_dir, &key)? {
+                println!("  📎 Unchanged: {}", relative.display());
+            } else {
+                println!("  📎 Copied: {}", relative.display());
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Builds the site from `content_dir`, `template_dir` and `static/` into
+/// `output_dir`, running `plugins` along the way, followed by the WASM
+/// plugins the config lists.
+///
+/// The build is written to a staging directory first and only swapped in for
+/// `output_dir` once all of it has succeeded, so a failed build leaves the
+/// previous one as it was.
+pub fn build_site(
+    content_dir: &str,
+    output_dir: &str,
+    template_dir: &str,
+    config: &Config,
+    plugins: Plugins,
+) -> io::Result<()> {
+    println!("🔨 Building site ({} profile)...\n", config.profile);
+    let started = Instant::now();
+    let mut report = BuildReport::default();
+    let output_path = Path::new(output_dir);
+    let hook_env = hook_env(content_dir, output_dir, template_dir, config);
+    hooks::run("pre_build", &config.hooks.pre_build, &hook_env)?;
+
+    let staging_path = stage_build(
+        content_dir,
+        output_path,
+        template_dir,
+        config,
+        plugins,
+        &mut report,
+    )?;
+
+    println!("\n🚚 Moving the build into {}/...\n", output_dir);
+    let protect = IgnoreRules::new(&config.prune.protect);
+    let removed = staging::publish(&staging_path, output_path, |path| {
+        !config.prune.enabled || prune::is_protected(path, &protect)
+    })?;
+    for path in &removed {
+        println!("  🗑 Removed stale {}", path);
+    }
+    if !removed.is_empty() {
+        println!("\n🗑 Removed {} stale file(s)", removed.len());
+    }
+
+    hooks::run("post_build", &config.hooks.post_build, &hook_env)?;
+
+    report.duration_ms = started.elapsed().as_millis();
+    report.print();
+    if let Some(path) = &config.report {
+        report.write_json(path)?;
+    }
+
+    // Everything that rendered is published, but the build still fails
+    if !report.failures.is_empty() {
+        report.print_failures();
+        return Err(io::Error::other(format!(
+            "{} page(s) failed to render",
+            report.failures.len()
+        )));
+    }
+
+    println!("\n✨ Site built successfully to {}/", output_dir);
+    Ok(())
+}
+
+/// The environment hook commands run in, telling them where the build reads
+/// from and writes to.
+fn hook_env<'a>(
+    content_dir: &'a str,
+    output_dir: &'a str,
+    template_dir: &'a str,
+    config: &'a Config,
+) -> [(&'static str, &'a str); 5] {
+    [
+        ("BREAD_CONTENT_DIR", content_dir),
+        ("BREAD_OUTPUT_DIR", output_dir),
+        ("BREAD_TEMPLATE_DIR", template_dir),
+        ("BREAD_STATIC_DIR", "static"),
+        ("BREAD_BASE_URL", config.base_url.as_str()),
+    ]
+}
+
+/// Builds the site into the staging directory for `output_dir`, which is
+/// returned. Nothing is left behind if the build fails.
+fn stage_build(
+    content_dir: &str,
+    output_dir: &Path,
+    template_dir: &str,
+    config: &Config,
+    mut plugins: Plugins,
+    report: &mut BuildReport,
+) -> io::Result<PathBuf> {
+    for plugin in wasm::load_plugins(&config.plugins.wasm)? {
+        plugins.register(plugin);
+    }
+    for plugin in plugins.iter() {
+        println!("  🧩 Using plugin: {}", plugin.name());
+    }
+
+    let staging_path = staging::dir(output_dir);
+    if staging_path.exists() {
+        fs::remove_dir_all(&staging_path)?;
+    }
+    fs::create_dir_all(&staging_path)?;
+
+    match render_site(
+        content_dir,
+        &staging_path,
+        template_dir,
+        config,
+        &plugins,
+        &FileSync::new(output_dir, &config.assets),
+        report,
+    ) {
+        Ok(()) => Ok(staging_path),
+        Err(e) => {
+            let _ = fs::remove_dir_all(&staging_path);
+            Err(e)
+        }
+    }
+}
+
+/// The theme's `subdir`, if the site has a theme, followed by the site's own
+/// `dir` that overrides it.
+fn theme_layers(theme_dir: Option<&Path>, subdir: &str, dir: &Path) -> Vec<PathBuf> {
+    theme_dir
+        .map(|theme| theme.join(subdir))
+        .into_iter()
+        .chain([dir.to_path_buf()])
+        .collect()
+}
+
+/// Renders every page, listing and feed and copies the assets into
+/// `output_path`, with `sync`.
+fn render_site(
+    content_dir: &str,
+    output_path: &Path,
+    template_dir: &str,
+    config: &Config,
+    plugins: &Plugins,
+    sync: &FileSync,
+    report: &mut BuildReport,
+) -> io::Result<()> {
+    // Load templates
+    let template_dir_path = Path::new(template_dir);
+    let theme_dir = config.theme_dir();
+    if let Some(dir) = &theme_dir {
+        if !dir.is_dir() {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("Theme '{}' not found in {}", config.theme, dir.display()),
+            ));
+        }
+        println!("  🎨 Using theme: {}", config.theme);
+    }
+    let page_templates = templates::load(template_dir_path, theme_dir.as_deref())?;
+
+    let date_formats: Vec<(String, String)> = [("date".to_string(), config.dates.format.clone())]
+        .into_iter()
+        .chain(
+            config
+                .dates
+                .formats
+                .iter()
+                .map(|(name, format)| (format!("date_{}", name), format.clone())),
+        )
+        .collect();
+
+    // Initialize template engine
+    let mut tt = TinyTemplate::new();
+    tt.set_default_formatter(&format_unescaped);
+    // Values are inserted as-is by default, so contexts escape their plain
+    // text themselves; `{value | escape}` for anything else
+    tt.add_formatter("escape", tinytemplate::format);
+    // `{date | date}`, and `{date | date_<name>}` for the named formats
+    for (name, format) in &date_formats {
+        dates::validate_format(format)?;
+        let format = format.clone();
+        tt.add_formatter(name, move |value, output| {
+            if let serde_json::Value::String(date) = value {
+                output.push_str(&dates::format(date, &format));
+            }
+            Ok(())
+        });
+    }
+    // Registered under `shortcodes/<name>` so they can't clash with page templates
+    let shortcodes: BTreeMap<String, String> = shortcodes::load_templates(&theme_layers(
+        theme_dir.as_deref(),
+        "templates/shortcodes",
+        &template_dir_path.join("shortcodes"),
+    ))?
+    .into_iter()
+    .map(|(name, template)| (format!("shortcodes/{}", name), template))
+    .collect();
+
+    let registered: Vec<(&str, &str)> = page_templates
+        .iter()
+        .chain(&shortcodes)
+        .map(|(name, template)| (name.as_str(), template.as_str()))
+        .collect();
+    let template_sources: BTreeMap<String, &str> = registered
+        .iter()
+        .map(|(name, template)| (name.to_string(), *template))
+        .collect();
+    for (name, template) in registered {
+        tt.add_template(name, template)
+            .map_err(|e| templates::error(&e, name, template_dir_path, &template_sources))?;
+    }
+
+    // Fingerprinted asset names have to be known before any page renders
+    let static_path = Path::new("static");
+    let mut manifest = AssetManifest::build(
+        &theme_layers(theme_dir.as_deref(), "static", static_path),
+        config,
+    )?;
+
+    // Find and process markdown files
+    let content_dirs = config.content_roots(Path::new(content_dir));
+    let ignore = IgnoreRules::load(&config.ignore, Path::new(".breadignore"))?;
+    // Images next to the content go through the pipeline like static ones
+    for dir in &content_dirs {
+        let mut walk = Walk::new(dir, config.follow_symlinks);
+        plan_content_images(dir, dir, &ignore, &mut walk, config, &mut manifest)?;
+    }
+    let mut md_files = Vec::new();
+    for dir in &content_dirs {
+        let mut walk = Walk::new(dir, config.follow_symlinks);
+        md_files.extend(find_markdown_files(dir, dir, &ignore, &mut walk)?);
+    }
+    let md_files = readable_pages(md_files, &content_dirs, config, plugins, report)?;
+
+    if md_files.is_empty() {
+        warn(
+            config,
+            format!("No markdown files found in {}", content_dir),
+        )?;
+    } else {
+        println!("  Found {} markdown file(s)\n", md_files.len());
+    }
+
+    let mut site = Site {
+        content_dirs: &content_dirs,
+        output_dir: output_path,
+        config,
+        tt,
+        template_dir: template_dir_path,
+        template_sources,
+        posts: BTreeMap::new(),
+        translations: BTreeMap::new(),
+        assets: manifest.template_map(),
+        data: data::load(Path::new("data"))?,
+        menus: Vec::new(),
+        images: &manifest.images,
+        shortcodes: &shortcodes,
+        wikilinks: collect_wikilink_targets(&md_files, &content_dirs, config, plugins)?,
+        link_graph: LinkGraph::default(),
+        history: BTreeMap::new(),
+        base_path: config.base_path(),
+        has_not_found_template: page_templates.contains_key("404"),
+        plugins,
+        bytes_written: Cell::new(0),
+        converted: RefCell::new(BTreeMap::new()),
+        has_copyable_code: Cell::new(false),
+        has_protected: Cell::new(false),
+    };
+    site.link_graph = collect_link_graph(&md_files, &site)?;
+    site.translations = collect_translations(&md_files, &site)?;
+    site.menus = menus::collect(&md_files, &site)?;
+    if config.git_history {
+        for dir in &content_dirs {
+            match history::load(dir) {
+                Ok(history) => {
+                    for (path, file) in history {
+                        site.history.entry(path).or_insert(file);
+                    }
+                }
+                Err(e) => warn(
+                    config,
+                    format!("No git history for {}: {}", dir.display(), e),
+                )?,
+            }
+        }
+    }
+
+    // Collect post metadata
+    let mut posts: Vec<PostMetadata> = md_files
+        .iter()
+        .filter_map(|md_file| collect_post_metadata(md_file, &site).ok().flatten())
+        .collect();
+
+    posts.sort_by(|a, b| b.date.cmp(&a.date));
+    for post in posts {
+        site.posts.entry(post.lang.clone()).or_default().push(post);
+    }
+
+    // Process all markdown files
+    let mut pages = Vec::new();
+    for md_file in &md_files {
+        let page_started = Instant::now();
+        let page = match process_markdown_file(md_file, &site) {
+            Ok(page) => page,
+            Err(e) => {
+                page_failed(config, report, md_file, e)?;
+                continue;
+            }
+        };
+        report.add_page(md_file, page.word_count, page_started.elapsed());
+        if !is_not_found_page(md_file, site.content_dir(md_file)) {
+            pages.push(page);
+        }
+    }
+
+    search::write_search_index(&pages, output_path)?;
+    links::write_graph_json(&site.link_graph, output_path)?;
+    if config.netlify_redirects {
+        write_netlify_redirects(&pages, config, output_path)?;
+    }
+
+    // Posts pages, feeds and series pages, for each language
+    let template = &config.posts.listing_template;
+    if config.posts.listing && !site.template_sources.contains_key(template) {
+        warn(
+            config,
+            format!(
+                "{} is missing, so there's no posts listing",
+                site.template_path(template).display()
+            ),
+        )?;
+    }
+    let mut extra_urls = Vec::new();
+    for (lang, posts) in &site.posts {
+        let language_prefix = config.language_prefix(lang);
+        if let Some(url) = site.listing_url(lang) {
+            let generated = generate_posts_page(&site, lang);
+            if skip_on_error(config, "the posts listing", generated)? {
+                extra_urls.push(url);
+            }
+        }
+        if config.feeds.enabled {
+            let written = feeds::write_feeds(
+                posts,
+                &collect_tags(posts),
+                config,
+                &output_path.join(language_prefix.trim_start_matches('/')),
+            );
+            skip_on_error(config, "the feeds", written)?;
+        }
+
+        if config.posts.tag_pages {
+            extra_urls.extend(generate_tag_pages(&site, lang)?);
+        }
+        if config.posts.series_pages {
+            extra_urls.extend(generate_series_pages(&site, lang)?);
+        }
+        if config.posts.author_pages {
+            extra_urls.extend(authors::generate_author_pages(&site, lang)?);
+        }
+    }
+
+    if config.gemini.enabled {
+        gemini::write_index(
+            site.posts_for(config.default_language()),
+            config,
+            output_path,
+        )?;
+    }
+
+    if config.activitypub.enabled {
+        let written = activitypub::write(
+            site.posts_for(config.default_language()),
+            config,
+            output_path,
+        );
+        skip_on_error(config, "the ActivityPub export", written)?;
+    }
+
+    extra_urls.extend(blogroll::generate_blogroll(&site, &pages)?);
+    extra_urls.extend(generate_template_pages(&site, &pages)?);
+    if config.sitemap {
+        let written = sitemap::write_sitemap(&pages, &extra_urls, config, output_path);
+        skip_on_error(config, "the sitemap", written)?;
+    }
+    sitemap::write_robots_txt(config, output_path)?;
+    well_known::write_files(config, output_path)?;
+
+    let has_not_found_page = md_files
+        .iter()
+        .any(|md_file| is_not_found_page(md_file, site.content_dir(md_file)));
+    if site.has_not_found_template && !has_not_found_page {
+        generate_not_found_page(&site)?;
+    }
+
+    for posts in site.posts.values() {
+        report.posts += posts.len();
+        for tag in collect_tags(posts) {
+            *report.tags.entry(tag.name).or_default() += tag.posts.len();
+        }
+    }
+    report.bytes_written = site.bytes_written.get();
+    if site.has_copyable_code.get() {
+        fs::write(output_path.join(COPY_CODE_SCRIPT), COPY_CODE_JS)?;
+        println!("  📋 Generated {}", COPY_CODE_SCRIPT);
+    }
+    if site.has_protected.get() {
+        fs::write(
+            output_path.join(protect::DECRYPT_SCRIPT),
+            protect::DECRYPT_JS,
+        )?;
+        println!("  🔒 Generated {}", protect::DECRYPT_SCRIPT);
+    }
+
+    if content_dirs.iter().any(|dir| dir.is_dir()) {
+        println!("\n📦 Copying content assets...\n");
+        for dir in &content_dirs {
+            let mut walk = Walk::new(dir, config.follow_symlinks);
+            copy_content_assets(
+                dir,
+                dir,
+                output_path,
+                &ignore,
+                &mut walk,
+                sync,
+                &manifest.images,
+            )?;
+        }
+    }
+
+    // Copy static assets
+    println!("\n📦 Copying static assets...\n");
+
+    if !static_path.is_dir() && theme_dir.is_none() {
+        println!(
+            "  ℹ No static directory found, so only the default theme's CSS and script are published. Create 'static/' for your own."
+        );
+    }
+    manifest.publish(output_path, sync)?;
+
+    if !config.domain.is_empty() {
+        write_github_pages_files(&config.domain, output_path)?;
+    }
+
+    plugins.after_build(output_path)
+}
+
+/// Builds the site into its staging directory and reports which files in
+/// `output_dir` the build would create, change or delete, without moving it
+/// into place. Hooks aren't run, since they may have effects of their own.
+pub fn dry_run(
+    content_dir: &str,
+    output_dir: &str,
+    template_dir: &str,
+    config: &Config,
+    plugins: Plugins,
+) -> io::Result<()> {
+    let output_path = Path::new(output_dir);
+    let mut report = BuildReport::default();
+    let staging_path = stage_build(
+        content_dir,
+        output_path,
+        template_dir,
+        config,
+        plugins,
+        &mut report,
+    )?;
+    let result = diff::compare(&staging_path, output_path);
+    fs::remove_dir_all(&staging_path)?;
+
+    let mut diff = result?;
+    if config.prune.enabled {
+        let protect = IgnoreRules::new(&config.prune.protect);
+        diff.deleted
+            .retain(|path| !prune::is_protected(path, &protect));
+    } else {
+        diff.deleted.clear();
+    }
+    diff.print(output_dir);
+    Ok(())
+}
+
+/// Prints statistics about the content in `content_dir` without building
+/// the site.
+pub fn print_stats(content_dir: &str, config: &Config) -> io::Result<()> {
+    report::print_content_stats(Path::new(content_dir), config)
+}
+
+/// Runs the `[lint]` prose checks over the markdown in `content_dir`,
+/// returning how many errors they found.
+pub fn lint_content(content_dir: &str, config: &Config) -> io::Result<usize> {
+    lint::lint_content(Path::new(content_dir), config)
+}
+
+/// Checks the built site in `output_dir` for broken internal links and
+/// anchors, and for the accessibility problems `[accessibility]` asks
+/// about, returning how many of them are errors.
+pub fn check_site(output_dir: &str, base_path: &str, config: &Config) -> io::Result<usize> {
+    println!("🔍 Checking links in {}/...\n", output_dir);
+
+    let output_path = Path::new(output_dir);
+    if !output_path.is_dir() {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!(
+                "output directory {} does not exist, run `bread build` first",
+                output_dir
+            ),
+        ));
+    }
+
+    let broken = check::check_links(output_path, base_path)?;
+    for link in &broken {
+        println!(
+            "  ✗ {}: {} ({})",
+            output_path.join(&link.page).display(),
+            link.link,
+            link.reason
+        );
+    }
+
+    if broken.is_empty() {
+        println!("✨ No broken links found");
+    } else {
+        println!("\n❌ Found {} broken link(s)", broken.len());
+    }
+
+    println!("\n♿ Checking accessibility in {}/...\n", output_dir);
+    let reports = check::check_accessibility(output_path, &config.accessibility)?;
+    let (mut errors, mut warnings) = (0, 0);
+    for (page, issues) in &reports {
+        println!("  {}", output_path.join(page).display());
+        for issue in issues {
+            let mark = if issue.severity == Severity::Error {
+                errors += 1;
+                "✗"
+            } else {
+                warnings += 1;
+                "⚠"
+            };
+            println!(
+                "    {} line {}: {} ({})",
+                mark, issue.line, issue.message, issue.rule
+            );
+        }
+    }
+
+    if reports.is_empty() {
+        println!("✨ No accessibility problems found");
+    } else {
+        println!(
+            "\n{} Found {} accessibility error(s) and {} warning(s) in {} page(s)",
+            if errors > 0 { "❌" } else { "⚠" },
+            errors,
+            warnings,
+            reports.len()
+        );
+    }
+    Ok(broken.len() + errors)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An empty directory of its own for a test to write into.
+    pub(crate) fn scratch_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("bread-{}-{}", std::process::id(), name));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn frontmatter_parse_splits_off_the_body() {
+        let (frontmatter, body) = Frontmatter::parse("---\ntitle: Hello\n---\n\n# Hello\n");
+        assert_eq!(frontmatter.title.as_deref(), Some("Hello"));
+        assert_eq!(body, "# Hello\n");
+    }
+
+    #[test]
+    fn frontmatter_parse_leaves_content_without_it_alone() {
+        let content = "# Just markdown\n";
+        let (frontmatter, body) = Frontmatter::parse(content);
+        assert!(frontmatter.title.is_none());
+        assert_eq!(body, content);
+
+        let unclosed = "---\ntitle: Never closed\n";
+        assert_eq!(Frontmatter::parse(unclosed).1, unclosed);
+    }
+
+    #[test]
+    fn frontmatter_parse_reads_lists_both_ways() {
+        let (inline, _) =
+            Frontmatter::parse("---\ntags: rust, web\naliases: [/a/, /b.html]\n---\n");
+        assert_eq!(
+            inline.tags,
+            Some(vec!["rust".to_string(), "web".to_string()])
+        );
+        assert_eq!(inline.aliases, ["/a/", "/b.html"]);
+
+        let (listed, _) =
+            Frontmatter::parse("---\ntags:\n  - rust\n  - web\nauthors:\n  - ana\n---\n");
+        assert_eq!(
+            listed.tags,
+            Some(vec!["rust".to_string(), "web".to_string()])
+        );
+        assert_eq!(listed.authors, ["ana"]);
+    }
+
+    #[test]
+    fn frontmatter_parse_reads_flags_and_numbers() {
+        let (frontmatter, _) = Frontmatter::parse(
+            "---\ndraft: true\npinned: false\nweight: -2\nseries_part: 3\nkind: page\nmarkdown.smart_punctuation: false\n---\n",
+        );
+        assert!(frontmatter.draft);
+        assert!(!frontmatter.pinned);
+        assert_eq!(frontmatter.weight, Some(-2));
+        assert_eq!(frontmatter.series_part, Some(3));
+        assert!(matches!(frontmatter.kind, Some(PageKind::Page)));
+        assert_eq!(
+            frontmatter.markdown,
+            [("smart_punctuation".to_string(), false)]
+        );
+    }
+
+    #[test]
+    fn frontmatter_parse_keeps_unknown_keys_as_extra() {
+        let (frontmatter, _) =
+            Frontmatter::parse("---\nmood: sunny\nfeatured: true\nlinks:\n  - one\n  - two\n---\n");
+        assert_eq!(frontmatter.extra["mood"], serde_json::json!("sunny"));
+        assert_eq!(frontmatter.extra["featured"], serde_json::json!(true));
+        assert_eq!(
+            frontmatter.extra["links"],
+            serde_json::json!(["one", "two"])
+        );
+    }
+
+    fn permalinked(pattern: &str, markdown: &str, path: &str) -> PageLocation {
+        let mut config = Config::default();
+        config.posts.permalink = pattern.to_string();
+        let (frontmatter, _) = Frontmatter::parse(markdown);
+        page_location(
+            &Path::new("content").join(path),
+            &frontmatter,
+            Path::new("content"),
+            &config,
+        )
+    }
+
+    #[test]
+    fn permalinks_place_posts_by_their_date_slug_and_section() {
+        let post = "---\ntitle: Hello, World\ndate: 2024-03-05\n---\n";
+        let location = permalinked("/:year/:month/:slug/", post, "posts/hello.md");
+        assert_eq!(location.url, "/2024/03/hello/");
+        assert_eq!(location.dir, Path::new("2024/03/hello"));
+        assert_eq!(location.filename, "index.html");
+
+        let location = permalinked(":section/:day-:title", post, "notes/hello.md");
+        assert_eq!(location.url, "/notes/05-hello-world.html");
+        let location = permalinked(":section/:slug.htm", post, "hello.md");
+        assert_eq!(location.url, "/hello.htm");
+    }
+
+    #[test]
+    fn permalinks_leave_pages_and_undated_posts_in_place() {
+        let undated = "---\ntitle: Hello\n---\n";
+        let location = permalinked("/:year/:slug/", undated, "posts/hello.md");
+        assert_eq!(location.url, "/posts/hello.html");
+        let dated = "---\ndate: 2024-03-05\n---\n";
+        let location = permalinked("/:year/:slug/", dated, "pages/about.md");
+        assert_eq!(location.url, "/pages/about.html");
+        let location = permalinked("", dated, "posts/hello.md");
+        assert_eq!(location.url, "/posts/hello.html");
+    }
+
+    #[test]
+    fn content_images_are_planned_unless_hidden_or_replaced_by_static_ones() {
+        let dir = scratch_dir("content-images");
+        fs::create_dir_all(dir.join("posts/.drafts")).unwrap();
+        for path in ["posts/wide.png", "posts/.drafts/wide.png", "logo.png"] {
+            image::RgbImage::new(40, 2).save(dir.join(path)).unwrap();
+        }
+
+        let mut config = Config::default();
+        config.images.optimize = true;
+        config.images.max_width = 20;
+        let mut manifest = AssetManifest::default();
+        manifest
+            .entries
+            .insert("logo.png".to_string(), "logo.png".to_string());
+        let mut walk = Walk::new(&dir, false);
+        let ignore = IgnoreRules::new(&[]);
+        plan_content_images(&dir, &dir, &ignore, &mut walk, &config, &mut manifest).unwrap();
+
+        assert_eq!(
+            manifest.images.keys().collect::<Vec<_>>(),
+            ["posts/wide.png"]
+        );
+        assert_eq!(manifest.images["posts/wide.png"].width, 20);
+    }
+}
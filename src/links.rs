@@ -0,0 +1,164 @@
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// A page as it appears in the link graph.
+#[derive(Debug, Clone)]
+pub struct PageNode {
+    pub title: String,
+    pub date: String,
+}
+
+/// Which pages link to which, by site-relative URL. Only links between pages
+/// of the site are recorded; assets, external links and links from a page to
+/// itself are left out.
+#[derive(Debug, Default)]
+pub struct LinkGraph {
+    pub pages: BTreeMap<String, PageNode>,
+    pub links: BTreeMap<String, BTreeSet<String>>,
+}
+
+impl LinkGraph {
+    pub fn add_page(&mut self, url: &str, title: &str, date: &str) {
+        self.pages.insert(
+            url.to_string(),
+            PageNode {
+                title: title.to_string(),
+                date: date.to_string(),
+            },
+        );
+    }
+
+    /// Records the links found on the page at `url`. Call once every page has
+    /// been added, so links can be checked against them.
+    pub fn add_links<'a>(
+        &mut self,
+        url: &str,
+        hrefs: impl IntoIterator<Item = &'a str>,
+        base_path: &str,
+    ) {
+        let targets = hrefs
+            .into_iter()
+            .filter_map(|href| normalize_link(href, url, base_path))
+            .map(|target| self.page_key(target))
+            .filter(|target| target != url && self.pages.contains_key(target))
+            .collect();
+        self.links.insert(url.to_string(), targets);
+    }
+
+    /// The URL a page is known by in the graph, for a link to it: links to
+    /// `/dir/` come out of `normalize_link` as `/dir/index.html`, while pages
+    /// published at `/dir/` are added under that.
+    fn page_key(&self, target: String) -> String {
+        match target.strip_suffix("index.html") {
+            Some(dir) if !self.pages.contains_key(&target) && self.pages.contains_key(dir) => {
+                dir.to_string()
+            }
+            _ => target,
+        }
+    }
+
+    /// URLs of the pages linking to `url`, in URL order.
+    pub fn backlinks(&self, url: &str) -> Vec<&str> {
+        self.links
+            .iter()
+            .filter(|(_, targets)| targets.contains(url))
+            .map(|(source, _)| source.as_str())
+            .collect()
+    }
+}
+
+#[derive(Serialize, Debug)]
+struct GraphNode<'a> {
+    id: &'a str,
+    title: &'a str,
+    date: &'a str,
+}
+
+#[derive(Serialize, Debug)]
+struct GraphEdge<'a> {
+    source: &'a str,
+    target: &'a str,
+}
+
+#[derive(Serialize, Debug)]
+struct GraphJson<'a> {
+    nodes: Vec<GraphNode<'a>>,
+    edges: Vec<GraphEdge<'a>>,
+}
+
+/// Writes `graph.json`: every page as a node (its `id` being its URL) and
+/// every link between two pages as an edge, in the shape graph libraries
+/// like D3's force layout expect.
+pub fn write_graph_json(graph: &LinkGraph, output_dir: &Path) -> io::Result<()> {
+    let nodes: Vec<GraphNode> = graph
+        .pages
+        .iter()
+        .map(|(url, page)| GraphNode {
+            id: url,
+            title: &page.title,
+            date: &page.date,
+        })
+        .collect();
+    let edges: Vec<GraphEdge> = graph
+        .links
+        .iter()
+        .flat_map(|(source, targets)| {
+            targets
+                .iter()
+                .map(move |target| GraphEdge { source, target })
+        })
+        .collect();
+
+    let (node_count, edge_count) = (nodes.len(), edges.len());
+    let json = serde_json::to_string(&GraphJson { nodes, edges }).map_err(io::Error::other)?;
+    fs::write(output_dir.join("graph.json"), json)?;
+    println!(
+        "  🕸  Generated graph.json ({} pages, {} links)",
+        node_count, edge_count
+    );
+
+    Ok(())
+}
+
+/// Turns a link found on the page at `page_url` into the site-relative URL
+/// of the page it points at. Returns `None` for external links and links
+/// within the same page.
+pub fn normalize_link(href: &str, page_url: &str, base_path: &str) -> Option<String> {
+    if href.contains("://") || href.starts_with("//") || href.starts_with("mailto:") {
+        return None;
+    }
+
+    let path = href.split(['#', '?']).next().unwrap_or("");
+    if path.is_empty() {
+        return None;
+    }
+
+    let absolute = match path.strip_prefix(base_path) {
+        Some(rest) => format!("/{}", rest),
+        None if path.starts_with('/') => path.to_string(),
+        None => {
+            let dir = page_url.rsplit_once('/').map_or("", |(dir, _)| dir);
+            format!("{}/{}", dir, path)
+        }
+    };
+
+    let mut segments: Vec<&str> = Vec::new();
+    for segment in absolute.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." => {
+                segments.pop();
+            }
+            segment => segments.push(segment),
+        }
+    }
+
+    let mut url = format!("/{}", segments.join("/"));
+    if absolute.ends_with('/') {
+        url = format!("{}/index.html", url.trim_end_matches('/'));
+    }
+    Some(url)
+}
@@ -0,0 +1,337 @@
+use regex::{Regex, RegexBuilder};
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::config::{Config, LintConfig, Severity};
+use crate::find_markdown_files;
+use crate::formats::InputFormat;
+use crate::ignore::IgnoreRules;
+use crate::walk::Walk;
+
+const MISSPELLINGS: &str = include_str!("misspellings.txt");
+
+/// Something `bread lint` found in a line of prose.
+#[derive(Debug)]
+struct Problem {
+    line: usize,
+    column: usize,
+    /// `spelling`, or the name of the `[[lint.rules]]` entry
+    rule: String,
+    message: String,
+    severity: Severity,
+}
+
+/// A `[[lint.rules]]` entry, compiled.
+struct Rule {
+    name: String,
+    regex: Regex,
+    message: String,
+    severity: Severity,
+}
+
+/// The checks `[lint]` asks for, ready to run over file after file.
+struct Linter {
+    /// Misspelled words, lowercase, and what they should be
+    misspellings: HashMap<String, String>,
+    spelling: Severity,
+    rules: Vec<Rule>,
+}
+
+/// Runs the `[lint]` checks over the prose of every markdown file in the
+/// content directories, printing the problems found file by file with
+/// their line and column. Returns how many of them are errors.
+pub fn lint_content(content_dir: &Path, config: &Config) -> io::Result<usize> {
+    let linter = Linter::new(&config.lint)?;
+    let ignore = IgnoreRules::load(&config.ignore, Path::new(".breadignore"))?;
+    let mut md_files = Vec::new();
+    for dir in &config.content_roots(content_dir) {
+        let mut walk = Walk::new(dir, config.follow_symlinks);
+        md_files.extend(find_markdown_files(dir, dir, &ignore, &mut walk)?);
+    }
+    md_files.retain(|file| matches!(InputFormat::of(file), Some(InputFormat::Markdown)));
+    md_files.sort();
+
+    println!("✍️  Linting {} file(s)...\n", md_files.len());
+    let (mut errors, mut warnings, mut files) = (0, 0, 0);
+    for md_file in &md_files {
+        let problems = linter.check(&fs::read_to_string(md_file)?);
+        if problems.is_empty() {
+            continue;
+        }
+        files += 1;
+        println!("  {}", md_file.display());
+        for problem in &problems {
+            let mark = if problem.severity == Severity::Error {
+                errors += 1;
+                "✗"
+            } else {
+                warnings += 1;
+                "⚠"
+            };
+            println!(
+                "    {} {}:{}: {} ({})",
+                mark, problem.line, problem.column, problem.message, problem.rule
+            );
+        }
+    }
+
+    if files == 0 {
+        println!("✨ No problems found");
+    } else {
+        println!(
+            "\n{} Found {} error(s) and {} warning(s) in {} file(s)",
+            if errors > 0 { "❌" } else { "⚠" },
+            errors,
+            warnings,
+            files
+        );
+    }
+    Ok(errors)
+}
+
+impl Linter {
+    fn new(config: &LintConfig) -> io::Result<Self> {
+        let mut misspellings: HashMap<String, String> = MISSPELLINGS
+            .lines()
+            .filter(|line| !line.starts_with('#'))
+            .filter_map(|line| line.split_once(' '))
+            .map(|(wrong, right)| (wrong.to_string(), right.to_string()))
+            .collect();
+        for (wrong, right) in &config.misspellings {
+            misspellings.insert(wrong.to_lowercase(), right.clone());
+        }
+        for word in &config.words {
+            misspellings.remove(&word.to_lowercase());
+        }
+
+        let rules = config
+            .rules
+            .iter()
+            .filter(|rule| rule.severity != Severity::Off)
+            .map(|rule| {
+                let regex = RegexBuilder::new(&rule.pattern)
+                    .case_insensitive(rule.ignore_case)
+                    .build()
+                    .map_err(|e| {
+                        io::Error::new(
+                            io::ErrorKind::InvalidData,
+                            format!("lint rule '{}': {}", rule.name, e),
+                        )
+                    })?;
+                Ok(Rule {
+                    name: rule.name.clone(),
+                    regex,
+                    message: rule.message.clone(),
+                    severity: rule.severity,
+                })
+            })
+            .collect::<io::Result<_>>()?;
+
+        Ok(Linter {
+            misspellings,
+            spelling: config.spelling,
+            rules,
+        })
+    }
+
+    /// The problems in the markdown `text`, in order.
+    fn check(&self, text: &str) -> Vec<Problem> {
+        let mut problems = Vec::new();
+        for (number, line) in prose_lines(text) {
+            let prose = mask(line);
+            let column = |at: usize| line[..at].chars().count() + 1;
+
+            if self.spelling != Severity::Off {
+                for (at, word) in words(&prose) {
+                    if let Some(right) = self.misspellings.get(&word.to_lowercase()) {
+                        problems.push(Problem {
+                            line: number,
+                            column: column(at),
+                            rule: "spelling".to_string(),
+                            message: format!("'{}' is misspelled, did you mean '{}'?", word, right),
+                            severity: self.spelling,
+                        });
+                    }
+                }
+            }
+            for rule in &self.rules {
+                for found in rule.regex.find_iter(&prose) {
+                    problems.push(Problem {
+                        line: number,
+                        column: column(found.start()),
+                        rule: rule.name.clone(),
+                        message: format!("{}: '{}'", rule.message, found.as_str()),
+                        severity: rule.severity,
+                    });
+                }
+            }
+        }
+        problems.sort_by_key(|problem| (problem.line, problem.column));
+        problems
+    }
+}
+
+/// The lines of a markdown file that hold prose, numbered from 1: all but
+/// its frontmatter and fenced code blocks.
+fn prose_lines(text: &str) -> Vec<(usize, &str)> {
+    let mut lines = Vec::new();
+    let mut in_frontmatter = false;
+    let mut fence: Option<&str> = None;
+    for (i, line) in text.lines().enumerate() {
+        let trimmed = line.trim();
+        if i == 0 && trimmed == "---" {
+            in_frontmatter = true;
+            continue;
+        }
+        if in_frontmatter {
+            in_frontmatter = trimmed != "---";
+            continue;
+        }
+
+        match fence {
+            Some(marker) => {
+                if trimmed.starts_with(marker) {
+                    fence = None;
+                }
+            }
+            None if trimmed.starts_with("```") => fence = Some("```"),
+            None if trimmed.starts_with("~~~") => fence = Some("~~~"),
+            None => lines.push((i + 1, line)),
+        }
+    }
+    lines
+}
+
+/// `line` with what isn't prose blanked out, keeping everything else where
+/// it was: inline code, HTML tags, link targets, URLs and shortcodes.
+fn mask(line: &str) -> String {
+    let mut bytes = line.as_bytes().to_vec();
+    let mut blank = |from: usize, to: usize| {
+        for byte in &mut bytes[from..to] {
+            *byte = b' ';
+        }
+    };
+
+    let spans = [
+        ("`", "`"),
+        ("{{", "}}"),
+        ("{%", "%}"),
+        ("<", ">"),
+        ("](", ")"),
+    ];
+    for (open, close) in spans {
+        let mut pos = 0;
+        while let Some(offset) = line[pos..].find(open) {
+            let start = pos + offset;
+            let after = start + open.len();
+            let end = line[after..]
+                .find(close)
+                .map_or(line.len(), |len| after + len + close.len());
+            blank(start, end);
+            pos = end;
+        }
+    }
+    for scheme in ["http://", "https://"] {
+        let mut pos = 0;
+        while let Some(offset) = line[pos..].find(scheme) {
+            let start = pos + offset;
+            let end = line[start..]
+                .find(char::is_whitespace)
+                .map_or(line.len(), |len| start + len);
+            blank(start, end);
+            pos = end;
+        }
+    }
+    // Only ASCII bytes were blanked, between ASCII delimiters
+    String::from_utf8(bytes).unwrap_or_default()
+}
+
+/// The words in `prose` with where they start, apostrophes and all.
+fn words(prose: &str) -> Vec<(usize, &str)> {
+    let mut words = Vec::new();
+    let mut start = None;
+    for (i, c) in prose.char_indices().chain([(prose.len(), ' ')]) {
+        let in_word = c.is_alphabetic() || (matches!(c, '\'' | '’') && start.is_some());
+        match (start, in_word) {
+            (None, true) => start = Some(i),
+            (Some(from), false) => {
+                words.push((from, prose[from..i].trim_end_matches(['\'', '’'])));
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    words
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn linter(config: &str) -> Linter {
+        Linter::new(&toml::from_str(config).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn check_only_reads_prose() {
+        let text = concat!(
+            "---\ntitle: acheive\n---\n",
+            "We acheive `acheive` at [acheive](/acheive) and https://x.com/acheive\n",
+            "```\nacheive\n```\n",
+            "<span title=\"acheive\">Bread</span> won't accomodate it.\n",
+        );
+        let problems = linter("").check(text);
+        let found: Vec<(usize, usize, &str)> = problems
+            .iter()
+            .map(|p| (p.line, p.column, p.message.as_str()))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                (4, 4, "'acheive' is misspelled, did you mean 'achieve'?"),
+                (4, 26, "'acheive' is misspelled, did you mean 'achieve'?"),
+                (
+                    8,
+                    42,
+                    "'accomodate' is misspelled, did you mean 'accommodate'?"
+                ),
+            ]
+        );
+        assert!(problems.iter().all(|p| p.severity == Severity::Warning));
+    }
+
+    #[test]
+    fn check_runs_the_sites_own_words_and_rules() {
+        let linter = linter(
+            r#"
+            spelling = "error"
+            words = ["acheive"]
+            misspellings = { Breadd = "bread" }
+
+            [[rules]]
+            name = "weasel"
+            pattern = '\bvery\b'
+            message = "Cut it"
+            ignore_case = true
+            "#,
+        );
+        let problems = linter.check("Very acheive, breadd.\n");
+        let found: Vec<(&str, usize, Severity)> = problems
+            .iter()
+            .map(|p| (p.rule.as_str(), p.column, p.severity))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                ("weasel", 1, Severity::Warning),
+                ("spelling", 15, Severity::Error),
+            ]
+        );
+
+        let broken =
+            toml::from_str("[[rules]]\nname = \"x\"\npattern = \"(\"\nmessage = \"\"").unwrap();
+        assert!(Linter::new(&broken).is_err());
+    }
+}
@@ -0,0 +1,1298 @@
+use pulldown_cmark::{
+    CodeBlockKind, CowStr, Event, HeadingLevel, LinkType, Options, Parser as MdParser, Tag, TagEnd,
+};
+use std::collections::{BTreeMap, HashSet};
+use std::io::Write;
+use std::ops::RangeInclusive;
+use std::process::{Command, Stdio};
+
+use crate::check::attribute_values;
+use crate::config::{MarkdownConfig, TaskListStyle};
+use crate::emoji;
+use crate::escape_html;
+use crate::images::ImageInfo;
+use crate::links;
+
+/// A heading in a page's table of contents, with the headings nested below it.
+#[derive(Debug, Clone)]
+pub struct TocEntry {
+    pub id: String,
+    pub title: String,
+    pub children: Vec<TocEntry>,
+}
+
+#[derive(Debug)]
+pub struct RenderedMarkdown {
+    pub html: String,
+    pub toc: Vec<TocEntry>,
+    /// Whether the page contains any math, and so needs KaTeX loaded
+    pub has_math: bool,
+    /// Whether the page has Mermaid diagrams left for the browser to draw
+    pub has_mermaid: bool,
+    /// Whether any code block got a copy button, which needs its script
+    pub has_copyable_code: bool,
+    /// Destinations of the page's links, in document order
+    pub links: Vec<String>,
+}
+
+impl RenderedMarkdown {
+    /// A page that arrives as HTML, written by hand or by a converter,
+    /// passed through as it is.
+    pub fn from_html(html: String) -> Self {
+        RenderedMarkdown {
+            links: attribute_values(&html, "href"),
+            html,
+            toc: Vec::new(),
+            has_math: false,
+            has_mermaid: false,
+            has_copyable_code: false,
+        }
+    }
+}
+
+/// What rendering a page needs to know about the rest of the site.
+pub struct RenderContext<'a> {
+    /// The image pipeline's manifest, used to build responsive markup for the
+    /// images it knows about
+    pub images: &'a BTreeMap<String, ImageInfo>,
+    /// Page URLs keyed by `wikilink_key` of their titles and file names
+    pub wikilinks: &'a BTreeMap<String, String>,
+    /// Prefix for site-relative URLs, ending in `/`
+    pub base_path: &'a str,
+    /// URL of the page being rendered, site-relative
+    pub page_url: &'a str,
+}
+
+pub fn markdown_to_html(
+    markdown: &str,
+    config: &MarkdownConfig,
+    site: &RenderContext,
+) -> RenderedMarkdown {
+    let options = parser_options(config);
+    let parser = MdParser::new_ext(markdown, options);
+    let mut events: Vec<Event> = Vec::new();
+    let mut headings: Vec<(u8, String, String)> = Vec::new();
+    let mut used_ids: HashSet<String> = HashSet::new();
+
+    // Explicit `{#id}` attributes are reserved up front so generated ids never
+    // shadow them, keeping hand-picked anchors stable as the page changes
+    for event in MdParser::new_ext(markdown, options) {
+        if let Event::Start(Tag::Heading { id: Some(id), .. }) = event {
+            used_ids.insert(id.to_string());
+        }
+    }
+
+    // Buffer each heading until its end so the id can be derived from its text
+    let mut heading: Option<(Tag, Vec<Event>)> = None;
+
+    for event in parser.map(|event| shift_heading(event, config)) {
+        match event {
+            Event::Start(tag @ Tag::Heading { .. }) => heading = Some((tag, Vec::new())),
+            Event::End(TagEnd::Heading(level)) => {
+                let Some((tag, inner)) = heading.take() else {
+                    continue;
+                };
+                let text = plain_text(&inner);
+                let Tag::Heading {
+                    id, classes, attrs, ..
+                } = tag
+                else {
+                    continue;
+                };
+                let id = match id {
+                    Some(id) => id.to_string(),
+                    None => unique_id(&slugify(&text), &mut used_ids),
+                };
+
+                headings.push((heading_level(level), id.clone(), text));
+                events.push(Event::Start(Tag::Heading {
+                    level,
+                    id: Some(CowStr::from(id.clone())),
+                    classes,
+                    attrs,
+                }));
+                events.extend(inner);
+                if config.heading_anchors {
+                    events.push(Event::InlineHtml(CowStr::from(format!(
+                        "<a class=\"heading-anchor\" href=\"#{}\" aria-hidden=\"true\">¶</a>",
+                        id
+                    ))));
+                }
+                events.push(Event::End(TagEnd::Heading(level)));
+            }
+            event => match heading.as_mut() {
+                Some((_, inner)) => inner.push(event),
+                None => events.push(event),
+            },
+        }
+    }
+
+    if config.emoji {
+        events = emoji::replace_shortcodes(events, &config.emoji_image_url);
+    }
+
+    if config.tasklists {
+        events = task_list_items(events, config.tasklist_style);
+    }
+
+    if config.responsive_images {
+        events = responsive_images(events, site);
+    }
+
+    if config.wikilinks {
+        events = resolve_wikilinks(events, site);
+    }
+
+    if config.footnotes {
+        events = collect_footnotes(events, &config.footnotes_heading);
+    }
+
+    let mut has_mermaid = false;
+    if config.mermaid {
+        (events, has_mermaid) = mermaid_diagrams(events, &config.mermaid_command);
+    }
+
+    let has_copyable_code = config.copy_button
+        && events
+            .iter()
+            .any(|e| matches!(e, Event::Start(Tag::CodeBlock(_))));
+    events = code_blocks(events, config.copy_button);
+
+    let has_math = events
+        .iter()
+        .any(|e| matches!(e, Event::InlineMath(_) | Event::DisplayMath(_)));
+
+    let links = events
+        .iter()
+        .filter_map(|e| match e {
+            Event::Start(Tag::Link { dest_url, .. }) => Some(dest_url.to_string()),
+            _ => None,
+        })
+        .collect();
+
+    let mut html_output = String::new();
+    pulldown_cmark::html::push_html(&mut html_output, events.into_iter());
+
+    // The page title is already the H1, so the contents start one level below
+    let toc_headings: Vec<_> = headings.into_iter().filter(|(l, _, _)| *l > 1).collect();
+
+    RenderedMarkdown {
+        html: html_output,
+        toc: build_toc(&toc_headings),
+        has_math,
+        has_mermaid,
+        has_copyable_code,
+        links,
+    }
+}
+
+/// The events of `markdown` with the extensions `config` enables and
+/// wikilinks pointed at their pages, for outputs other than HTML.
+pub fn events<'a>(
+    markdown: &'a str,
+    config: &MarkdownConfig,
+    site: &RenderContext,
+) -> Vec<Event<'a>> {
+    let mut events = MdParser::new_ext(markdown, parser_options(config))
+        .map(|event| shift_heading(event, config))
+        .collect();
+    // Other formats can't show images, so unknown names stay as written
+    if config.emoji {
+        events = emoji::replace_shortcodes(events, "");
+    }
+    if config.wikilinks {
+        resolve_wikilinks(events, site)
+    } else {
+        events
+    }
+}
+
+/// With `shift_headings`, moves a heading one level down, as far as H6.
+fn shift_heading<'a>(event: Event<'a>, config: &MarkdownConfig) -> Event<'a> {
+    if !config.shift_headings {
+        return event;
+    }
+    let lower = |level| match level {
+        HeadingLevel::H1 => HeadingLevel::H2,
+        HeadingLevel::H2 => HeadingLevel::H3,
+        HeadingLevel::H3 => HeadingLevel::H4,
+        HeadingLevel::H4 => HeadingLevel::H5,
+        HeadingLevel::H5 | HeadingLevel::H6 => HeadingLevel::H6,
+    };
+    match event {
+        Event::Start(Tag::Heading {
+            level,
+            id,
+            classes,
+            attrs,
+        }) => Event::Start(Tag::Heading {
+            level: lower(level),
+            id,
+            classes,
+            attrs,
+        }),
+        Event::End(TagEnd::Heading(level)) => Event::End(TagEnd::Heading(lower(level))),
+        event => event,
+    }
+}
+
+/// `markdown` without its first block if that's an H1 saying the same as
+/// `title`, ignoring case.
+pub fn without_title_heading<'a>(
+    markdown: &'a str,
+    title: &str,
+    config: &MarkdownConfig,
+) -> &'a str {
+    let mut events = MdParser::new_ext(markdown, parser_options(config)).into_offset_iter();
+    let Some((
+        Event::Start(Tag::Heading {
+            level: HeadingLevel::H1,
+            ..
+        }),
+        range,
+    )) = events.next()
+    else {
+        return markdown;
+    };
+    let inner: Vec<Event> = events
+        .map(|(event, _)| event)
+        .take_while(|event| !matches!(event, Event::End(TagEnd::Heading(_))))
+        .collect();
+    if plain_text(&inner).to_lowercase() == title.trim().to_lowercase() {
+        &markdown[range.end..]
+    } else {
+        markdown
+    }
+}
+
+/// Marks up task list items in `style`, with classes saying whether they're
+/// done. The marker comes right after the item's start, or after its first
+/// paragraph's start in loose lists.
+fn task_list_items(events: Vec<Event<'_>>, style: TaskListStyle) -> Vec<Event<'_>> {
+    let mut output = Vec::with_capacity(events.len());
+    let mut items = Vec::new();
+
+    for event in events {
+        match event {
+            Event::Start(Tag::Item) => {
+                items.push(output.len());
+                output.push(event);
+            }
+            Event::End(TagEnd::Item) => {
+                items.pop();
+                output.push(event);
+            }
+            Event::TaskListMarker(checked) => {
+                if let Some(&item) = items.last() {
+                    let class = if checked {
+                        "task-list-item task-list-item-checked"
+                    } else {
+                        "task-list-item"
+                    };
+                    output[item] = Event::Html(CowStr::from(format!("<li class=\"{}\">", class)));
+                }
+                let checked_attribute = if checked { " checked" } else { "" };
+                let marker = match style {
+                    TaskListStyle::Checkbox => format!(
+                        "<input type=\"checkbox\" class=\"task-list-checkbox\" disabled{}> ",
+                        checked_attribute
+                    ),
+                    TaskListStyle::Interactive => format!(
+                        "<input type=\"checkbox\" class=\"task-list-checkbox\"{}> ",
+                        checked_attribute
+                    ),
+                    TaskListStyle::Span => format!(
+                        "<span class=\"task-list-marker\" role=\"checkbox\" aria-checked=\"{}\" aria-readonly=\"true\">{}</span> ",
+                        checked,
+                        if checked { "☑" } else { "☐" }
+                    ),
+                };
+                output.push(Event::InlineHtml(CowStr::from(marker)));
+            }
+            event => output.push(event),
+        }
+    }
+
+    output
+}
+
+/// Moves footnote definitions into a numbered list at the end of the page,
+/// in the order they're first referenced, each with links back to its
+/// references. Definitions nothing refers to are dropped, as are references
+/// to footnotes that aren't defined (as happens in summaries cut short).
+fn collect_footnotes<'a>(events: Vec<Event<'a>>, heading: &str) -> Vec<Event<'a>> {
+    let mut definitions: BTreeMap<String, Vec<Event<'a>>> = BTreeMap::new();
+    let mut body = Vec::with_capacity(events.len());
+    let mut definition: Option<(String, Vec<Event<'a>>)> = None;
+
+    for event in events {
+        match event {
+            Event::Start(Tag::FootnoteDefinition(label)) => {
+                definition = Some((label.to_string(), Vec::new()));
+            }
+            Event::End(TagEnd::FootnoteDefinition) => {
+                if let Some((label, inner)) = definition.take() {
+                    definitions.entry(label).or_insert(inner);
+                }
+            }
+            event => match definition.as_mut() {
+                Some((_, inner)) => inner.push(event),
+                None => body.push(event),
+            },
+        }
+    }
+
+    let mut numbering = FootnoteNumbering {
+        defined: definitions.keys().cloned().collect(),
+        order: Vec::new(),
+        ref_counts: BTreeMap::new(),
+    };
+    let mut output = numbering.number(body);
+    if numbering.order.is_empty() {
+        return output;
+    }
+
+    let mut section = String::from("<section class=\"footnotes\">\n");
+    if !heading.is_empty() {
+        section.push_str(&format!("<h2>{}</h2>\n", escape_html(heading)));
+    }
+    section.push_str("<ol>\n");
+    output.push(Event::Html(CowStr::from(section)));
+
+    // Footnotes can reference other footnotes, so `order` may grow as we go
+    let mut index = 0;
+    while index < numbering.order.len() {
+        let label = numbering.order[index].clone();
+        index += 1;
+
+        let inner = definitions.remove(&label).unwrap_or_default();
+        let mut inner = numbering.number(inner);
+
+        let backrefs: String = (1..=numbering.ref_counts[&label])
+            .map(|n| {
+                format!(
+                    " <a href=\"#{}\" class=\"footnote-backref\" aria-label=\"Back to reference\">↩</a>",
+                    footnote_ref_id(&label, n)
+                )
+            })
+            .collect();
+        // Keep the arrows inside the last paragraph rather than on a line of their own
+        let backrefs = Event::InlineHtml(CowStr::from(backrefs));
+        match inner.last() {
+            Some(Event::End(TagEnd::Paragraph)) => {
+                let end = inner.len() - 1;
+                inner.insert(end, backrefs);
+            }
+            _ => inner.push(backrefs),
+        }
+
+        output.push(Event::Html(CowStr::from(format!(
+            "<li id=\"{}\">\n",
+            footnote_id(&label)
+        ))));
+        output.extend(inner);
+        output.push(Event::Html(CowStr::Borrowed("</li>\n")));
+    }
+
+    output.push(Event::Html(CowStr::Borrowed("</ol>\n</section>\n")));
+    output
+}
+
+struct FootnoteNumbering {
+    defined: HashSet<String>,
+    /// Labels in the order they were first referenced
+    order: Vec<String>,
+    ref_counts: BTreeMap<String, usize>,
+}
+
+impl FootnoteNumbering {
+    /// Replaces the footnote references in `events` with numbered links.
+    fn number<'a>(&mut self, events: Vec<Event<'a>>) -> Vec<Event<'a>> {
+        events
+            .into_iter()
+            .filter_map(|event| match event {
+                Event::FootnoteReference(label) if self.defined.contains(label.as_ref()) => {
+                    let label = label.to_string();
+                    let number = match self.order.iter().position(|l| *l == label) {
+                        Some(i) => i + 1,
+                        None => {
+                            self.order.push(label.clone());
+                            self.order.len()
+                        }
+                    };
+                    let count = self.ref_counts.entry(label.clone()).or_insert(0);
+                    *count += 1;
+                    Some(Event::InlineHtml(CowStr::from(format!(
+                        "<sup class=\"footnote-ref\" id=\"{}\"><a href=\"#{}\">{}</a></sup>",
+                        footnote_ref_id(&label, *count),
+                        footnote_id(&label),
+                        number
+                    ))))
+                }
+                Event::FootnoteReference(_) => None,
+                event => Some(event),
+            })
+            .collect()
+    }
+}
+
+fn footnote_id(label: &str) -> String {
+    format!("fn-{}", slugify(label))
+}
+
+fn footnote_ref_id(label: &str, n: usize) -> String {
+    if n == 1 {
+        format!("fnref-{}", slugify(label))
+    } else {
+        format!("fnref-{}-{}", slugify(label), n)
+    }
+}
+
+/// Opens the wrapper of a code block that gets a copy button
+const COPYABLE_START: &str = "<div class=\"code-copy\" data-copyable>\n";
+
+/// What a fenced code block's info string says besides its language, as
+/// in ```` ```rust,title=main.rs,hl=3-5,linenos ````.
+#[derive(Debug, Default)]
+struct CodeBlockOptions {
+    language: String,
+    title: String,
+    /// Ranges of line numbers to highlight, counting from 1, kept as
+    /// ranges so a huge one costs no more than a small one
+    highlight: Vec<RangeInclusive<usize>>,
+    line_numbers: bool,
+}
+
+impl CodeBlockOptions {
+    /// Reads an info string whose parts are separated by commas or spaces.
+    /// `hl` takes lines and ranges, also separated by commas, like
+    /// `hl=1,3-5`. Returns `None` for info strings with nothing but a
+    /// language, which are left to the usual rendering.
+    fn parse(info: &str) -> Option<Self> {
+        let mut options = CodeBlockOptions::default();
+        let mut has_options = false;
+        let mut in_highlight = false;
+
+        for (i, part) in info
+            .split([',', ' ', '\t'])
+            .filter(|part| !part.is_empty())
+            .enumerate()
+        {
+            let (key, value) = part.split_once('=').unwrap_or((part, ""));
+            match key {
+                _ if i == 0 && value.is_empty() && key != "linenos" => {
+                    options.language = key.to_string();
+                    continue;
+                }
+                "title" => options.title = value.trim_matches('"').to_string(),
+                "hl" => {
+                    options.highlight.extend(line_range(value));
+                    in_highlight = true;
+                    has_options = true;
+                    continue;
+                }
+                "linenos" => options.line_numbers = true,
+                // More lines for the `hl` before
+                _ if in_highlight
+                    && value.is_empty()
+                    && key.starts_with(|c: char| c.is_ascii_digit()) =>
+                {
+                    options.highlight.extend(line_range(key));
+                    continue;
+                }
+                _ => {}
+            }
+            in_highlight = false;
+            has_options = true;
+        }
+
+        has_options.then_some(options)
+    }
+}
+
+/// The lines in `range`, like `3-5` or `7`.
+fn line_range(range: &str) -> Option<RangeInclusive<usize>> {
+    let (start, end) = range.split_once('-').unwrap_or((range, range));
+    match (start.trim().parse::<usize>(), end.trim().parse::<usize>()) {
+        (Ok(start), Ok(end)) if start <= end => Some(start..=end),
+        _ => None,
+    }
+}
+
+/// Renders fenced code blocks whose info string asks for a title, line
+/// numbers or highlighted lines, see `CodeBlockOptions`. Each line becomes a
+/// `<span class="line">`, with its number in `data-line` when numbered and
+/// the `highlighted` class when highlighted, and the whole is captioned with
+/// the title in a `<figure class="code-block">`. With `copyable`, every
+/// code block is wrapped for a copy button, see `MarkdownConfig::copy_button`.
+fn code_blocks(events: Vec<Event<'_>>, copyable: bool) -> Vec<Event<'_>> {
+    let mut output = Vec::with_capacity(events.len());
+    let mut block: Option<(CodeBlockOptions, String)> = None;
+
+    for event in events {
+        match event {
+            Event::Start(Tag::CodeBlock(ref kind)) => {
+                if copyable {
+                    output.push(Event::Html(CowStr::Borrowed(COPYABLE_START)));
+                }
+                let options = match kind {
+                    CodeBlockKind::Fenced(info) => CodeBlockOptions::parse(info),
+                    CodeBlockKind::Indented => None,
+                };
+                match options {
+                    Some(options) => block = Some((options, String::new())),
+                    None => output.push(event),
+                }
+            }
+            Event::Text(text) if block.is_some() => {
+                if let Some((_, code)) = block.as_mut() {
+                    code.push_str(&text);
+                }
+            }
+            Event::End(TagEnd::CodeBlock) => {
+                match block.take() {
+                    Some((options, code)) => {
+                        output.push(Event::Html(CowStr::from(code_block_html(&options, &code))));
+                    }
+                    None => output.push(event),
+                }
+                if copyable {
+                    output.push(Event::Html(CowStr::Borrowed("</div>\n")));
+                }
+            }
+            event => output.push(event),
+        }
+    }
+
+    output
+}
+
+fn code_block_html(options: &CodeBlockOptions, code: &str) -> String {
+    let lines: String = code
+        .strip_suffix('\n')
+        .unwrap_or(code)
+        .split('\n')
+        .enumerate()
+        .map(|(i, line)| {
+            let number = i + 1;
+            let class = if options
+                .highlight
+                .iter()
+                .any(|range| range.contains(&number))
+            {
+                "line highlighted"
+            } else {
+                "line"
+            };
+            let data_line = if options.line_numbers {
+                format!(" data-line=\"{}\"", number)
+            } else {
+                String::new()
+            };
+            format!(
+                "<span class=\"{}\"{}>{}</span>\n",
+                class,
+                data_line,
+                escape_html(line)
+            )
+        })
+        .collect();
+
+    let language = if options.language.is_empty() {
+        String::new()
+    } else {
+        format!(" class=\"language-{}\"", escape_html(&options.language))
+    };
+    let pre_class = if options.line_numbers {
+        " class=\"line-numbers\""
+    } else {
+        ""
+    };
+    let caption = if options.title.is_empty() {
+        String::new()
+    } else {
+        format!(
+            "<figcaption class=\"code-title\">{}</figcaption>\n",
+            escape_html(&options.title)
+        )
+    };
+
+    format!(
+        "<figure class=\"code-block\">\n{}<pre{}><code{}>{}</code></pre>\n</figure>\n",
+        caption, pre_class, language, lines
+    )
+}
+
+/// Replaces ```` ```mermaid ```` code blocks with diagrams: inline SVG when a
+/// build-time `command` is configured and succeeds, otherwise a
+/// `<pre class="mermaid">` for Mermaid's browser script to draw. Returns
+/// whether any diagram was left for the browser.
+fn mermaid_diagrams<'a>(events: Vec<Event<'a>>, command: &[String]) -> (Vec<Event<'a>>, bool) {
+    let mut output = Vec::with_capacity(events.len());
+    let mut diagram: Option<String> = None;
+    let mut needs_script = false;
+
+    for event in events {
+        match event {
+            Event::Start(Tag::CodeBlock(CodeBlockKind::Fenced(ref lang)))
+                if lang.split_whitespace().next() == Some("mermaid") =>
+            {
+                diagram = Some(String::new());
+            }
+            Event::Text(text) if diagram.is_some() => {
+                if let Some(source) = diagram.as_mut() {
+                    source.push_str(&text);
+                }
+            }
+            Event::End(TagEnd::CodeBlock) if diagram.is_some() => {
+                let source = diagram.take().unwrap_or_default();
+                let html = match render_mermaid(&source, command) {
+                    Some(svg) => format!("<figure class=\"mermaid-diagram\">{}</figure>\n", svg),
+                    None => {
+                        needs_script = true;
+                        format!("<pre class=\"mermaid\">{}</pre>\n", escape_html(&source))
+                    }
+                };
+                output.push(Event::Html(CowStr::from(html)));
+            }
+            event => output.push(event),
+        }
+    }
+
+    (output, needs_script)
+}
+
+/// Pipes a diagram through the configured renderer, returning its SVG output.
+/// Failures are reported and leave the diagram to the browser instead.
+fn render_mermaid(source: &str, command: &[String]) -> Option<String> {
+    let (program, args) = command.split_first()?;
+
+    let result = Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .and_then(|mut child| {
+            if let Some(mut stdin) = child.stdin.take() {
+                stdin.write_all(source.as_bytes())?;
+            }
+            child.wait_with_output()
+        });
+
+    match result {
+        Ok(output) if output.status.success() => {
+            Some(String::from_utf8_lossy(&output.stdout).into_owned())
+        }
+        Ok(output) => {
+            println!(
+                "  ⚠ {} failed to render a Mermaid diagram: {}",
+                program,
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+            None
+        }
+        Err(e) => {
+            println!("  ⚠ Could not run {}: {}", program, e);
+            None
+        }
+    }
+}
+
+/// Normalizes a page title or file name for wikilink lookups, so
+/// `[[my first post]]` finds "My First Post".
+pub fn wikilink_key(name: &str) -> String {
+    name.trim().to_lowercase()
+}
+
+/// Points wikilinks at the pages they name. `[[Page#Heading]]` links to the
+/// heading's anchor on that page, `[[#Heading]]` to one on the same page
+/// (spelled out in full, as summaries get shown on other pages).
+/// Links to pages that don't exist become `<span class="wikilink-missing">`.
+fn resolve_wikilinks<'a>(events: Vec<Event<'a>>, site: &RenderContext) -> Vec<Event<'a>> {
+    let mut in_missing_link = false;
+
+    events
+        .into_iter()
+        .map(|event| match event {
+            Event::Start(Tag::Link {
+                link_type: LinkType::WikiLink { .. },
+                dest_url,
+                title,
+                id,
+            }) => {
+                let (page, heading) = match dest_url.split_once('#') {
+                    Some((page, heading)) => (page, Some(slugify(heading))),
+                    None => (dest_url.as_ref(), None),
+                };
+                let url = if page.trim().is_empty() {
+                    Some(site.page_url)
+                } else {
+                    site.wikilinks.get(&wikilink_key(page)).map(String::as_str)
+                }
+                .map(|url| format!("{}{}", site.base_path, url.trim_start_matches('/')));
+
+                match url {
+                    Some(url) => {
+                        let anchor = heading.map(|h| format!("#{}", h)).unwrap_or_default();
+                        Event::Start(Tag::Link {
+                            link_type: LinkType::Inline,
+                            dest_url: CowStr::from(format!("{}{}", url, anchor)),
+                            title,
+                            id,
+                        })
+                    }
+                    None => {
+                        in_missing_link = true;
+                        Event::InlineHtml(CowStr::Borrowed("<span class=\"wikilink-missing\">"))
+                    }
+                }
+            }
+            Event::End(TagEnd::Link) if in_missing_link => {
+                in_missing_link = false;
+                Event::InlineHtml(CowStr::Borrowed("</span>"))
+            }
+            event => event,
+        })
+        .collect()
+}
+
+/// The pulldown-cmark extensions enabled by `config`.
+fn parser_options(config: &MarkdownConfig) -> Options {
+    let mut options = Options::empty();
+    options.set(Options::ENABLE_TABLES, config.tables);
+    options.set(Options::ENABLE_FOOTNOTES, config.footnotes);
+    options.set(Options::ENABLE_STRIKETHROUGH, config.strikethrough);
+    options.set(Options::ENABLE_TASKLISTS, config.tasklists);
+    options.set(
+        Options::ENABLE_HEADING_ATTRIBUTES,
+        config.heading_attributes,
+    );
+    options.set(Options::ENABLE_SMART_PUNCTUATION, config.smart_punctuation);
+    options.set(Options::ENABLE_MATH, config.math);
+    options.set(Options::ENABLE_WIKILINKS, config.wikilinks);
+    options
+}
+
+/// Replaces every markdown image with a lazy-loaded `<img>`; images known to
+/// the image pipeline get their dimensions and are wrapped in a `<picture>`
+/// offering the WebP and narrower variants through `srcset`.
+fn responsive_images<'a>(events: Vec<Event<'a>>, site: &RenderContext) -> Vec<Event<'a>> {
+    let mut output = Vec::with_capacity(events.len());
+    let mut image: Option<(CowStr, CowStr, String)> = None;
+
+    for event in events {
+        match event {
+            Event::Start(Tag::Image {
+                dest_url, title, ..
+            }) => image = Some((dest_url, title, String::new())),
+            Event::End(TagEnd::Image) => {
+                if let Some((src, title, alt)) = image.take() {
+                    output.push(Event::InlineHtml(CowStr::from(picture_markup(
+                        &src, &title, &alt, site,
+                    ))));
+                }
+            }
+            Event::Text(text) | Event::Code(text) if image.is_some() => {
+                if let Some((_, _, alt)) = image.as_mut() {
+                    alt.push_str(&text);
+                }
+            }
+            // Other inline markup in alt text contributes nothing but its text
+            _ if image.is_some() => {}
+            event => output.push(event),
+        }
+    }
+
+    output
+}
+
+fn picture_markup(src: &str, title: &str, alt: &str, site: &RenderContext) -> String {
+    let title_attr = if title.is_empty() {
+        String::new()
+    } else {
+        format!(" title=\"{}\"", escape_html(title))
+    };
+
+    // Only the site's own images are in the manifest, keyed by where they're
+    // published: resolve `src` from the page, under the base path
+    let known = links::normalize_link(src, site.page_url, site.base_path)
+        .filter(|_| !src.contains(':'))
+        .and_then(|url| site.images.get_key_value(url.trim_start_matches('/')));
+
+    let Some((key, info)) = known else {
+        return format!(
+            "<img src=\"{}\" alt=\"{}\"{} loading=\"lazy\" decoding=\"async\">",
+            escape_html(src),
+            escape_html(alt),
+            title_attr
+        );
+    };
+
+    let url = |path: &str| escape_html(&format!("{}{}", site.base_path, path));
+    let sizes = format!("(max-width: {}px) 100vw, {}px", info.width, info.width);
+
+    let mut img_srcset: Vec<String> = info
+        .variants
+        .iter()
+        .map(|v| format!("{} {}w", url(&v.path), v.width))
+        .collect();
+    img_srcset.push(format!("{} {}w", url(key), info.width));
+
+    let webp_source = info
+        .webp
+        .as_ref()
+        .map(|webp| {
+            let mut srcset: Vec<String> = info
+                .variants
+                .iter()
+                .filter_map(|v| v.webp.as_ref().map(|w| format!("{} {}w", url(w), v.width)))
+                .collect();
+            srcset.push(format!("{} {}w", url(webp), info.width));
+            format!(
+                "<source type=\"image/webp\" srcset=\"{}\" sizes=\"{}\">",
+                srcset.join(", "),
+                sizes
+            )
+        })
+        .unwrap_or_default();
+
+    format!(
+        "<picture>{}<img src=\"{}\" srcset=\"{}\" sizes=\"{}\" alt=\"{}\"{} width=\"{}\" height=\"{}\" loading=\"lazy\" decoding=\"async\"></picture>",
+        webp_source,
+        url(key),
+        img_srcset.join(", "),
+        sizes,
+        escape_html(alt),
+        title_attr,
+        info.width,
+        info.height
+    )
+}
+
+/// Marker separating a post's summary from the rest of its body.
+const MORE_MARKER: &str = "<!--more-->";
+
+/// Picks the markdown to use as a post's summary: everything before a
+/// `<!--more-->` marker if there is one, otherwise the first paragraph. The
+/// marker only counts on a line of its own at the top level, not quoted in
+/// code or nested in a list.
+pub fn summary_source(markdown: &str) -> &str {
+    let mut depth = 0;
+    let mut first_paragraph = None;
+    for (event, range) in MdParser::new(markdown).into_offset_iter() {
+        match event {
+            Event::Html(html) if depth == 1 && html.trim() == MORE_MARKER => {
+                return markdown[..range.start].trim();
+            }
+            Event::Start(Tag::Paragraph) if first_paragraph.is_none() => {
+                first_paragraph = Some(markdown[range].trim());
+                depth += 1;
+            }
+            Event::Start(_) => depth += 1,
+            Event::End(_) => depth -= 1,
+            _ => {}
+        }
+    }
+    first_paragraph.unwrap_or_default()
+}
+
+/// Average adult silent reading speed, in words per minute.
+const WORDS_PER_MINUTE: usize = 200;
+
+/// Counts the words in the text of a markdown document, ignoring markup,
+/// link targets and raw HTML.
+pub fn word_count(markdown: &str) -> usize {
+    MdParser::new(markdown)
+        .map(|event| match event {
+            Event::Text(text) | Event::Code(text) => text
+                .split_whitespace()
+                .filter(|word| word.chars().any(char::is_alphanumeric))
+                .count(),
+            _ => 0,
+        })
+        .sum()
+}
+
+/// The text of a markdown document with all markup stripped and whitespace
+/// collapsed, for indexing.
+pub fn plain_text_body(markdown: &str, config: &MarkdownConfig) -> String {
+    let mut text = String::new();
+    for event in MdParser::new_ext(markdown, parser_options(config)) {
+        match event {
+            Event::Text(t) | Event::Code(t) | Event::InlineMath(t) | Event::DisplayMath(t) => {
+                text.push_str(&t)
+            }
+            Event::SoftBreak | Event::HardBreak => text.push(' '),
+            Event::End(
+                TagEnd::Paragraph
+                | TagEnd::Heading(_)
+                | TagEnd::Item
+                | TagEnd::CodeBlock
+                | TagEnd::TableCell
+                | TagEnd::BlockQuote(_),
+            ) => text.push(' '),
+            _ => {}
+        }
+    }
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// The page as plain text for reading, keeping its paragraphs, list items
+/// and code blocks on lines of their own and giving links' destinations
+/// after their text.
+pub fn plain_text_document(
+    markdown: &str,
+    config: &MarkdownConfig,
+    site: &RenderContext,
+) -> String {
+    let mut text = String::new();
+    let mut links = Vec::new();
+    let mut list_depth = 0;
+    for event in events(markdown, config, site) {
+        match event {
+            Event::Text(t) | Event::Code(t) | Event::InlineMath(t) | Event::DisplayMath(t) => {
+                text.push_str(&t)
+            }
+            Event::SoftBreak => text.push(' '),
+            Event::HardBreak => text.push('\n'),
+            Event::Start(Tag::Link { dest_url, .. }) => links.push(dest_url),
+            Event::End(TagEnd::Link) => {
+                if let Some(url) = links.pop() {
+                    text.push_str(&format!(" <{}>", url));
+                }
+            }
+            Event::Start(Tag::List(_)) => {
+                if list_depth > 0 {
+                    text.push('\n');
+                }
+                list_depth += 1;
+            }
+            Event::End(TagEnd::List(_)) => {
+                list_depth -= 1;
+                if list_depth == 0 {
+                    text.push('\n');
+                }
+            }
+            Event::Start(Tag::Item) => {
+                text.push_str(&"  ".repeat(list_depth - 1));
+                text.push_str("- ");
+            }
+            Event::End(TagEnd::Item) if !text.ends_with('\n') => text.push('\n'),
+            Event::End(TagEnd::Paragraph) if list_depth > 0 => {}
+            Event::End(TagEnd::TableCell) => text.push_str(" | "),
+            Event::End(TagEnd::TableHead | TagEnd::TableRow) => text.push('\n'),
+            Event::End(
+                TagEnd::Paragraph
+                | TagEnd::Heading(_)
+                | TagEnd::CodeBlock
+                | TagEnd::BlockQuote(_)
+                | TagEnd::Table,
+            ) => text.push_str("\n\n"),
+            _ => {}
+        }
+    }
+
+    let mut document = String::new();
+    let mut blank_lines = 0;
+    for line in text.lines() {
+        let line = line.trim_end().trim_end_matches(" |");
+        if line.is_empty() {
+            blank_lines += 1;
+            continue;
+        }
+        if !document.is_empty() && blank_lines > 0 {
+            document.push('\n');
+        }
+        blank_lines = 0;
+        document.push_str(line);
+        document.push('\n');
+    }
+    document
+}
+
+/// Estimated reading time in whole minutes, never less than one.
+pub fn reading_time(words: usize) -> usize {
+    words.div_ceil(WORDS_PER_MINUTE).max(1)
+}
+
+fn heading_level(level: HeadingLevel) -> u8 {
+    match level {
+        HeadingLevel::H1 => 1,
+        HeadingLevel::H2 => 2,
+        HeadingLevel::H3 => 3,
+        HeadingLevel::H4 => 4,
+        HeadingLevel::H5 => 5,
+        HeadingLevel::H6 => 6,
+    }
+}
+
+fn plain_text(events: &[Event]) -> String {
+    events
+        .iter()
+        .filter_map(|event| match event {
+            Event::Text(text) | Event::Code(text) => Some(text.as_ref()),
+            _ => None,
+        })
+        .collect::<String>()
+        .trim()
+        .to_string()
+}
+
+/// Turns heading text into a URL fragment: lowercase alphanumerics joined by dashes.
+pub fn slugify(text: &str) -> String {
+    let mut slug = String::new();
+    for c in text.chars().flat_map(char::to_lowercase) {
+        if c.is_alphanumeric() {
+            slug.push(c);
+        } else if (c.is_whitespace() || c == '-' || c == '_') && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    let slug = slug.trim_matches('-').to_string();
+    if slug.is_empty() {
+        "section".to_string()
+    } else {
+        slug
+    }
+}
+
+fn unique_id(slug: &str, used_ids: &mut HashSet<String>) -> String {
+    let mut candidate = slug.to_string();
+    let mut suffix = 0;
+    while used_ids.contains(&candidate) {
+        suffix += 1;
+        candidate = format!("{}-{}", slug, suffix);
+    }
+    used_ids.insert(candidate.clone());
+    candidate
+}
+
+fn build_toc(headings: &[(u8, String, String)]) -> Vec<TocEntry> {
+    let mut entries: Vec<TocEntry> = Vec::new();
+    let mut i = 0;
+    while i < headings.len() {
+        let (level, id, title) = &headings[i];
+        let mut end = i + 1;
+        while end < headings.len() && headings[end].0 > *level {
+            end += 1;
+        }
+        entries.push(TocEntry {
+            id: id.clone(),
+            title: title.clone(),
+            children: build_toc(&headings[i + 1..end]),
+        });
+        i = end;
+    }
+    entries
+}
+
+/// Renders a table of contents as nested `<ul>` lists linking to each heading.
+pub fn toc_to_html(entries: &[TocEntry]) -> String {
+    if entries.is_empty() {
+        return String::new();
+    }
+    format!("<ul class=\"toc\">{}</ul>", toc_items(entries))
+}
+
+fn toc_items(entries: &[TocEntry]) -> String {
+    entries
+        .iter()
+        .map(|entry| {
+            let children = if entry.children.is_empty() {
+                String::new()
+            } else {
+                format!("<ul>{}</ul>", toc_items(&entry.children))
+            };
+            format!(
+                "<li><a href=\"#{}\">{}</a>{}</li>",
+                entry.id,
+                escape_html(&entry.title),
+                children
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::images::ImageVariant;
+
+    fn images() -> BTreeMap<String, ImageInfo> {
+        let info = ImageInfo {
+            width: 800,
+            height: 600,
+            resized: false,
+            webp: None,
+            variants: vec![ImageVariant {
+                width: 400,
+                height: 300,
+                path: "posts/cat-400w.png".to_string(),
+                webp: None,
+            }],
+        };
+        BTreeMap::from([("posts/cat.png".to_string(), info)])
+    }
+
+    fn context<'a>(
+        images: &'a BTreeMap<String, ImageInfo>,
+        wikilinks: &'a BTreeMap<String, String>,
+    ) -> RenderContext<'a> {
+        RenderContext {
+            images,
+            wikilinks,
+            base_path: "/blog/",
+            page_url: "/posts/hello.html",
+        }
+    }
+
+    #[test]
+    fn picture_markup_resolves_the_sites_own_images() {
+        let (images, wikilinks) = (images(), BTreeMap::new());
+        let site = context(&images, &wikilinks);
+        for src in ["cat.png", "/blog/posts/cat.png", "../posts/cat.png"] {
+            let html = picture_markup(src, "", "A cat", &site);
+            assert!(html.starts_with("<picture>"), "{}: {}", src, html);
+            assert!(
+                html.contains("srcset=\"/blog/posts/cat-400w.png 400w, /blog/posts/cat.png 800w\"")
+            );
+        }
+    }
+
+    #[test]
+    fn picture_markup_leaves_other_images_alone() {
+        let (images, wikilinks) = (images(), BTreeMap::new());
+        let site = context(&images, &wikilinks);
+        for src in [
+            "https://cdn.example.com/posts/cat.png",
+            "//cdn.example.com/blog/posts/cat.png",
+            "data:image/png;base64,cG9zdHMvY2F0LnBuZw==",
+            "other/cat.png",
+        ] {
+            let html = picture_markup(src, "", "A cat", &site);
+            assert!(html.starts_with("<img src="), "{}: {}", src, html);
+            assert!(!html.contains("srcset"));
+        }
+    }
+
+    fn render(markdown: &str, config: &MarkdownConfig) -> RenderedMarkdown {
+        let (images, wikilinks) = (BTreeMap::new(), BTreeMap::new());
+        markdown_to_html(markdown, config, &context(&images, &wikilinks))
+    }
+
+    #[test]
+    fn copyable_code_is_flagged_only_when_a_block_gets_a_button() {
+        let config = MarkdownConfig {
+            copy_button: true,
+            ..MarkdownConfig::default()
+        };
+        let rendered = render("```rust\nfn main() {}\n```\n", &config);
+        assert!(rendered.has_copyable_code);
+        assert!(rendered.html.contains("data-copyable"));
+
+        let mention = "Code blocks get a `data-copyable` attribute.\n";
+        assert!(!render(mention, &config).has_copyable_code);
+        let off = MarkdownConfig {
+            copy_button: false,
+            ..MarkdownConfig::default()
+        };
+        assert!(!render("```\ncode\n```\n", &off).has_copyable_code);
+    }
+
+    #[test]
+    fn code_block_options_read_titles_highlights_and_line_numbers() {
+        let options = CodeBlockOptions::parse("rust,title=\"main.rs\",hl=1,3-5 8,linenos").unwrap();
+        assert_eq!(options.language, "rust");
+        assert_eq!(options.title, "main.rs");
+        assert_eq!(options.highlight, [1..=1, 3..=5, 8..=8]);
+        assert!(options.line_numbers);
+        assert!(CodeBlockOptions::parse("rust").is_none());
+    }
+
+    #[test]
+    fn huge_highlight_ranges_cost_nothing() {
+        let options = CodeBlockOptions::parse("hl=1-4000000000").unwrap();
+        assert_eq!(options.highlight, [1..=4_000_000_000]);
+        assert!(
+            CodeBlockOptions::parse("hl=5-2")
+                .unwrap()
+                .highlight
+                .is_empty()
+        );
+
+        let html = render(
+            "```text,hl=2-4000000000\na\nb\n```\n",
+            &MarkdownConfig::default(),
+        )
+        .html;
+        assert!(html.contains("<span class=\"line\">a"));
+        assert!(html.contains("<span class=\"line highlighted\">b"));
+    }
+
+    #[test]
+    fn heading_attributes_are_opt_in() {
+        let markdown = "## Sets {#sets .wide}\n";
+        let html = render(markdown, &MarkdownConfig::default()).html;
+        assert!(html.contains("Sets {#sets .wide}</h2>"), "{}", html);
+
+        let config = MarkdownConfig {
+            heading_attributes: true,
+            ..MarkdownConfig::default()
+        };
+        let html = render(markdown, &config).html;
+        assert!(html.contains("id=\"sets\""), "{}", html);
+        assert!(html.contains("class=\"wide\""), "{}", html);
+        assert!(!html.contains('{'), "{}", html);
+    }
+
+    #[test]
+    fn summary_source_ends_at_a_top_level_more_marker() {
+        let post = "First.\n\nSecond.\n\n<!--more-->\n\nRest.\n";
+        assert_eq!(summary_source(post), "First.\n\nSecond.");
+        assert_eq!(summary_source("First.\n\nSecond.\n"), "First.");
+    }
+
+    #[test]
+    fn summary_source_ignores_more_markers_in_code() {
+        let fenced = "Intro.\n\n```html\n<!--more-->\n```\n\nRest.\n";
+        assert_eq!(summary_source(fenced), "Intro.");
+        let inline = "Write `<!--more-->` to end the summary.\n\nRest.\n";
+        assert_eq!(
+            summary_source(inline),
+            "Write `<!--more-->` to end the summary."
+        );
+        let indented = "Intro.\n\n    <!--more-->\n\nRest.\n";
+        assert_eq!(summary_source(indented), "Intro.");
+    }
+
+    #[test]
+    fn footnotes_are_numbered_by_first_reference_with_backrefs() {
+        let config = MarkdownConfig {
+            footnotes_heading: "Notes".to_string(),
+            ..MarkdownConfig::default()
+        };
+        let markdown =
+            "B[^b], A[^a] and B again[^b].\n\n[^a]: Alpha.\n[^b]: Beta.\n[^unused]: Gone.\n";
+        let html = render(markdown, &config).html;
+
+        assert!(
+            html.contains(
+                "B<sup class=\"footnote-ref\" id=\"fnref-b\"><a href=\"#fn-b\">1</a></sup>"
+            )
+        );
+        assert!(html.contains("id=\"fnref-a\"><a href=\"#fn-a\">2</a>"));
+        assert!(html.contains("id=\"fnref-b-2\"><a href=\"#fn-b\">1</a>"));
+        assert!(html.contains("<section class=\"footnotes\">\n<h2>Notes</h2>\n<ol>"));
+        let (b, a) = (
+            html.find("<li id=\"fn-b\">").unwrap(),
+            html.find("<li id=\"fn-a\">").unwrap(),
+        );
+        assert!(b < a);
+        assert!(html.contains(concat!(
+            "Beta. <a href=\"#fnref-b\" class=\"footnote-backref\" aria-label=\"Back to reference\">↩</a>",
+            " <a href=\"#fnref-b-2\" class=\"footnote-backref\" aria-label=\"Back to reference\">↩</a></p>"
+        )));
+        assert!(!html.contains("Gone"));
+    }
+}
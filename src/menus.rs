@@ -0,0 +1,116 @@
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::io;
+use std::path::PathBuf;
+
+use crate::{Site, page_location, read_page};
+
+/// A link in one of the site's menus, as templates see it.
+#[derive(Serialize, Debug, Clone)]
+pub struct MenuItem {
+    #[serde(serialize_with = "crate::escaped")]
+    pub name: String,
+    pub url: String,
+    pub weight: i32,
+    /// Whether the item links to the page being rendered
+    pub active: bool,
+}
+
+/// An item of the menu called `menu`. Items from the config show up in
+/// every language, those from pages only in the page's own.
+#[derive(Debug, Clone)]
+pub struct MenuEntry {
+    menu: String,
+    lang: Option<String>,
+    item: MenuItem,
+}
+
+/// Gathers the `[[menu.<name>]]` entries of the config, followed by the
+/// pages listing a menu in their `menu:` frontmatter.
+pub fn collect(md_files: &[PathBuf], site: &Site) -> io::Result<Vec<MenuEntry>> {
+    let mut entries: Vec<MenuEntry> = site
+        .config
+        .menu
+        .iter()
+        .flat_map(|(menu, items)| {
+            items.iter().map(|item| MenuEntry {
+                menu: menu.clone(),
+                lang: None,
+                item: MenuItem {
+                    name: item.name.clone(),
+                    url: item.url.clone(),
+                    weight: item.weight,
+                    active: false,
+                },
+            })
+        })
+        .collect();
+
+    for md_file in md_files {
+        let (frontmatter, _) = read_page(md_file, site.plugins)?;
+        if frontmatter.menus.is_empty() {
+            continue;
+        }
+        let location = page_location(
+            md_file,
+            &frontmatter,
+            site.content_dir(md_file),
+            site.config,
+        );
+        let name = frontmatter
+            .menu_name
+            .or(frontmatter.title)
+            .unwrap_or_else(|| "Untitled".to_string());
+        for menu in frontmatter.menus {
+            entries.push(MenuEntry {
+                menu,
+                lang: Some(location.lang.clone()),
+                item: MenuItem {
+                    name: name.clone(),
+                    url: location.url.clone(),
+                    weight: frontmatter.menu_weight.unwrap_or(0),
+                    active: false,
+                },
+            });
+        }
+    }
+
+    Ok(entries)
+}
+
+/// The menus for a page at `page_url` in `lang`, by name, each sorted by
+/// weight with the page's own item marked active. `main` is always there,
+/// empty if nothing was put in it, so templates can test for it.
+pub fn for_page(
+    entries: &[MenuEntry],
+    lang: &str,
+    page_url: &str,
+) -> BTreeMap<String, Vec<MenuItem>> {
+    let mut menus: BTreeMap<String, Vec<MenuItem>> = BTreeMap::new();
+    menus.insert("main".to_string(), Vec::new());
+
+    for entry in entries {
+        if entry.lang.as_deref().is_some_and(|l| l != lang) {
+            continue;
+        }
+        let mut item = entry.item.clone();
+        item.active = same_page(&item.url, page_url);
+        menus.entry(entry.menu.clone()).or_default().push(item);
+    }
+
+    for items in menus.values_mut() {
+        items.sort_by_key(|item| item.weight);
+    }
+    menus
+}
+
+/// Whether two site-relative URLs point at the same page, treating `/dir/`
+/// and `/dir/index.html` as one.
+fn same_page(a: &str, b: &str) -> bool {
+    let normalize = |url: &str| {
+        let url = url.split(['#', '?']).next().unwrap_or_default();
+        let url = url.strip_suffix("index.html").unwrap_or(url);
+        format!("/{}", url.trim_start_matches('/'))
+    };
+    normalize(a) == normalize(b)
+}
@@ -0,0 +1,94 @@
+/// Elements whose contents are whitespace-sensitive and copied through untouched.
+const RAW_ELEMENTS: [&str; 4] = ["pre", "textarea", "script", "style"];
+
+/// Shrinks rendered HTML by stripping comments and collapsing whitespace runs
+/// to a single space. The contents of `<pre>`, `<textarea>`, `<script>` and
+/// `<style>` are left as they are, as are conditional comments.
+pub fn minify_html(html: &str) -> String {
+    let mut output = String::with_capacity(html.len());
+    let mut rest = html;
+
+    while let Some(c) = rest.chars().next() {
+        if rest.starts_with("<!--") && !rest.starts_with("<!--[if") {
+            rest = match rest.find("-->") {
+                Some(end) => &rest[end + 3..],
+                None => "",
+            };
+            continue;
+        }
+
+        if c == '<'
+            && let Some(element) = raw_element_at(rest)
+        {
+            let closing = format!("</{}", element);
+            let end = find_ignore_case(rest, &closing).unwrap_or(rest.len());
+            output.push_str(&rest[..end]);
+            rest = &rest[end..];
+            // Emit the closing tag itself so we don't match the element again
+            if let Some(close) = rest.find('>') {
+                output.push_str(&rest[..=close]);
+                rest = &rest[close + 1..];
+            }
+            continue;
+        }
+
+        if c.is_whitespace() {
+            let end = rest
+                .find(|ch: char| !ch.is_whitespace())
+                .unwrap_or(rest.len());
+            if !output.is_empty() && !output.ends_with(' ') && end < rest.len() {
+                output.push(' ');
+            }
+            rest = &rest[end..];
+            continue;
+        }
+
+        output.push(c);
+        rest = &rest[c.len_utf8()..];
+    }
+
+    output
+}
+
+/// Returns the name of the raw element opened at the start of `html`, if any.
+fn raw_element_at(html: &str) -> Option<&'static str> {
+    RAW_ELEMENTS.into_iter().find(|element| {
+        let tag = &html[1..];
+        tag.len() > element.len()
+            && tag.is_char_boundary(element.len())
+            && tag[..element.len()].eq_ignore_ascii_case(element)
+            && tag[element.len()..].starts_with(|c: char| c == '>' || c.is_whitespace())
+    })
+}
+
+fn find_ignore_case(haystack: &str, needle: &str) -> Option<usize> {
+    haystack
+        .to_ascii_lowercase()
+        .find(&needle.to_ascii_lowercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn minify_html_collapses_whitespace_and_drops_comments() {
+        let html = "<p>  a \n\n b </p>\n<!-- note -->\n<p>c</p>\n";
+        assert_eq!(minify_html(html), "<p> a b </p> <p>c</p>");
+        let conditional = "<!--[if IE]><p>old</p><![endif]-->";
+        assert_eq!(minify_html(conditional), conditional);
+    }
+
+    #[test]
+    fn minify_html_keeps_raw_elements_as_they_are() {
+        let html = "<PRE class=\"code\">a\n  <!-- b --></PRE>\n\n<script>if (a  <  b) {}</script>";
+        assert_eq!(
+            minify_html(html),
+            "<PRE class=\"code\">a\n  <!-- b --></PRE> <script>if (a  <  b) {}</script>"
+        );
+        assert_eq!(
+            minify_html("<preview>  a</preview>"),
+            "<preview> a</preview>"
+        );
+    }
+}
@@ -0,0 +1,105 @@
+use serde_json::Value;
+
+/// Turns a Jupyter notebook into markdown for the usual renderer: markdown
+/// cells as they are, code cells as fenced blocks in the notebook's language,
+/// and their outputs after them, with images inlined as data URLs and HTML
+/// passed through. A first cell starting with `---` holds the page's
+/// frontmatter.
+pub fn to_markdown(source: &str) -> Result<String, String> {
+    let notebook: Value = serde_json::from_str(source).map_err(|e| e.to_string())?;
+    let Some(cells) = notebook["cells"].as_array() else {
+        return Err("not a notebook: no cells".to_string());
+    };
+    let language = notebook["metadata"]["language_info"]["name"]
+        .as_str()
+        .or_else(|| notebook["metadata"]["kernelspec"]["language"].as_str())
+        .unwrap_or("python");
+
+    let mut blocks = Vec::new();
+    for cell in cells {
+        let source = text(&cell["source"]);
+        match cell["cell_type"].as_str() {
+            Some("markdown") | Some("raw") => blocks.push(source),
+            Some("code") => {
+                if !source.trim().is_empty() {
+                    blocks.push(fenced(&source, language));
+                }
+                for output in cell["outputs"].as_array().into_iter().flatten() {
+                    blocks.extend(render_output(output));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Ok(blocks.join("\n\n"))
+}
+
+/// One output of a code cell, in the richest form markdown can show.
+fn render_output(output: &Value) -> Option<String> {
+    match output["output_type"].as_str()? {
+        "stream" => Some(fenced(&text(&output["text"]), "text")),
+        "error" => {
+            let message = format!(
+                "{}: {}",
+                output["ename"].as_str().unwrap_or("Error"),
+                output["evalue"].as_str().unwrap_or_default()
+            );
+            Some(fenced(&message, "text"))
+        }
+        "execute_result" | "display_data" => {
+            let data = &output["data"];
+            for mime in ["image/png", "image/jpeg", "image/gif"] {
+                if let Some(image) = data.get(mime) {
+                    let base64: String = text(image).split_whitespace().collect();
+                    return Some(format!("![output](data:{};base64,{})", mime, base64));
+                }
+            }
+            if let Some(html) = data.get("image/svg+xml").or_else(|| data.get("text/html")) {
+                return Some(raw_html(&text(html)));
+            }
+            if let Some(markdown) = data.get("text/markdown") {
+                return Some(text(markdown));
+            }
+            data.get("text/plain")
+                .map(|plain| fenced(&text(plain), "text"))
+        }
+        _ => None,
+    }
+}
+
+/// Notebook text fields are either a string or a list of lines.
+fn text(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.clone(),
+        Value::Array(lines) => lines.iter().filter_map(Value::as_str).collect(),
+        _ => String::new(),
+    }
+}
+
+/// A fenced code block, with a fence longer than any run of backticks in
+/// `code`.
+fn fenced(code: &str, language: &str) -> String {
+    let longest_run = code.split(|c| c != '`').map(str::len).max().unwrap_or(0);
+    let fence = "`".repeat(longest_run.max(2) + 1);
+    format!(
+        "{}{}\n{}\n{}",
+        fence,
+        language,
+        code.trim_end_matches('\n'),
+        fence
+    )
+}
+
+/// HTML output in a block of its own. Blank lines would end the HTML block
+/// and send the rest through markdown, so they're dropped.
+fn raw_html(html: &str) -> String {
+    let lines: Vec<&str> = html
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .collect();
+    format!(
+        "<div class=\"notebook-output\">\n{}\n</div>",
+        lines.join("\n")
+    )
+}
@@ -0,0 +1,88 @@
+use std::io;
+use std::path::Path;
+
+use crate::Frontmatter;
+
+/// An extension to the build pipeline, for programs embedding bread as a
+/// library. Every hook does nothing by default, so a plugin only implements
+/// the stages it cares about. An error from any hook fails the build.
+pub trait Plugin {
+    /// Shown in the build output when the plugin is registered.
+    fn name(&self) -> &str;
+
+    /// Called once a content file's frontmatter has been parsed, with the
+    /// markdown that follows it. Pages are read more than once during a
+    /// build (to collect posts, links and so on), so this should give the
+    /// same result each time.
+    fn after_frontmatter(
+        &self,
+        _path: &Path,
+        _frontmatter: &mut Frontmatter,
+        _markdown: &mut String,
+    ) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// Called with a page's markdown rendered to HTML, before it goes into
+    /// the page template.
+    fn after_markdown(&self, _path: &Path, _html: &mut String) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// Called with every complete HTML page just before it is written to
+    /// `path` in the output directory.
+    fn before_write(&self, _path: &Path, _html: &mut String) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// Called once the whole site has been written to `output_dir`.
+    fn after_build(&self, _output_dir: &Path) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// The plugins a build runs, in the order they were registered.
+#[derive(Default)]
+pub struct Plugins {
+    plugins: Vec<Box<dyn Plugin>>,
+}
+
+impl Plugins {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn register(&mut self, plugin: impl Plugin + 'static) -> &mut Self {
+        self.plugins.push(Box::new(plugin));
+        self
+    }
+
+    pub(crate) fn iter(&self) -> impl Iterator<Item = &dyn Plugin> {
+        self.plugins.iter().map(|plugin| plugin.as_ref())
+    }
+
+    pub(crate) fn after_frontmatter(
+        &self,
+        path: &Path,
+        frontmatter: &mut Frontmatter,
+        markdown: &mut String,
+    ) -> io::Result<()> {
+        self.iter()
+            .try_for_each(|plugin| plugin.after_frontmatter(path, frontmatter, markdown))
+    }
+
+    pub(crate) fn after_markdown(&self, path: &Path, html: &mut String) -> io::Result<()> {
+        self.iter()
+            .try_for_each(|plugin| plugin.after_markdown(path, html))
+    }
+
+    pub(crate) fn before_write(&self, path: &Path, html: &mut String) -> io::Result<()> {
+        self.iter()
+            .try_for_each(|plugin| plugin.before_write(path, html))
+    }
+
+    pub(crate) fn after_build(&self, output_dir: &Path) -> io::Result<()> {
+        self.iter()
+            .try_for_each(|plugin| plugin.after_build(output_dir))
+    }
+}
@@ -0,0 +1,125 @@
+use std::io::{self, Write};
+use std::process::{Command, Stdio};
+
+use crate::escape_html;
+
+/// Where the script unlocking protected pages is published
+pub const DECRYPT_SCRIPT: &str = "decrypt.js";
+
+pub const DECRYPT_JS: &str = include_str!("decrypt.js");
+
+/// Put in front of the content before it's encrypted, so the script can
+/// tell a wrong password from a right one
+const MARKER: &str = "<!--bread-protected-->";
+
+/// Encrypts a page's rendered content with `password` and returns the
+/// markup standing in for it: a password form that `DECRYPT_SCRIPT` uses to
+/// decrypt it in the browser.
+///
+/// The content is encrypted by `openssl enc`, as salted AES-256-CBC with the
+/// key derived by `iterations` rounds of PBKDF2-SHA256, which the browser's
+/// Web Crypto API can undo.
+pub fn protect(html: &str, password: &str, iterations: u32) -> io::Result<String> {
+    let ciphertext = encrypt(&format!("{}{}", MARKER, html), password, iterations)?;
+    Ok(format!(
+        "<div class=\"protected\" data-protected=\"{}\" data-iterations=\"{}\">\n  \
+         <form class=\"protected-form\">\n    \
+         <p>This post is password protected.</p>\n    \
+         <input type=\"password\" aria-label=\"Password\" placeholder=\"Password\" required>\n    \
+         <button type=\"submit\">Unlock</button>\n    \
+         <p class=\"protected-error\" hidden>That password didn't work.</p>\n  \
+         </form>\n  \
+         <noscript><p>Unlocking this post needs JavaScript.</p></noscript>\n\
+         </div>\n",
+        escape_html(&ciphertext),
+        iterations
+    ))
+}
+
+/// `plaintext` encrypted with `password`, base64-encoded.
+fn encrypt(plaintext: &str, password: &str, iterations: u32) -> io::Result<String> {
+    let iterations = iterations.to_string();
+    // The password goes through the environment, which other users can't
+    // read like they can the command line
+    let mut child = Command::new("openssl")
+        .args(["enc", "-aes-256-cbc", "-pbkdf2", "-md", "sha256", "-salt"])
+        .args([
+            "-iter",
+            &iterations,
+            "-pass",
+            "env:BREAD_PASSWORD",
+            "-base64",
+            "-A",
+        ])
+        .env("BREAD_PASSWORD", password)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| io::Error::new(e.kind(), format!("could not run openssl: {}", e)))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(plaintext.as_bytes())?;
+    }
+    let output = child.wait_with_output()?;
+
+    if !output.status.success() {
+        return Err(io::Error::other(format!(
+            "openssl failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `ciphertext` decrypted the way `decrypt.js` does, with `openssl enc -d`.
+    fn decrypt(ciphertext: &str, password: &str, iterations: u32) -> String {
+        let mut child = Command::new("openssl")
+            .args(["enc", "-d", "-aes-256-cbc", "-pbkdf2", "-md", "sha256"])
+            .args([
+                "-iter",
+                &iterations.to_string(),
+                "-pass",
+                "env:BREAD_PASSWORD",
+            ])
+            .args(["-base64", "-A"])
+            .env("BREAD_PASSWORD", password)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .unwrap();
+        child
+            .stdin
+            .take()
+            .unwrap()
+            .write_all(ciphertext.as_bytes())
+            .unwrap();
+        String::from_utf8_lossy(&child.wait_with_output().unwrap().stdout).into_owned()
+    }
+
+    #[test]
+    fn protect_replaces_content_with_what_the_password_decrypts() {
+        let html = "<p>Only for friends.</p>";
+        let protected = protect(html, "sourdough", 1000).unwrap();
+        assert!(!protected.contains("friends"));
+        assert!(protected.contains("data-iterations=\"1000\""));
+
+        let ciphertext = protected
+            .split_once("data-protected=\"")
+            .and_then(|(_, rest)| rest.split_once('"'))
+            .unwrap()
+            .0;
+        assert_eq!(
+            decrypt(ciphertext, "sourdough", 1000),
+            format!("{}{}", MARKER, html)
+        );
+        assert_ne!(
+            decrypt(ciphertext, "rye", 1000),
+            format!("{}{}", MARKER, html)
+        );
+    }
+}
@@ -0,0 +1,43 @@
+use crate::ignore::IgnoreRules;
+
+/// Whether `relative`, a path in the output directory, or any directory it's
+/// in matches `protect`.
+pub fn is_protected(relative: &str, protect: &IgnoreRules) -> bool {
+    relative
+        .match_indices('/')
+        .any(|(i, _)| protect.is_ignored(&relative[..i], true))
+        || protect.is_ignored(relative, false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn protect(patterns: &[&str]) -> IgnoreRules {
+        IgnoreRules::new(&patterns.iter().map(|p| p.to_string()).collect::<Vec<_>>())
+    }
+
+    #[test]
+    fn protects_files_matching_by_name() {
+        let protect = protect(&["CNAME", "*.pdf"]);
+        assert!(is_protected("CNAME", &protect));
+        assert!(is_protected("docs/paper.pdf", &protect));
+        assert!(!is_protected("index.html", &protect));
+    }
+
+    #[test]
+    fn protects_everything_in_a_protected_directory() {
+        let protect = protect(&[".git", "uploads/"]);
+        assert!(is_protected(".git/HEAD", &protect));
+        assert!(is_protected(".git/refs/heads/main", &protect));
+        assert!(is_protected("uploads/2024/photo.jpg", &protect));
+        assert!(!is_protected("uploads", &protect));
+    }
+
+    #[test]
+    fn protects_paths_matching_anchored_globs() {
+        let protect = protect(&["/files/*.zip"]);
+        assert!(is_protected("files/release.zip", &protect));
+        assert!(!is_protected("other/files/release.zip", &protect));
+    }
+}
@@ -0,0 +1,113 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use chrono::{DateTime, Utc};
+
+use crate::dates;
+
+/// Takes the draft at `path` live: sets `draft: false` and `date:` to `date`
+/// or, without one, today, keeping the rest of the file as it is. With
+/// `rename`, the file also gets the date in front of its name, like
+/// `2024-03-05-hello.md`, and a `slug:` keeping its URL what it was. Returns
+/// where the file is now.
+pub fn publish(path: &Path, date: Option<&str>, rename: bool) -> io::Result<PathBuf> {
+    let date = match date {
+        Some(date) if dates::parse(date).is_none() => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "can't read date '{}', expected YYYY-MM-DD with an optional time",
+                    date
+                ),
+            ));
+        }
+        Some(date) => date.to_string(),
+        None => now().format("%Y-%m-%d").to_string(),
+    };
+    let content = fs::read_to_string(path)?;
+
+    let mut settings = vec![("draft", "false".to_string()), ("date", date.clone())];
+    let stem = path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or_default()
+        .to_string();
+    let day = date.get(..10).unwrap_or(&date);
+    let renamed = (rename && !stem.starts_with(day)).then(|| {
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        path.with_file_name(format!("{}-{}", day, name))
+    });
+    if renamed.is_some() && frontmatter_value(&content, "slug").is_none() {
+        settings.push(("slug", stem));
+    }
+
+    fs::write(path, set_frontmatter(&content, &settings))?;
+    let published = match renamed {
+        Some(renamed) => {
+            fs::rename(path, &renamed)?;
+            renamed
+        }
+        None => path.to_path_buf(),
+    };
+    println!("📰 Published {} on {}", published.display(), date);
+    Ok(published)
+}
+
+/// The current time, for `publish_date`s in the past and the date of
+/// freshly published posts.
+pub fn now() -> DateTime<Utc> {
+    let seconds = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs());
+    DateTime::from_timestamp(seconds as i64, 0).unwrap_or_default()
+}
+
+/// The value of `key` in the frontmatter at the top of `content`, if it's
+/// there.
+fn frontmatter_value<'a>(content: &'a str, key: &str) -> Option<&'a str> {
+    let (frontmatter, _) = split_frontmatter(content)?;
+    frontmatter.lines().find_map(|line| {
+        line.split_once(':')
+            .filter(|(name, _)| name.trim() == key)
+            .map(|(_, value)| value.trim())
+    })
+}
+
+/// The frontmatter of `content`, between its `---` lines, and the rest.
+fn split_frontmatter(content: &str) -> Option<(&str, &str)> {
+    let rest = content.strip_prefix("---")?;
+    let end = rest.find("\n---")?;
+    Some((&rest[..end], &rest[end + 1..]))
+}
+
+/// `content` with each of `settings` changed in its frontmatter, or added at
+/// the end of it; files without frontmatter get some.
+fn set_frontmatter(content: &str, settings: &[(&str, String)]) -> String {
+    let (frontmatter, body) = split_frontmatter(content).unwrap_or(("\n", content));
+    let mut lines: Vec<String> = frontmatter.lines().map(String::from).collect();
+    for (key, value) in settings {
+        let setting = format!("{}: {}", key, value);
+        let existing = lines.iter().position(|line| {
+            !line.starts_with(char::is_whitespace)
+                && line
+                    .split_once(':')
+                    .is_some_and(|(name, _)| name.trim() == *key)
+        });
+        match existing {
+            Some(index) => lines[index] = setting,
+            None => lines.push(setting),
+        }
+    }
+
+    let body = if split_frontmatter(content).is_some() {
+        body.to_string()
+    } else {
+        format!("---\n\n{}", body)
+    };
+    format!("---{}\n{}", lines.join("\n"), body)
+}
@@ -0,0 +1,167 @@
+/// Attributes holding a single URL
+const URL_ATTRIBUTES: [&str; 4] = ["href", "src", "poster", "action"];
+
+/// Rewrites the links in `html` that point into the site, those starting
+/// with `base_path`, as relative to the page at `page` (its path in the
+/// output directory, like `posts/hello.html`). Links to directories get
+/// their `index.html` spelled out, as there's no server to add it when the
+/// site is opened from disk. Full URLs, like canonical links, are left alone.
+pub fn rewrite(html: &str, page: &str, base_path: &str) -> String {
+    let mut output = String::with_capacity(html.len());
+    let mut rest = html;
+
+    while let Some(start) = rest.find('<') {
+        output.push_str(&rest[..start]);
+        let Some(len) = rest[start..].find('>') else {
+            rest = &rest[start..];
+            break;
+        };
+        let mut tag = rest[start..=start + len].to_string();
+        rest = &rest[start + len + 1..];
+
+        for attr in URL_ATTRIBUTES {
+            tag = rewrite_attribute(&tag, attr, |url| relative_link(url, page, base_path));
+        }
+        tag = rewrite_attribute(&tag, "srcset", |srcset| {
+            srcset
+                .split(',')
+                .map(|candidate| {
+                    let candidate = candidate.trim();
+                    let (url, descriptor) = candidate.split_once(' ').unwrap_or((candidate, ""));
+                    let url = relative_link(url, page, base_path);
+                    if descriptor.is_empty() {
+                        url
+                    } else {
+                        format!("{} {}", url, descriptor)
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join(", ")
+        });
+        output.push_str(&tag);
+    }
+
+    output.push_str(rest);
+    output
+}
+
+/// `tag` with the quoted values of its `attr` attributes passed through
+/// `rewrite`.
+fn rewrite_attribute(tag: &str, attr: &str, rewrite: impl Fn(&str) -> String) -> String {
+    let needle = format!("{}=", attr);
+    let mut output = String::with_capacity(tag.len());
+    let mut rest = tag;
+
+    while let Some(pos) = rest.find(&needle) {
+        let value_start = pos + needle.len();
+        output.push_str(&rest[..value_start]);
+        rest = &rest[value_start..];
+
+        // A whole attribute name, not e.g. `data-href=`
+        let whole_name = output[..output.len() - needle.len()]
+            .chars()
+            .next_back()
+            .is_some_and(|c| c.is_whitespace());
+        let Some(quote) = rest.chars().next().filter(|c| *c == '"' || *c == '\'') else {
+            continue;
+        };
+        let Some(len) = rest[1..].find(quote) else {
+            break;
+        };
+        let value = &rest[1..1 + len];
+        output.push(quote);
+        if whole_name {
+            output.push_str(&rewrite(value));
+        } else {
+            output.push_str(value);
+        }
+        output.push(quote);
+        rest = &rest[len + 2..];
+    }
+
+    output.push_str(rest);
+    output
+}
+
+/// `url` relative to the page at `page` if it's a link into the site, or
+/// else as it is.
+pub fn relative_link(url: &str, page: &str, base_path: &str) -> String {
+    if url.starts_with("//") {
+        return url.to_string();
+    }
+    let Some(target) = url.strip_prefix(base_path) else {
+        return url.to_string();
+    };
+    let split = target.find(['#', '?']).unwrap_or(target.len());
+    let (path, suffix) = target.split_at(split);
+    let path = path.trim_start_matches('/');
+
+    let mut target: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+    if path.is_empty() || path.ends_with('/') {
+        target.push("index.html");
+    }
+    let mut dir: Vec<&str> = page.split('/').filter(|s| !s.is_empty()).collect();
+    dir.pop();
+
+    // Up from the page's directory to where the two paths part, then down
+    let common = dir
+        .iter()
+        .zip(&target)
+        .take_while(|(a, b)| a == b)
+        .count()
+        .min(target.len() - 1);
+    let mut relative = "../".repeat(dir.len() - common);
+    relative.push_str(&target[common..].join("/"));
+    format!("{}{}", relative, suffix)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn relative_link_walks_from_the_page_to_the_target() {
+        let link = |url| relative_link(url, "posts/a.html", "/blog/");
+        assert_eq!(link("/blog/posts/b.html"), "b.html");
+        assert_eq!(link("/blog/about.html"), "../about.html");
+        assert_eq!(link("/blog/"), "../index.html");
+        assert_eq!(link("/blog/posts/"), "index.html");
+        assert_eq!(
+            link("/blog/tags/rust/rss.xml?v=1"),
+            "../tags/rust/rss.xml?v=1"
+        );
+        assert_eq!(
+            relative_link("/about/#team", "index.html", "/"),
+            "about/index.html#team"
+        );
+    }
+
+    #[test]
+    fn relative_link_leaves_links_out_of_the_site_alone() {
+        for url in [
+            "https://example.com/blog/x.html",
+            "//cdn.example.com/blog/x.js",
+            "/other/x.html",
+            "#top",
+            "mailto:me@example.com",
+        ] {
+            assert_eq!(relative_link(url, "posts/a.html", "/blog/"), url);
+        }
+        assert_eq!(relative_link("//cdn/x.js", "a.html", "/"), "//cdn/x.js");
+    }
+
+    #[test]
+    fn rewrite_only_touches_url_attributes() {
+        let html = concat!(
+            "<a href=\"/blog/b.html\" data-href=\"/blog/c.html\">/blog/d.html</a>",
+            "<img src='/blog/cat.png' srcset=\"/blog/cat-400w.png 400w, /blog/cat.png 800w\">",
+        );
+        assert_eq!(
+            rewrite(html, "posts/a.html", "/blog/"),
+            concat!(
+                "<a href=\"../b.html\" data-href=\"/blog/c.html\">/blog/d.html</a>",
+                "<img src='../cat.png' srcset=\"../cat-400w.png 400w, ../cat.png 800w\">",
+            )
+        );
+    }
+}
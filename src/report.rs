@@ -0,0 +1,204 @@
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::Duration;
+
+use crate::config::Config;
+use crate::ignore::IgnoreRules;
+use crate::markdown::{reading_time, word_count};
+use crate::walk::Walk;
+use crate::{Plugins, content_root, dates, find_markdown_files, is_listed_post, read_page};
+
+/// How many of the slowest pages the report lists
+const SLOWEST_PAGES: usize = 5;
+
+/// How many of the longest posts `bread stats` lists
+const LONGEST_POSTS: usize = 5;
+
+/// What a build did, printed at the end of `build_site` and written as JSON
+/// when the config asks for a `report`.
+#[derive(Serialize, Debug, Default)]
+pub struct BuildReport {
+    pub pages: usize,
+    pub posts: usize,
+    pub words: usize,
+    /// Posts per tag
+    pub tags: BTreeMap<String, usize>,
+    /// The pages that took longest to render, slowest first
+    pub slowest_pages: Vec<PageTiming>,
+    /// Size of the HTML written
+    pub bytes_written: u64,
+    pub duration_ms: u128,
+    /// The pages that failed to render and why
+    pub failures: Vec<PageFailure>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct PageTiming {
+    pub path: String,
+    pub duration_ms: f64,
+}
+
+#[derive(Serialize, Debug)]
+pub struct PageFailure {
+    pub path: String,
+    pub error: String,
+}
+
+impl BuildReport {
+    /// Counts a rendered page that took `duration`.
+    pub fn add_page(&mut self, path: &Path, words: usize, duration: Duration) {
+        self.pages += 1;
+        self.words += words;
+        self.slowest_pages.push(PageTiming {
+            path: path.display().to_string(),
+            duration_ms: duration.as_secs_f64() * 1000.0,
+        });
+        self.slowest_pages
+            .sort_by(|a, b| b.duration_ms.total_cmp(&a.duration_ms));
+        self.slowest_pages.truncate(SLOWEST_PAGES);
+    }
+
+    /// Records a page that failed to render, to be listed at the end of the
+    /// build.
+    pub fn add_failure(&mut self, path: &Path, error: &io::Error) -> &PageFailure {
+        let path = path.display().to_string();
+        let error = error.to_string();
+        // Most errors already start with the path they're about
+        let error = match error.strip_prefix(&format!("{}: ", path)) {
+            Some(rest) => rest.to_string(),
+            None => error,
+        };
+        self.failures.push(PageFailure { path, error });
+        &self.failures[self.failures.len() - 1]
+    }
+
+    /// Lists the pages that failed to render.
+    pub fn print_failures(&self) {
+        println!("\n❌ {} page(s) failed to render:\n", self.failures.len());
+        for failure in &self.failures {
+            println!("  {}: {}", failure.path, failure.error);
+        }
+    }
+
+    pub fn print(&self) {
+        println!("\n📊 Build report\n");
+        println!("  Pages:   {} ({} posts)", self.pages, self.posts);
+        println!("  Words:   {}", self.words);
+        println!("  Written: {}", format_bytes(self.bytes_written));
+        println!("  Took:    {:.2}s", self.duration_ms as f64 / 1000.0);
+        if !self.tags.is_empty() {
+            println!("  Tags:    {}", format_counts(&self.tags));
+        }
+        if !self.slowest_pages.is_empty() {
+            println!("  Slowest:");
+            for page in &self.slowest_pages {
+                println!("    {:>7.1} ms  {}", page.duration_ms, page.path);
+            }
+        }
+    }
+
+    pub fn write_json(&self, path: &Path) -> io::Result<()> {
+        let json = serde_json::to_string_pretty(self).map_err(io::Error::other)?;
+        fs::write(path, json)?;
+        println!("  📊 Wrote build report to {}", path.display());
+        Ok(())
+    }
+}
+
+/// Prints statistics about the content in `content_dir`, without building
+/// anything: how much there is, how it's tagged, when it was written and the
+/// longest posts.
+pub fn print_content_stats(content_dir: &Path, config: &Config) -> io::Result<()> {
+    let ignore = IgnoreRules::load(&config.ignore, Path::new(".breadignore"))?;
+    let content_dirs = config.content_roots(content_dir);
+    let mut md_files = Vec::new();
+    for dir in &content_dirs {
+        let mut walk = Walk::new(dir, config.follow_symlinks);
+        md_files.extend(find_markdown_files(dir, dir, &ignore, &mut walk)?);
+    }
+    let plugins = Plugins::new();
+
+    let mut posts = 0;
+    let mut words = 0;
+    let mut post_words = 0;
+    let mut tags: BTreeMap<String, usize> = BTreeMap::new();
+    let mut years: BTreeMap<String, usize> = BTreeMap::new();
+    let mut longest: Vec<(usize, String)> = Vec::new();
+
+    for md_file in &md_files {
+        let (frontmatter, markdown) = read_page(md_file, &plugins)?;
+        let count = word_count(&markdown);
+        words += count;
+        if !is_listed_post(md_file, &frontmatter, content_root(md_file, &content_dirs)) {
+            continue;
+        }
+
+        posts += 1;
+        post_words += count;
+        let mut post_tags = frontmatter.tags.unwrap_or_default();
+        post_tags.sort();
+        post_tags.dedup();
+        for tag in post_tags {
+            *tags.entry(tag.trim().to_string()).or_default() += 1;
+        }
+        let year = match frontmatter.date.as_deref().and_then(dates::parse) {
+            Some(date) => date.format("%Y").to_string(),
+            None => "undated".to_string(),
+        };
+        *years.entry(year).or_default() += 1;
+        let title = frontmatter
+            .title
+            .unwrap_or_else(|| md_file.display().to_string());
+        longest.push((count, title));
+    }
+    longest.sort_by_key(|(count, _)| std::cmp::Reverse(*count));
+    longest.truncate(LONGEST_POSTS);
+
+    println!("📊 Content in {}/\n", content_dir.display());
+    println!("  Pages:        {} ({} posts)", md_files.len(), posts);
+    println!("  Words:        {}", words);
+    println!("  Reading time: {} min", reading_time(words));
+    if let Some(average) = post_words.checked_div(posts) {
+        println!("  Words/post:   {}", average);
+    }
+    if !years.is_empty() {
+        let years: Vec<String> = years
+            .iter()
+            .map(|(year, count)| format!("{} ({})", year, count))
+            .collect();
+        println!("  Posts/year:   {}", years.join(", "));
+    }
+    if !tags.is_empty() {
+        println!("  Tags:         {}", format_counts(&tags));
+    }
+    if !longest.is_empty() {
+        println!("  Longest:");
+        for (count, title) in &longest {
+            println!("    {:>6} words  {}", count, title);
+        }
+    }
+
+    Ok(())
+}
+
+/// `name (count)` pairs, most common first.
+fn format_counts(counts: &BTreeMap<String, usize>) -> String {
+    let mut counts: Vec<(&String, &usize)> = counts.iter().collect();
+    counts.sort_by(|a, b| b.1.cmp(a.1));
+    counts
+        .iter()
+        .map(|(name, count)| format!("{} ({})", name, count))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn format_bytes(bytes: u64) -> String {
+    match bytes {
+        0..1024 => format!("{} B", bytes),
+        1024..1_048_576 => format!("{:.1} KB", bytes as f64 / 1024.0),
+        _ => format!("{:.1} MB", bytes as f64 / 1_048_576.0),
+    }
+}
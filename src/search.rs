@@ -0,0 +1,36 @@
+use serde::Serialize;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::BuiltPage;
+
+#[derive(Serialize, Debug)]
+struct SearchEntry<'a> {
+    title: &'a str,
+    url: &'a str,
+    tags: &'a [String],
+    body: &'a str,
+}
+
+/// Writes `search-index.json`, a flat list of every page's title, URL, tags
+/// and markup-free body text, for client-side search libraries to load.
+/// Unlisted pages are left out.
+pub fn write_search_index(pages: &[BuiltPage], output_dir: &Path) -> io::Result<()> {
+    let entries: Vec<SearchEntry> = pages
+        .iter()
+        .filter(|page| !page.unlisted)
+        .map(|page| SearchEntry {
+            title: &page.title,
+            url: &page.url,
+            tags: &page.tags,
+            body: &page.text,
+        })
+        .collect();
+
+    let json = serde_json::to_string(&entries).map_err(io::Error::other)?;
+    fs::write(output_dir.join("search-index.json"), json)?;
+    println!("  🔎 Generated search-index.json ({} pages)", entries.len());
+
+    Ok(())
+}
@@ -0,0 +1,213 @@
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+/// One `{{< name arg key="value" >}}` use in a page, as handed to its template.
+///
+/// Positional arguments are available as `{args.0}`, `{args.1}`, ..., named
+/// ones as `{params.key}`. A shortcode can also wrap content, closed by
+/// `{{< /name >}}`, which its template gets as `{body}`. Everything is passed
+/// through unescaped, so templates should use `{value | escape}` wherever
+/// plain text is expected.
+#[derive(Debug, Default, Serialize)]
+pub struct ShortcodeCall {
+    pub name: String,
+    pub args: Vec<String>,
+    pub params: BTreeMap<String, String>,
+    pub body: String,
+}
+
+/// Shortcodes every site gets, unless its own templates replace them.
+const BUILTIN_TEMPLATES: [(&str, &str); 2] = [
+    (
+        "youtube",
+        include_str!("../templates/shortcodes/youtube.html"),
+    ),
+    ("vimeo", include_str!("../templates/shortcodes/vimeo.html")),
+];
+
+/// Reads the shortcode templates in `dirs`, keyed by file stem, so
+/// `templates/shortcodes/figure.html` defines `{{< figure >}}`. Templates in
+/// later directories replace those of the same name in earlier ones. The built-in
+/// shortcodes are included too: `{{< youtube id >}}` and `{{< vimeo id >}}`
+/// embed a video as a thumbnail that only loads the player (from YouTube's
+/// no-cookie domain, or Vimeo with tracking disabled) once clicked.
+pub fn load_templates(dirs: &[PathBuf]) -> io::Result<BTreeMap<String, String>> {
+    let mut templates: BTreeMap<String, String> = BUILTIN_TEMPLATES
+        .iter()
+        .map(|(name, template)| (name.to_string(), template.to_string()))
+        .collect();
+
+    for dir in dirs.iter().filter(|dir| dir.is_dir()) {
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.extension().and_then(|s| s.to_str()) != Some("html") {
+                continue;
+            }
+            if let Some(name) = path.file_stem().and_then(|s| s.to_str()) {
+                templates.insert(name.to_string(), fs::read_to_string(&path)?);
+            }
+        }
+    }
+
+    Ok(templates)
+}
+
+/// Replaces every shortcode in `markdown` with the output of `render`, which
+/// returns `None` for shortcodes that don't exist. Fenced code blocks and
+/// inline code are copied through untouched, so shortcodes can be shown
+/// literally.
+pub fn expand<F>(markdown: &str, render: &F) -> io::Result<String>
+where
+    F: Fn(&ShortcodeCall) -> Option<io::Result<String>>,
+{
+    let mut output = String::with_capacity(markdown.len());
+    let mut rest = markdown;
+    let mut fence: Option<&str> = None;
+    let mut at_line_start = true;
+
+    while let Some(c) = rest.chars().next() {
+        if at_line_start {
+            let line_end = rest.find('\n').map_or(rest.len(), |i| i + 1);
+            let line = &rest[..line_end];
+            let trimmed = line.trim_start();
+
+            let in_fence = match fence {
+                Some(marker) => {
+                    if trimmed.starts_with(marker) {
+                        fence = None;
+                    }
+                    true
+                }
+                None => {
+                    fence = fence_marker(trimmed);
+                    fence.is_some()
+                }
+            };
+            if in_fence {
+                output.push_str(line);
+                rest = &rest[line_end..];
+                continue;
+            }
+            at_line_start = false;
+        }
+
+        if c == '`' {
+            let ticks = &rest[..rest.len() - rest.trim_start_matches('`').len()];
+            let span_len = match rest[ticks.len()..].find(ticks) {
+                Some(end) => ticks.len() * 2 + end,
+                None => ticks.len(),
+            };
+            output.push_str(&rest[..span_len]);
+            rest = &rest[span_len..];
+            continue;
+        }
+
+        if rest.starts_with("{{<")
+            && let Some((mut call, tag_len)) = parse_tag(rest)
+            && !call.name.starts_with('/')
+        {
+            let mut consumed = tag_len;
+            if let Some((body_len, closing_len)) = find_closing(&rest[tag_len..], &call.name) {
+                call.body = expand(&rest[tag_len..tag_len + body_len], render)?;
+                consumed += body_len + closing_len;
+            }
+
+            let html = render(&call).ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("unknown shortcode '{}'", call.name),
+                )
+            })??;
+            output.push_str(&html);
+            rest = &rest[consumed..];
+            continue;
+        }
+
+        output.push(c);
+        rest = &rest[c.len_utf8()..];
+        at_line_start = c == '\n';
+    }
+
+    Ok(output)
+}
+
+/// The fence that opens a code block on this line, if any.
+fn fence_marker(line: &str) -> Option<&str> {
+    ["```", "~~~"]
+        .into_iter()
+        .find(|marker| line.starts_with(marker))
+}
+
+/// Parses the shortcode tag at the start of `text`, returning it along with
+/// the tag's length.
+fn parse_tag(text: &str) -> Option<(ShortcodeCall, usize)> {
+    let end = text.find(">}}")?;
+    let mut tokens = tokenize(&text[3..end]).into_iter();
+
+    let (None, name) = tokens.next()? else {
+        return None;
+    };
+    let mut call = ShortcodeCall {
+        name,
+        ..Default::default()
+    };
+    for (key, value) in tokens {
+        match key {
+            Some(key) => {
+                call.params.insert(key, value);
+            }
+            None => call.args.push(value),
+        }
+    }
+
+    Some((call, end + 3))
+}
+
+/// Splits a tag's contents on whitespace into positional values and
+/// `key=value` pairs, either of which may be double-quoted.
+fn tokenize(text: &str) -> Vec<(Option<String>, String)> {
+    let mut tokens = Vec::new();
+    let mut chars = text.trim().chars().peekable();
+
+    while chars.peek().is_some() {
+        let mut key = None;
+        let mut value = String::new();
+        let mut quoted = false;
+
+        while let Some(c) = chars.next() {
+            match c {
+                '"' => quoted = !quoted,
+                '\\' if quoted => value.extend(chars.next()),
+                '=' if !quoted && key.is_none() && !value.is_empty() => {
+                    key = Some(std::mem::take(&mut value));
+                }
+                c if c.is_whitespace() && !quoted => break,
+                c => value.push(c),
+            }
+        }
+        tokens.push((key, value));
+
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+    }
+
+    tokens
+}
+
+/// Finds `{{< /name >}}` in `text`, returning the offset it starts at and its
+/// length.
+fn find_closing(text: &str, name: &str) -> Option<(usize, usize)> {
+    let mut offset = 0;
+    while let Some(start) = text[offset..].find("{{<") {
+        let start = offset + start;
+        if let Some((call, len)) = parse_tag(&text[start..])
+            && call.name.strip_prefix('/') == Some(name)
+        {
+            return Some((start, len));
+        }
+        offset = start + 3;
+    }
+    None
+}
@@ -0,0 +1,174 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::config::Config;
+use crate::{BuiltPage, dates, escape_html, warn};
+
+/// The most URLs one sitemap file may list
+const MAX_URLS: usize = 50_000;
+
+/// The biggest one sitemap file may be, uncompressed
+const MAX_BYTES: usize = 50 * 1024 * 1024;
+
+/// Where the list of sitemaps goes when there's more than one
+const INDEX: &str = "sitemap_index.xml";
+
+const URLSET_START: &str = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+     <urlset xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">\n";
+
+const URLSET_END: &str = "</urlset>\n";
+
+/// Writes `sitemap.xml`, listing every page but the unlisted ones along with the extra generated
+/// pages in `extra_urls`. A page's `<lastmod>` is its last commit, when git
+/// history is on, or else its date.
+///
+/// Sites with more URLs than one sitemap may hold, or too big a one, get
+/// `sitemap-1.xml`, `sitemap-2.xml` and so on instead, listed in a
+/// `sitemap_index.xml`.
+///
+/// Sitemaps only take full URLs, so without an absolute `base_url` there's a
+/// warning instead.
+pub fn write_sitemap(
+    pages: &[BuiltPage],
+    extra_urls: &[String],
+    config: &Config,
+    output_dir: &Path,
+) -> io::Result<()> {
+    if !config.base_url.contains("://") {
+        return warn(
+            config,
+            format!(
+                "Skipped the sitemap: base_url \"{}\" isn't a full URL like https://example.com/",
+                config.base_url
+            ),
+        );
+    }
+
+    let mut entries = Vec::new();
+    for page in pages.iter().filter(|page| !page.unlisted) {
+        let mut entry = String::from("  <url>\n");
+        entry.push_str(&format!(
+            "    <loc>{}</loc>\n",
+            escape_html(&config.absolute_url(&page.url))
+        ));
+        let last_modified = [&page.last_modified, &page.date]
+            .into_iter()
+            .find_map(|date| dates::parse(date));
+        if let Some(date) = last_modified {
+            entry.push_str(&format!(
+                "    <lastmod>{}</lastmod>\n",
+                date.format("%Y-%m-%d")
+            ));
+        }
+        entry.push_str("  </url>\n");
+        entries.push(entry);
+    }
+    for url in extra_urls {
+        entries.push(format!(
+            "  <url>\n    <loc>{}</loc>\n  </url>\n",
+            escape_html(&config.absolute_url(url))
+        ));
+    }
+
+    let sitemaps = split(&entries);
+    if let [sitemap] = sitemaps.as_slice() {
+        fs::write(output_dir.join("sitemap.xml"), urlset(sitemap))?;
+        println!("  🗺  Generated sitemap.xml ({} URLs)", entries.len());
+        return Ok(());
+    }
+
+    let mut index = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    index.push_str("<sitemapindex xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">\n");
+    for (i, sitemap) in sitemaps.iter().enumerate() {
+        let name = format!("sitemap-{}.xml", i + 1);
+        fs::write(output_dir.join(&name), urlset(sitemap))?;
+        index.push_str(&format!(
+            "  <sitemap>\n    <loc>{}</loc>\n  </sitemap>\n",
+            escape_html(&config.absolute_url(&name))
+        ));
+    }
+    index.push_str("</sitemapindex>\n");
+    fs::write(output_dir.join(INDEX), index)?;
+    println!(
+        "  🗺  Generated {} ({} URLs in {} sitemaps)",
+        INDEX,
+        entries.len(),
+        sitemaps.len()
+    );
+
+    Ok(())
+}
+
+/// Divides the `<url>` entries into runs that each fit in a sitemap file.
+fn split(entries: &[String]) -> Vec<&[String]> {
+    let wrapper = URLSET_START.len() + URLSET_END.len();
+    let mut sitemaps = Vec::new();
+    let mut start = 0;
+    let mut bytes = wrapper;
+    for (i, entry) in entries.iter().enumerate() {
+        if i > start && (i - start == MAX_URLS || bytes + entry.len() > MAX_BYTES) {
+            sitemaps.push(&entries[start..i]);
+            start = i;
+            bytes = wrapper;
+        }
+        bytes += entry.len();
+    }
+    sitemaps.push(&entries[start..]);
+    sitemaps
+}
+
+fn urlset(entries: &[String]) -> String {
+    format!("{}{}{}", URLSET_START, entries.concat(), URLSET_END)
+}
+
+/// Writes a `robots.txt` allowing every crawler and pointing them at the
+/// sitemap, or the sitemap index if it was split, if one was written.
+pub fn write_robots_txt(config: &Config, output_dir: &Path) -> io::Result<()> {
+    let mut robots = "User-agent: *\nAllow: /\n".to_string();
+    let sitemap = [INDEX, "sitemap.xml"]
+        .into_iter()
+        .find(|name| output_dir.join(name).exists());
+    if let Some(sitemap) = sitemap {
+        robots.push_str(&format!("\nSitemap: {}\n", config.absolute_url(sitemap)));
+    }
+    fs::write(output_dir.join("robots.txt"), robots)?;
+    println!("  🤖 Generated robots.txt");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_keeps_a_small_sitemap_whole() {
+        let entries = vec!["<url/>".to_string(); 3];
+        assert_eq!(split(&entries), [&entries[..]]);
+    }
+
+    #[test]
+    fn split_yields_one_empty_sitemap_without_entries() {
+        let entries: Vec<String> = Vec::new();
+        assert_eq!(split(&entries).len(), 1);
+    }
+
+    #[test]
+    fn split_starts_a_new_sitemap_after_max_urls() {
+        let entries = vec!["<url/>".to_string(); MAX_URLS + 1];
+        let sitemaps = split(&entries);
+        assert_eq!(sitemaps.len(), 2);
+        assert_eq!(sitemaps[0].len(), MAX_URLS);
+        assert_eq!(sitemaps[1].len(), 1);
+    }
+
+    #[test]
+    fn split_starts_a_new_sitemap_before_max_bytes() {
+        let entries = vec!["x".repeat(MAX_BYTES / 2); 3];
+        let sitemaps = split(&entries);
+        assert_eq!(sitemaps.len(), 3);
+        for sitemap in sitemaps {
+            assert!(urlset(sitemap).len() <= MAX_BYTES);
+        }
+    }
+}
@@ -0,0 +1,167 @@
+use ab_glyph::{Font, FontRef, PxScale, ScaleFont, point};
+use image::{Rgba, RgbaImage};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::config::SocialCardsConfig;
+
+/// Open Graph's recommended image size.
+const WIDTH: u32 = 1200;
+const HEIGHT: u32 = 630;
+const PADDING: f32 = 80.0;
+const TITLE_SIZE: f32 = 72.0;
+const TITLE_MAX_LINES: usize = 4;
+const SITE_NAME_SIZE: f32 = 36.0;
+
+static DEFAULT_FONT: &[u8] = include_bytes!("../fonts/DejaVuSans-Bold.ttf");
+
+/// Draws a post's social preview card, its title over a plain background
+/// with the site name underneath, and saves it as a PNG at `dest`.
+pub fn write_card(
+    title: &str,
+    site_name: &str,
+    config: &SocialCardsConfig,
+    dest: &Path,
+) -> io::Result<()> {
+    let custom_font = if config.font.is_empty() {
+        None
+    } else {
+        Some(fs::read(&config.font)?)
+    };
+    let font = FontRef::try_from_slice(custom_font.as_deref().unwrap_or(DEFAULT_FONT))
+        .map_err(|e| invalid_data(format!("{}: {}", config.font, e)))?;
+
+    let background = parse_color(&config.background)?;
+    let foreground = parse_color(&config.foreground)?;
+    let mut card = RgbaImage::from_pixel(WIDTH, HEIGHT, background);
+
+    let title_font = font.as_scaled(PxScale::from(TITLE_SIZE));
+    let line_height = title_font.height() + title_font.line_gap();
+    for (i, line) in wrap(title, &title_font, WIDTH as f32 - 2.0 * PADDING)
+        .iter()
+        .enumerate()
+    {
+        let baseline = PADDING + title_font.ascent() + i as f32 * line_height;
+        draw_text(&mut card, &title_font, line, PADDING, baseline, foreground);
+    }
+
+    if !site_name.is_empty() {
+        let name_font = font.as_scaled(PxScale::from(SITE_NAME_SIZE));
+        let baseline = HEIGHT as f32 - PADDING + name_font.descent();
+        let muted = Rgba([foreground[0], foreground[1], foreground[2], 180]);
+        draw_text(&mut card, &name_font, site_name, PADDING, baseline, muted);
+    }
+
+    if let Some(parent) = dest.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    card.save(dest)
+        .map_err(|e| invalid_data(format!("{}: {}", dest.display(), e)))
+}
+
+/// Breaks `text` into lines no wider than `max_width`, ending with an
+/// ellipsis if it doesn't fit in `TITLE_MAX_LINES`.
+fn wrap<F: Font, SF: ScaleFont<F>>(text: &str, font: &SF, max_width: f32) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    let mut current = String::new();
+
+    for word in text.split_whitespace() {
+        let candidate = if current.is_empty() {
+            word.to_string()
+        } else {
+            format!("{} {}", current, word)
+        };
+        if text_width(font, &candidate) <= max_width || current.is_empty() {
+            current = candidate;
+        } else {
+            lines.push(std::mem::replace(&mut current, word.to_string()));
+        }
+    }
+    if !current.is_empty() {
+        lines.push(current);
+    }
+
+    if lines.len() > TITLE_MAX_LINES {
+        lines.truncate(TITLE_MAX_LINES);
+        let last = &mut lines[TITLE_MAX_LINES - 1];
+        while !last.is_empty() && text_width(font, &format!("{}…", last)) > max_width {
+            last.pop();
+        }
+        last.push('…');
+    }
+
+    lines
+}
+
+fn text_width<F: Font, SF: ScaleFont<F>>(font: &SF, text: &str) -> f32 {
+    let mut width = 0.0;
+    let mut previous = None;
+    for c in text.chars() {
+        let id = font.glyph_id(c);
+        if let Some(previous) = previous {
+            width += font.kern(previous, id);
+        }
+        width += font.h_advance(id);
+        previous = Some(id);
+    }
+    width
+}
+
+fn draw_text<F: Font, SF: ScaleFont<F>>(
+    image: &mut RgbaImage,
+    font: &SF,
+    text: &str,
+    x: f32,
+    baseline: f32,
+    color: Rgba<u8>,
+) {
+    let mut caret = x;
+    let mut previous = None;
+
+    for c in text.chars() {
+        let id = font.glyph_id(c);
+        if let Some(previous) = previous {
+            caret += font.kern(previous, id);
+        }
+        let glyph = id.with_scale_and_position(font.scale(), point(caret, baseline));
+        caret += font.h_advance(id);
+        previous = Some(id);
+
+        let Some(outline) = font.outline_glyph(glyph) else {
+            continue;
+        };
+        let bounds = outline.px_bounds();
+        outline.draw(|gx, gy, coverage| {
+            let px = bounds.min.x as i32 + gx as i32;
+            let py = bounds.min.y as i32 + gy as i32;
+            if px < 0 || py < 0 || px >= image.width() as i32 || py >= image.height() as i32 {
+                return;
+            }
+            let alpha = coverage * f32::from(color[3]) / 255.0;
+            let pixel = image.get_pixel_mut(px as u32, py as u32);
+            for channel in 0..3 {
+                let blended =
+                    f32::from(pixel[channel]) * (1.0 - alpha) + f32::from(color[channel]) * alpha;
+                pixel[channel] = blended.round() as u8;
+            }
+        });
+    }
+}
+
+/// Parses a `#rrggbb` color.
+fn parse_color(color: &str) -> io::Result<Rgba<u8>> {
+    let hex = color.trim_start_matches('#');
+    let channel = |i: usize| {
+        hex.get(i..i + 2)
+            .and_then(|pair| u8::from_str_radix(pair, 16).ok())
+    };
+    match (hex.len(), channel(0), channel(2), channel(4)) {
+        (6, Some(r), Some(g), Some(b)) => Ok(Rgba([r, g, b, 255])),
+        _ => Err(invalid_data(format!("invalid color '{}'", color))),
+    }
+}
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
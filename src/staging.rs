@@ -0,0 +1,147 @@
+use std::fs;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+
+use crate::assets::find_files;
+use crate::relative_path;
+
+/// Where a build of `output_dir` is written before it's moved into place: a
+/// hidden directory next to it, so moving it over is a rename on the same
+/// filesystem.
+pub fn dir(output_dir: &Path) -> PathBuf {
+    sibling(output_dir, "staging")
+}
+
+/// Where the previous build is moved aside while the new one takes its place.
+fn old_dir(output_dir: &Path) -> PathBuf {
+    sibling(output_dir, "old")
+}
+
+fn sibling(output_dir: &Path, suffix: &str) -> PathBuf {
+    let name = output_dir
+        .file_name()
+        .map_or("output".into(), |name| name.to_string_lossy());
+    output_dir.with_file_name(format!(".{}.{}", name, suffix))
+}
+
+/// `path`, a file being written into a staging directory, as it'll be once
+/// published: relative to the output directory. Other paths are shown as
+/// they are.
+pub fn shown(path: &Path) -> String {
+    let components: Vec<Component> = path.components().collect();
+    let staging = components.iter().rposition(|component| {
+        let name = component.as_os_str().to_string_lossy();
+        name.starts_with('.') && name.ends_with(".staging")
+    });
+    match staging {
+        Some(i) => components[i + 1..]
+            .iter()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/"),
+        None => path.display().to_string(),
+    }
+}
+
+/// Puts the finished build in `staging` in place of `output_dir`.
+///
+/// Files of the previous build the new one doesn't have are first carried
+/// over into `staging` if `keep` says so, and the output-relative paths of
+/// the rest are returned. Then `output_dir` is renamed aside and `staging`
+/// renamed into its place, putting the old one back if that fails, so
+/// `output_dir` always holds either the whole previous build or the whole
+/// new one. The previous build is removed last.
+pub fn publish(
+    staging: &Path,
+    output_dir: &Path,
+    keep: impl Fn(&str) -> bool,
+) -> io::Result<Vec<String>> {
+    if !output_dir.exists() {
+        fs::rename(staging, output_dir)?;
+        return Ok(Vec::new());
+    }
+
+    let mut removed = Vec::new();
+    for file in find_files(output_dir)? {
+        let relative = relative_path(&file, output_dir);
+        let dest = staging.join(&relative);
+        // The new build wins wherever it has a file, or a file where the old
+        // one had a directory
+        let taken = dest.exists()
+            || Path::new(&relative)
+                .ancestors()
+                .skip(1)
+                .any(|ancestor| staging.join(ancestor).is_file());
+        if taken {
+            continue;
+        }
+        if !keep(&relative) {
+            removed.push(relative);
+            continue;
+        }
+        if let Some(parent) = dest.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        if fs::hard_link(&file, &dest).is_err() {
+            fs::copy(&file, &dest)?;
+        }
+    }
+
+    let old = old_dir(output_dir);
+    if old.exists() {
+        fs::remove_dir_all(&old)?;
+    }
+    fs::rename(output_dir, &old)?;
+    if let Err(e) = fs::rename(staging, output_dir) {
+        fs::rename(&old, output_dir)?;
+        return Err(e);
+    }
+    fs::remove_dir_all(&old)?;
+    Ok(removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::scratch_dir;
+
+    #[test]
+    fn staging_dirs_sit_next_to_the_output_directory() {
+        let output = Path::new("site/public");
+        assert_eq!(dir(output), Path::new("site/.public.staging"));
+        assert_eq!(old_dir(output), Path::new("site/.public.old"));
+        assert_eq!(shown(&dir(output).join("posts/a.html")), "posts/a.html");
+        assert_eq!(shown(Path::new("public/a.html")), "public/a.html");
+    }
+
+    #[test]
+    fn publish_swaps_in_the_new_build_keeping_what_its_asked_to() {
+        let dir = scratch_dir("staging-publish");
+        let (output, staging) = (dir.join("public"), dir.join(".public.staging"));
+        fs::create_dir_all(output.join("posts")).unwrap();
+        fs::create_dir_all(&staging).unwrap();
+        fs::write(output.join("index.html"), "old").unwrap();
+        fs::write(output.join("posts/gone.html"), "old").unwrap();
+        fs::write(output.join("uploads.txt"), "kept").unwrap();
+        fs::write(output.join("posts.html"), "old").unwrap();
+        fs::write(staging.join("index.html"), "new").unwrap();
+        // The new build has a directory where the old one had a file
+        fs::create_dir_all(staging.join("posts.html")).unwrap();
+
+        let removed = publish(&staging, &output, |path| path == "uploads.txt").unwrap();
+
+        assert_eq!(removed, ["posts/gone.html"]);
+        assert!(!staging.exists());
+        assert!(!old_dir(&output).exists());
+        assert_eq!(
+            fs::read_to_string(output.join("index.html")).unwrap(),
+            "new"
+        );
+        assert_eq!(
+            fs::read_to_string(output.join("uploads.txt")).unwrap(),
+            "kept"
+        );
+        assert!(output.join("posts.html").is_dir());
+        assert!(!output.join("posts").exists());
+    }
+}
@@ -0,0 +1,145 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::config::AssetsConfig;
+
+/// Puts static files into a build as cheaply as it can. A file the previous
+/// build already published, with the same size and modification time as its
+/// source, is hard-linked from there rather than copied again; with
+/// `assets.hard_links`, changed files are hard-linked from their source too.
+/// Everything else is copied, which on filesystems that support it (Btrfs,
+/// XFS, APFS) shares the data copy-on-write, and given the source's
+/// modification time so the next build sees it's unchanged.
+pub struct FileSync<'a> {
+    /// The output directory of the previous build
+    previous_dir: &'a Path,
+    hard_links: bool,
+}
+
+impl<'a> FileSync<'a> {
+    pub fn new(previous_dir: &'a Path, config: &AssetsConfig) -> Self {
+        FileSync {
+            previous_dir,
+            hard_links: config.hard_links,
+        }
+    }
+
+    /// Puts `source` at `relative` in `output_dir`, replacing what's there.
+    /// Returns whether the previous build's copy was reused.
+    pub fn copy(&self, source: &Path, output_dir: &Path, relative: &str) -> io::Result<bool> {
+        let dest = output_dir.join(relative);
+        unlink(&dest)?;
+        let previous = self.previous_dir.join(relative);
+        if is_unchanged(source, &previous)? && fs::hard_link(&previous, &dest).is_ok() {
+            return Ok(true);
+        }
+        if self.hard_links && fs::hard_link(source, &dest).is_ok() {
+            return Ok(false);
+        }
+
+        copy_file(source, &dest)?;
+        Ok(false)
+    }
+}
+
+/// Copies `source` to `dest` as a file of its own, never a hard link, for
+/// files the build goes on to rewrite, like images the pipeline downscales.
+pub fn copy_file(source: &Path, dest: &Path) -> io::Result<()> {
+    unlink(dest)?;
+    fs::copy(source, dest)?;
+    let modified = fs::metadata(source)?.modified()?;
+    fs::File::options()
+        .write(true)
+        .open(dest)?
+        .set_modified(modified)
+}
+
+/// Removes the file at `dest`, if there is one, so what's written there next
+/// can't go through a hard link into a source file or the previous build.
+pub fn unlink(dest: &Path) -> io::Result<()> {
+    match fs::remove_file(dest) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
+
+/// Whether `previous` is a copy of `source` going by size and modification
+/// time.
+fn is_unchanged(source: &Path, previous: &Path) -> io::Result<bool> {
+    let Ok(published) = fs::metadata(previous) else {
+        return Ok(false);
+    };
+    let source = fs::metadata(source)?;
+    Ok(published.is_file()
+        && published.len() == source.len()
+        && published.modified()? == source.modified()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::scratch_dir;
+
+    fn hard_linking() -> AssetsConfig {
+        AssetsConfig {
+            hard_links: true,
+            ..AssetsConfig::default()
+        }
+    }
+
+    #[test]
+    fn copy_replaces_a_hard_link_rather_than_writing_through_it() {
+        let dir = scratch_dir("sync-replace");
+        let (previous, output) = (dir.join("previous"), dir.join("output"));
+        fs::create_dir_all(&previous).unwrap();
+        fs::create_dir_all(&output).unwrap();
+        fs::write(dir.join("content.txt"), "content").unwrap();
+        fs::write(dir.join("static.txt"), "static").unwrap();
+        fs::hard_link(dir.join("content.txt"), output.join("a.txt")).unwrap();
+
+        let sync = FileSync::new(&previous, &AssetsConfig::default());
+        sync.copy(&dir.join("static.txt"), &output, "a.txt")
+            .unwrap();
+
+        assert_eq!(fs::read_to_string(output.join("a.txt")).unwrap(), "static");
+        assert_eq!(
+            fs::read_to_string(dir.join("content.txt")).unwrap(),
+            "content"
+        );
+    }
+
+    #[test]
+    fn copy_reuses_an_unchanged_file_from_the_previous_build() {
+        let dir = scratch_dir("sync-unchanged");
+        let (previous, output) = (dir.join("previous"), dir.join("output"));
+        fs::create_dir_all(&output).unwrap();
+        fs::create_dir_all(&previous).unwrap();
+        fs::write(dir.join("a.txt"), "a").unwrap();
+        copy_file(&dir.join("a.txt"), &previous.join("a.txt")).unwrap();
+
+        let sync = FileSync::new(&previous, &AssetsConfig::default());
+        assert!(sync.copy(&dir.join("a.txt"), &output, "a.txt").unwrap());
+        fs::write(dir.join("a.txt"), "changed").unwrap();
+        assert!(!sync.copy(&dir.join("a.txt"), &output, "a.txt").unwrap());
+        assert_eq!(fs::read_to_string(output.join("a.txt")).unwrap(), "changed");
+        assert_eq!(fs::read_to_string(previous.join("a.txt")).unwrap(), "a");
+    }
+
+    #[test]
+    fn copy_file_never_writes_into_a_linked_source() {
+        let dir = scratch_dir("sync-copy-file");
+        let output = dir.join("output");
+        fs::create_dir_all(&output).unwrap();
+        fs::write(dir.join("a.txt"), "source").unwrap();
+        FileSync::new(&dir, &hard_linking())
+            .copy(&dir.join("a.txt"), &output, "a.txt")
+            .unwrap();
+
+        fs::write(dir.join("b.txt"), "other").unwrap();
+        copy_file(&dir.join("b.txt"), &output.join("a.txt")).unwrap();
+
+        assert_eq!(fs::read_to_string(dir.join("a.txt")).unwrap(), "source");
+        assert_eq!(fs::read_to_string(output.join("a.txt")).unwrap(), "other");
+    }
+}
@@ -0,0 +1,122 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use tinytemplate::error::Error;
+
+use crate::{relative_path, theme};
+
+/// Reads every `.html` file under `template_dir` but those in
+/// `shortcodes/`, keyed by its path without the extension: `base`, `posts`,
+/// `404`, partials like `partials/header` for `{{ call }}`, and section
+/// templates like `projects/base` that pages in `content/projects/` render
+/// with instead of the global `base.html`. The templates of the site's
+/// theme, if it has one, and then those of the default theme fill in for
+/// those the directory doesn't have.
+pub fn load(template_dir: &Path, theme_dir: Option<&Path>) -> io::Result<BTreeMap<String, String>> {
+    let mut templates: BTreeMap<String, String> = theme::DEFAULT_TEMPLATES
+        .iter()
+        .map(|(name, template)| (name.to_string(), template.to_string()))
+        .collect();
+    let theme_templates = theme_dir.map(|dir| dir.join("templates"));
+    for dir in theme_templates
+        .iter()
+        .map(PathBuf::as_path)
+        .chain([template_dir])
+    {
+        if dir.is_dir() {
+            find_templates(dir, dir, &mut templates)?;
+        }
+    }
+    Ok(templates)
+}
+
+fn find_templates(
+    dir: &Path,
+    template_dir: &Path,
+    templates: &mut BTreeMap<String, String>,
+) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            if path != template_dir.join("shortcodes") {
+                find_templates(&path, template_dir, templates)?;
+            }
+        } else if path.extension().and_then(|s| s.to_str()) == Some("html") {
+            let name = relative_path(&path.with_extension(""), template_dir);
+            templates.insert(name, fs::read_to_string(&path)?);
+        }
+    }
+    Ok(())
+}
+
+/// Turns an error from the template engine into one pointing at the template
+/// file, line and column it's about, quoting the offending line.
+/// `sources` holds the text of every registered template by name, for
+/// quoting from, and `template_dir` is where their files are.
+pub fn error(
+    err: &Error,
+    name: &str,
+    template_dir: &Path,
+    sources: &BTreeMap<String, &str>,
+) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        describe(err, name, template_dir, sources),
+    )
+}
+
+fn describe(
+    err: &Error,
+    name: &str,
+    template_dir: &Path,
+    sources: &BTreeMap<String, &str>,
+) -> String {
+    let file = template_dir.join(format!("{}.html", name));
+    let source = sources.get(name).copied();
+    match err {
+        Error::ParseError { msg, line, column } | Error::RenderError { msg, line, column } => {
+            located(&file, source, *line, *column, msg)
+        }
+        Error::CalledTemplateError {
+            name: called,
+            err,
+            line,
+            column,
+        } => format!(
+            "{}\n  called from {}",
+            describe(err, called, template_dir, sources),
+            located(&file, source, *line, *column, "")
+        ),
+        Error::CalledFormatterError {
+            name: formatter,
+            err,
+            line,
+            column,
+        } => located(
+            &file,
+            source,
+            *line,
+            *column,
+            &format!("formatter '{}' failed: {}", formatter, err),
+        ),
+        _ => format!("{}: {}", file.display(), err),
+    }
+}
+
+/// `file:line:column: message`, then the line itself with a marker under the
+/// column. Lines count from 1 and columns, in bytes, from 0.
+fn located(file: &Path, source: Option<&str>, line: usize, column: usize, message: &str) -> String {
+    let mut text = format!("{}:{}:{}", file.display(), line, column + 1);
+    if !message.is_empty() {
+        text.push_str(&format!(": {}", message));
+    }
+    let code = source.and_then(|source| source.lines().nth(line.saturating_sub(1)));
+    if let Some(code) = code {
+        let indent = code
+            .get(..column)
+            .map_or(column, |before| before.chars().count());
+        text.push_str(&format!("\n    {}\n    {}^", code, " ".repeat(indent)));
+    }
+    text
+}
@@ -0,0 +1,166 @@
+//! The default theme, built into the binary so a directory of content is
+//! all a site needs. The site's own templates and static files replace its
+//! files one by one.
+
+use std::fs;
+use std::io;
+use std::path::{Component, Path};
+
+use crate::config::THEMES_DIR;
+use crate::deploy;
+
+/// Page templates every site gets, unless its own templates replace them.
+pub const DEFAULT_TEMPLATES: [(&str, &str); 3] = [
+    ("base", include_str!("../templates/base.html")),
+    ("posts", include_str!("../templates/posts.html")),
+    ("tag", include_str!("../templates/tag.html")),
+];
+
+/// The stylesheet and script the default templates link to, published
+/// unless `static/` has files by the same names.
+pub const DEFAULT_ASSETS: [(&str, &str); 2] = [
+    ("style.css", include_str!("../static/style.css")),
+    ("script.js", include_str!("../static/script.js")),
+];
+
+/// Templates a theme has to have, relative to its directory
+const REQUIRED_TEMPLATES: [&str; 1] = ["templates/base.html"];
+
+/// Clones the theme repository at `url` into `themes/<name>`, checks it's
+/// a theme, and makes it the site's theme in the config file at
+/// `config_path`. The name defaults to the repository's.
+pub fn install(url: &str, name: Option<&str>, config_path: &Path) -> io::Result<()> {
+    let name = match name {
+        Some(name) => name.to_string(),
+        None => repository_name(url).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Can't tell a theme name from {}, pass --name", url),
+            )
+        })?,
+    };
+    if !is_directory_name(&name) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "{} isn't a theme name: it has to be a single directory name",
+                name
+            ),
+        ));
+    }
+    let dir = Path::new(THEMES_DIR).join(&name);
+    if dir.exists() {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("{} already exists", dir.display()),
+        ));
+    }
+
+    println!("📥 Cloning {} into {}...", url, dir.display());
+    fs::create_dir_all(THEMES_DIR)?;
+    deploy::git(&["clone", "--depth", "1", url, &dir.to_string_lossy()], &[])?;
+
+    let missing: Vec<&str> = REQUIRED_TEMPLATES
+        .into_iter()
+        .filter(|template| !dir.join(template).is_file())
+        .collect();
+    if !missing.is_empty() {
+        fs::remove_dir_all(&dir)?;
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{} isn't a bread theme: no {}", url, missing.join(", ")),
+        ));
+    }
+
+    set_theme(config_path, &name)?;
+    println!(
+        "🎨 Installed theme {} and set it in {}",
+        name,
+        config_path.display()
+    );
+    Ok(())
+}
+
+/// The last part of a repository URL, without `.git`.
+fn repository_name(url: &str) -> Option<String> {
+    let name = url
+        .trim_end_matches('/')
+        .rsplit(['/', ':'])
+        .next()?
+        .trim_end_matches(".git");
+    (!name.is_empty()).then(|| name.to_string())
+}
+
+/// Whether `name` names a directory right inside `themes/`, rather than
+/// somewhere else like `../x`.
+fn is_directory_name(name: &str) -> bool {
+    let mut components = Path::new(name).components();
+    matches!(
+        (components.next(), components.next()),
+        (Some(Component::Normal(_)), None)
+    )
+}
+
+/// Sets the top-level `theme` in the config file, keeping the rest of it as
+/// it is. A new `theme` line goes before the first table, where top-level
+/// keys have to be.
+fn set_theme(config_path: &Path, name: &str) -> io::Result<()> {
+    let config = if config_path.exists() {
+        fs::read_to_string(config_path)?
+    } else {
+        String::new()
+    };
+    let setting = format!("theme = {}", toml::Value::String(name.to_string()));
+
+    let mut lines: Vec<String> = config.lines().map(String::from).collect();
+    let first_table = lines
+        .iter()
+        .position(|line| line.trim_start().starts_with('['))
+        .unwrap_or(lines.len());
+    let existing = lines[..first_table].iter().position(|line| {
+        line.split_once('=')
+            .is_some_and(|(key, _)| key.trim() == "theme")
+    });
+    match existing {
+        Some(index) => lines[index] = setting,
+        None if first_table < lines.len() => {
+            lines.insert(first_table, setting);
+            lines.insert(first_table + 1, String::new());
+        }
+        None => lines.push(setting),
+    }
+
+    fs::write(config_path, lines.join("\n") + "\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::scratch_dir;
+
+    #[test]
+    fn theme_names_are_single_directory_names() {
+        assert!(is_directory_name("paper"));
+        assert!(is_directory_name("paper.v2"));
+        for name in ["", "..", "../paper", "themes/paper", "/paper", "./paper"] {
+            assert!(!is_directory_name(name), "{:?}", name);
+        }
+    }
+
+    #[test]
+    fn set_theme_quotes_the_name_as_toml() {
+        let dir = scratch_dir("theme-set");
+        let config = dir.join("bread.toml");
+        fs::write(&config, "title = \"Site\"\n\n[posts]\nrelated = 2\n").unwrap();
+
+        set_theme(&config, "a \"b\" \\ c").unwrap();
+        let written: toml::Table = fs::read_to_string(&config).unwrap().parse().unwrap();
+        assert_eq!(written["theme"].as_str(), Some("a \"b\" \\ c"));
+        assert_eq!(written["posts"]["related"].as_integer(), Some(2));
+
+        set_theme(&config, "paper").unwrap();
+        let written = fs::read_to_string(&config).unwrap();
+        assert_eq!(written.matches("theme = ").count(), 1);
+        assert!(written.contains("theme = \"paper\"\n\n[posts]"));
+    }
+}
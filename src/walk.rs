@@ -0,0 +1,51 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Keeps track of a recursive walk through a directory, so symlinked
+/// directories are followed (or not) deliberately: a link back to a
+/// directory the walk is already inside would go round forever, so it's
+/// skipped with a warning.
+pub struct Walk {
+    follow_symlinks: bool,
+    /// The directories being walked, root first, resolved
+    ancestors: Vec<PathBuf>,
+}
+
+impl Walk {
+    pub fn new(root: &Path, follow_symlinks: bool) -> Self {
+        Walk {
+            follow_symlinks,
+            ancestors: fs::canonicalize(root).into_iter().collect(),
+        }
+    }
+
+    /// Runs `walk_dir` on the subdirectory at `path`, unless it's a symlink
+    /// that isn't to be followed or would lead back to where the walk came
+    /// from, in which case it returns `None`.
+    pub fn descend<T>(
+        &mut self,
+        path: &Path,
+        walk_dir: impl FnOnce(&mut Self) -> io::Result<T>,
+    ) -> io::Result<Option<T>> {
+        let is_symlink = fs::symlink_metadata(path)?.file_type().is_symlink();
+        if is_symlink && !self.follow_symlinks {
+            return Ok(None);
+        }
+
+        let resolved = fs::canonicalize(path)?;
+        if self.ancestors.contains(&resolved) {
+            println!(
+                "  ⚠ Skipped {}: it links back to {}",
+                path.display(),
+                resolved.display()
+            );
+            return Ok(None);
+        }
+
+        self.ancestors.push(resolved);
+        let result = walk_dir(self);
+        self.ancestors.pop();
+        result.map(Some)
+    }
+}
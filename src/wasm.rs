@@ -0,0 +1,221 @@
+use serde::Serialize;
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::io;
+use std::path::{Path, PathBuf};
+use wasmi::{
+    Config as EngineConfig, Engine, Linker, Module, Store, StoreLimits, StoreLimitsBuilder,
+};
+
+use crate::{Frontmatter, Plugin};
+
+/// How much work a plugin may do on one page before it is stopped, in wasmi
+/// fuel units (roughly one per instruction).
+const FUEL_PER_PAGE: u64 = 100_000_000;
+
+/// How big a plugin's linear memory may grow while working on one page
+const MEMORY_PER_PAGE: usize = 128 * 1024 * 1024;
+
+/// A content transform compiled to WebAssembly, loaded from a `.wasm` (or
+/// `.wat` text) file listed in the `plugins.wasm` setting.
+///
+/// The module gets each page's markdown after its frontmatter is parsed and
+/// returns the markdown to use instead. It runs sandboxed: nothing is
+/// imported into it, so it can't reach the file system or network, and its
+/// fuel and memory are limited so a runaway loop or allocation fails the
+/// build rather than hanging it or exhausting the machine.
+///
+/// A module has to export:
+///
+/// - `memory`, its linear memory;
+/// - `alloc(len: i32) -> i32`, returning a buffer of `len` bytes for the
+///   input to be written to;
+/// - `transform(ptr: i32, len: i32) -> i64`, called with the input, a JSON
+///   object with the page's `path`, `title`, `date`, `tags` and markdown
+///   `content`, and returning the output's UTF-8 bytes as a pointer in the
+///   high 32 bits and a length in the low 32.
+pub struct WasmPlugin {
+    name: String,
+    engine: Engine,
+    module: Module,
+    /// The output for each page by path, with the input it was for, since
+    /// pages are read several times a build
+    transformed: RefCell<BTreeMap<PathBuf, (Vec<u8>, String)>>,
+}
+
+#[derive(Serialize)]
+struct TransformInput<'a> {
+    path: &'a str,
+    title: &'a str,
+    date: &'a str,
+    tags: &'a [String],
+    content: &'a str,
+}
+
+impl WasmPlugin {
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let mut config = EngineConfig::default();
+        config.consume_fuel(true);
+        let engine = Engine::new(&config);
+        let module = Module::new(&engine, std::fs::read(path)?)
+            .map_err(|e| invalid_data(path, &e.to_string()))?;
+
+        Ok(WasmPlugin {
+            name: path.display().to_string(),
+            engine,
+            module,
+            transformed: RefCell::new(BTreeMap::new()),
+        })
+    }
+
+    /// Runs the module's `transform` on `input` in a fresh instance, so
+    /// nothing carries over from one page to the next.
+    fn transform(&self, input: &[u8]) -> Result<String, String> {
+        let limits = StoreLimitsBuilder::new()
+            .memory_size(MEMORY_PER_PAGE)
+            .build();
+        let mut store = Store::new(&self.engine, limits);
+        store.limiter(|limits: &mut StoreLimits| limits);
+        store.set_fuel(FUEL_PER_PAGE).map_err(|e| e.to_string())?;
+        let instance = Linker::<StoreLimits>::new(&self.engine)
+            .instantiate_and_start(&mut store, &self.module)
+            .map_err(|e| e.to_string())?;
+
+        let memory = instance
+            .get_memory(&store, "memory")
+            .ok_or("no exported memory")?;
+        let alloc = instance
+            .get_typed_func::<i32, i32>(&store, "alloc")
+            .map_err(|e| e.to_string())?;
+        let transform = instance
+            .get_typed_func::<(i32, i32), i64>(&store, "transform")
+            .map_err(|e| e.to_string())?;
+
+        let len = i32::try_from(input.len()).map_err(|_| "page too large")?;
+        let ptr = alloc.call(&mut store, len).map_err(|e| e.to_string())?;
+        memory
+            .write(&mut store, ptr as u32 as usize, input)
+            .map_err(|e| e.to_string())?;
+
+        let result = transform
+            .call(&mut store, (ptr, len))
+            .map_err(|e| e.to_string())? as u64;
+        let mut output = vec![0; (result & 0xffff_ffff) as usize];
+        memory
+            .read(&store, (result >> 32) as usize, &mut output)
+            .map_err(|e| e.to_string())?;
+
+        String::from_utf8(output).map_err(|_| "output is not valid UTF-8".to_string())
+    }
+}
+
+impl Plugin for WasmPlugin {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn after_frontmatter(
+        &self,
+        path: &Path,
+        frontmatter: &mut Frontmatter,
+        markdown: &mut String,
+    ) -> io::Result<()> {
+        let input = TransformInput {
+            path: &path.to_string_lossy(),
+            title: frontmatter.title.as_deref().unwrap_or_default(),
+            date: frontmatter.date.as_deref().unwrap_or_default(),
+            tags: frontmatter.tags.as_deref().unwrap_or_default(),
+            content: markdown,
+        };
+        let input = serde_json::to_vec(&input).map_err(io::Error::other)?;
+        if let Some((previous, output)) = self.transformed.borrow().get(path)
+            && *previous == input
+        {
+            *markdown = output.clone();
+            return Ok(());
+        }
+
+        *markdown = self.transform(&input).map_err(|e| {
+            invalid_data(
+                Path::new(&self.name),
+                &format!("failed on {}: {}", path.display(), e),
+            )
+        })?;
+        self.transformed
+            .borrow_mut()
+            .insert(path.to_path_buf(), (input, markdown.clone()));
+        Ok(())
+    }
+}
+
+/// Loads every module listed in the `plugins.wasm` setting.
+pub fn load_plugins(paths: &[PathBuf]) -> io::Result<Vec<WasmPlugin>> {
+    paths.iter().map(|path| WasmPlugin::load(path)).collect()
+}
+
+fn invalid_data(path: &Path, message: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("{}: {}", path.display(), message),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::scratch_dir;
+
+    /// A module answering every page with `ok`, after running `work`.
+    fn plugin(name: &str, work: &str) -> WasmPlugin {
+        let path = scratch_dir(name).join("plugin.wat");
+        let wat = format!(
+            r#"(module
+                (memory (export "memory") 1)
+                (data (i32.const 16) "ok")
+                (func (export "alloc") (param i32) (result i32) (i32.const 1024))
+                (func (export "transform") (param i32 i32) (result i64)
+                    {}
+                    (i64.or (i64.shl (i64.const 16) (i64.const 32)) (i64.const 2))))"#,
+            work
+        );
+        std::fs::write(&path, wat).unwrap();
+        WasmPlugin::load(&path).unwrap()
+    }
+
+    fn run(plugin: &WasmPlugin, markdown: &str) -> io::Result<String> {
+        let mut markdown = markdown.to_string();
+        plugin.after_frontmatter(
+            Path::new("content/post.md"),
+            &mut Frontmatter::default(),
+            &mut markdown,
+        )?;
+        Ok(markdown)
+    }
+
+    #[test]
+    fn transform_replaces_the_markdown() {
+        assert_eq!(run(&plugin("wasm-ok", ""), "# Hi").unwrap(), "ok");
+    }
+
+    #[test]
+    fn memory_is_limited() {
+        let grow = "(if (i32.eq (memory.grow (i32.const 4096)) (i32.const -1)) (then unreachable))";
+        assert!(run(&plugin("wasm-grow", grow), "# Hi").is_err());
+    }
+
+    #[test]
+    fn each_page_is_transformed_once_for_the_same_input() {
+        let plugin = plugin("wasm-cache", "");
+        run(&plugin, "# Hi").unwrap();
+        run(&plugin, "# Hi").unwrap();
+        assert_eq!(plugin.transformed.borrow().len(), 1);
+
+        // A different input for the same page isn't answered from the cache
+        let (input, _) = plugin.transformed.borrow()[Path::new("content/post.md")].clone();
+        run(&plugin, "# Changed").unwrap();
+        assert_ne!(
+            plugin.transformed.borrow()[Path::new("content/post.md")].0,
+            input
+        );
+    }
+}
@@ -0,0 +1,86 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use crate::assets::find_files;
+
+/// How often watched files are checked for changes.
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// What was last seen of the watched files: each one's modification time.
+type Snapshot = BTreeMap<PathBuf, Option<SystemTime>>;
+
+/// Runs `build`, then runs it again whenever a file under the paths `watched`
+/// returns is added, removed or modified, until the process is stopped.
+/// Every build loads the config and templates afresh, so a failed one is
+/// reported and waited out: saving the fix is enough to get going again.
+pub fn run(
+    mut build: impl FnMut() -> io::Result<()>,
+    mut watched: impl FnMut() -> Vec<PathBuf>,
+) -> ! {
+    loop {
+        let seen = snapshot(&watched());
+        if let Err(e) = build() {
+            eprintln!("Error building site: {}", e);
+        }
+        println!("\n👀 Watching for changes, press Ctrl+C to stop...");
+        while snapshot(&watched()) == seen {
+            thread::sleep(POLL_INTERVAL);
+        }
+        println!("\n🔄 Change detected, rebuilding...\n");
+    }
+}
+
+/// The files under `paths`, each either a file or a directory to search;
+/// paths that don't exist are left out.
+fn snapshot(paths: &[PathBuf]) -> Snapshot {
+    let mut files = Snapshot::new();
+    for path in paths {
+        let found = if path.is_dir() {
+            find_files(path).unwrap_or_default()
+        } else {
+            vec![path.clone()]
+        };
+        for file in found {
+            if let Ok(metadata) = fs::metadata(&file) {
+                files.insert(file, metadata.modified().ok());
+            }
+        }
+    }
+    files
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::scratch_dir;
+
+    #[test]
+    fn snapshot_changes_with_added_modified_and_removed_files() {
+        let dir = scratch_dir("watch-snapshot");
+        let templates = dir.join("templates");
+        fs::create_dir_all(&templates).unwrap();
+        fs::write(templates.join("base.html"), "{title}").unwrap();
+        let watched = [templates.clone(), dir.join("bread.toml")];
+        let before = snapshot(&watched);
+        assert_eq!(before.len(), 1);
+
+        fs::write(dir.join("bread.toml"), "title = \"Site\"").unwrap();
+        let added = snapshot(&watched);
+        assert_ne!(added, before);
+
+        let page = fs::File::options()
+            .write(true)
+            .open(templates.join("base.html"))
+            .unwrap();
+        page.set_modified(SystemTime::UNIX_EPOCH).unwrap();
+        let modified = snapshot(&watched);
+        assert_ne!(modified, added);
+
+        fs::remove_file(dir.join("bread.toml")).unwrap();
+        assert_ne!(snapshot(&watched), modified);
+    }
+}
@@ -0,0 +1,172 @@
+use chrono::Duration;
+use serde_json::json;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::config::{Config, SecurityTxtConfig};
+use crate::{activitypub, publish};
+
+/// Writes the files `[well_known]` asks for: `.well-known/security.txt`,
+/// `humans.txt` and `.well-known/webfinger`.
+pub fn write_files(config: &Config, output_dir: &Path) -> io::Result<()> {
+    let settings = &config.well_known;
+    let well_known_dir = output_dir.join(".well-known");
+
+    if !settings.security.contact.is_empty() {
+        fs::create_dir_all(&well_known_dir)?;
+        fs::write(
+            well_known_dir.join("security.txt"),
+            security_txt(&settings.security, config),
+        )?;
+        println!("  🛡  Generated .well-known/security.txt");
+    }
+    if settings.humans {
+        fs::write(output_dir.join("humans.txt"), humans_txt(config))?;
+        println!("  🧑 Generated humans.txt");
+    }
+    if let Some(webfinger) = webfinger(config) {
+        fs::create_dir_all(&well_known_dir)?;
+        let json = serde_json::to_string_pretty(&webfinger).map_err(io::Error::other)?;
+        fs::write(well_known_dir.join("webfinger"), json)?;
+        println!("  👉 Generated .well-known/webfinger");
+    }
+    Ok(())
+}
+
+/// `security.txt` as RFC 9116 lays it out. Without a configured `expires`,
+/// it expires a year after the build, so a site that's rebuilt now and then
+/// keeps a valid one.
+fn security_txt(security: &SecurityTxtConfig, config: &Config) -> String {
+    let mut lines: Vec<String> = security
+        .contact
+        .iter()
+        .map(|contact| format!("Contact: {}", contact))
+        .collect();
+    let expires = if security.expires.is_empty() {
+        (publish::now() + Duration::days(365))
+            .format("%Y-%m-%dT%H:%M:%SZ")
+            .to_string()
+    } else {
+        security.expires.clone()
+    };
+    lines.push(format!("Expires: {}", expires));
+    for key in &security.encryption {
+        lines.push(format!("Encryption: {}", key));
+    }
+    let mut field = |name: &str, value: &str| {
+        if !value.is_empty() {
+            lines.push(format!("{}: {}", name, value));
+        }
+    };
+    field("Acknowledgments", &security.acknowledgments);
+    field("Policy", &security.policy);
+    field("Hiring", &security.hiring);
+    field(
+        "Preferred-Languages",
+        &security.preferred_languages.join(", "),
+    );
+    if config.base_url.contains("://") {
+        field(
+            "Canonical",
+            &config.absolute_url(".well-known/security.txt"),
+        );
+    }
+    lines.join("\n") + "\n"
+}
+
+/// `humans.txt`, crediting the authors in `[authors]` and describing the
+/// site, in the layout humanstxt.org suggests.
+fn humans_txt(config: &Config) -> String {
+    let mut text = String::from("/* TEAM */\n");
+    for (id, author) in &config.authors {
+        let name = if author.name.is_empty() {
+            id
+        } else {
+            &author.name
+        };
+        text.push_str(&format!("  Name: {}\n", name));
+        for (site, url) in &author.links {
+            text.push_str(&format!("  {}: {}\n", capitalized(site), url));
+        }
+        text.push('\n');
+    }
+
+    text.push_str("/* SITE */\n");
+    if !config.title.is_empty() {
+        text.push_str(&format!("  Title: {}\n", config.title));
+    }
+    text.push_str(&format!(
+        "  Last update: {}\n",
+        publish::now().format("%Y/%m/%d")
+    ));
+    if !config.languages.is_empty() {
+        text.push_str(&format!("  Language: {}\n", config.languages.join(", ")));
+    }
+    text.push_str("  Software: bread\n");
+    if !config.well_known.humans_note.is_empty() {
+        text.push_str(&format!("\n{}\n", config.well_known.humans_note.trim_end()));
+    }
+    text
+}
+
+fn capitalized(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/// The WebFinger document for the site's account, pointing at its profile
+/// page and, if there is one, its ActivityPub actor. With the ActivityPub
+/// export on, the account defaults to the export's actor, so the blog can
+/// be looked up as `@<username>@<domain>`.
+fn webfinger(config: &Config) -> Option<serde_json::Value> {
+    let settings = &config.well_known.webfinger;
+    let exported = config.activitypub.enabled && config.base_url.contains("://");
+    let subject = if !settings.subject.is_empty() {
+        settings.subject.clone()
+    } else if exported {
+        format!(
+            "acct:{}@{}",
+            config.activitypub.username,
+            host(&config.base_url)
+        )
+    } else {
+        return None;
+    };
+    let actor = if settings.actor.is_empty() && exported {
+        activitypub::actor_url(config)
+    } else {
+        settings.actor.clone()
+    };
+
+    let mut links = Vec::new();
+    if !settings.profile.is_empty() {
+        links.push(json!({
+            "rel": "http://webfinger.net/rel/profile-page",
+            "type": "text/html",
+            "href": settings.profile,
+        }));
+    }
+    if !actor.is_empty() {
+        links.push(json!({
+            "rel": "self",
+            "type": "application/activity+json",
+            "href": actor,
+        }));
+    }
+    Some(json!({
+        "subject": subject,
+        "aliases": settings.aliases,
+        "links": links,
+    }))
+}
+
+/// The host part of a full URL, like `example.com` in
+/// `https://example.com/blog/`.
+fn host(url: &str) -> &str {
+    let rest = url.split_once("://").map_or(url, |(_, rest)| rest);
+    rest.split('/').next().unwrap_or(rest)
+}
@@ -3,15 +3,18 @@ document.addEventListener('DOMContentLoaded', function() {
     const tagFilter = document.getElementById('tag-filter');
     const postsContainer = document.querySelector('.posts-container');
     const postItems = document.querySelectorAll('.post-item');
-    const tags = [...new Set(Array.from(document.querySelectorAll('.clickable-tag'))
-        .map(t => t.dataset.tag))];
+    // Tags by slug, shown by name
+    const tags = new Map(Array.from(document.querySelectorAll('.clickable-tag'))
+        .map(t => [t.dataset.tag, t.textContent]));
 
-    // Populate tag filter
+    // Populate tag filter with any tags it doesn't list yet
     const tagFilterSelect = document.getElementById('tag-filter');
-    tags.forEach(tag => {
+    const listed = new Set(Array.from(tagFilterSelect.options).map(o => o.value));
+    tags.forEach((name, tag) => {
+        if (listed.has(tag)) return;
         const option = document.createElement('option');
         option.value = tag;
-        option.textContent = '#' + tag;
+        option.textContent = name;
         tagFilterSelect.appendChild(option);
     });
 
@@ -20,7 +23,7 @@ document.addEventListener('DOMContentLoaded', function() {
         const selectedTag = tagFilter.value;
 
         postItems.forEach(item => {
-            const title = item.querySelector('h3 a').textContent.toLowerCase();
+            const title = item.querySelector('h2 a').textContent.toLowerCase();
             const tags = Array.from(item.querySelectorAll('.clickable-tag'))
             .map(t => t.dataset.tag);
 